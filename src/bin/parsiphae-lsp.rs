@@ -0,0 +1,19 @@
+extern crate parsiphae;
+
+use std::io;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+
+    let exitcode = match parsiphae::lsp::run(stdin.lock(), stdout.lock()) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    };
+
+    ::std::process::exit(exitcode);
+}
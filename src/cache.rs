@@ -0,0 +1,240 @@
+//! A persistent cache of the results for every file of a src, so that a re-run only parses the
+//! files that changed, and only analyses them again together with the files using their symbols.
+
+use cst::{self, TokenKind};
+use errors::Result;
+use inner_errors::{LinkerError, TypeError};
+use serde_json;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use types::{charset, with_deserialized_file, Declaration, FileId, PrintableByteVec, AST};
+
+/// Entries written by another version of Parsiphae are ignored, as the AST may have changed.
+const VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "/4");
+
+/// FNV-1a, which unlike the hasher of the standard library is the same in every run.
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Everything known about one file of the last run.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Entry {
+    version: String,
+    /// Hash of the content the entry was made from.
+    pub hash: u64,
+    pub ast: AST,
+    /// The lowercase names of the symbols the file declares, including class members.
+    pub exports: Vec<PrintableByteVec>,
+    /// Every lowercase word in the file, which is a superset of the names it uses.
+    pub imports: Vec<PrintableByteVec>,
+    /// The errors of the last analysis, unless the file was never analysed since it changed.
+    pub analysis: Option<FileAnalysis>,
+}
+
+/// The linker and type errors of a single file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileAnalysis {
+    pub link_errors: Vec<LinkerError>,
+    pub type_errors: Vec<TypeError>,
+}
+
+impl Entry {
+    /// An entry for a file that parsed to `ast` and has not been analysed yet.
+    pub fn new(content: &[u8], ast: AST) -> Self {
+        Entry {
+            version: VERSION.to_owned(),
+            hash: hash(content),
+            exports: exports(&ast),
+            imports: imports(content),
+            ast,
+            analysis: None,
+        }
+    }
+
+    /// Whether the file uses any of `names`, which must be lowercase.
+    pub fn imports_any(&self, names: &HashSet<&[u8]>) -> bool {
+        self.imports.iter().any(|name| names.contains(&name[..]))
+    }
+
+    /// Adds the exports of `previous`, the entry this one replaces before it was analysed, so
+    /// that the files using what the file used to declare are analysed again along with it.
+    pub fn keep_exports(&mut self, previous: &Entry) {
+        self.exports.extend(previous.exports.iter().cloned());
+        self.exports = names(self.exports.iter().map(|name| &name[..]));
+    }
+}
+
+/// The lowercase names of the symbols declared in `ast`, including class members.
+pub fn exports(ast: &AST) -> Vec<PrintableByteVec> {
+    let mut exports = Vec::new();
+    for decl in &ast.declarations {
+        match *decl {
+            Declaration::Var(ref vars) => exports.extend(vars.iter().map(|var| &var.name)),
+            Declaration::Func(ref func) => exports.push(&func.name),
+            Declaration::Class(ref class) => {
+                exports.push(&class.name);
+                exports.extend(class.members.iter().map(|member| &member.name));
+            }
+            Declaration::Inst(ref insts) => exports.extend(insts.iter().map(|inst| &inst.name)),
+            Declaration::Proto(ref proto) => exports.push(&proto.name),
+            Declaration::Const(ref decl) => exports.push(&decl.name),
+            Declaration::ConstArray(ref decl) => exports.push(&decl.name),
+        }
+    }
+    names(exports.into_iter().map(|name| name.as_bytes()))
+}
+
+/// Every lowercase word in `content`.
+fn imports(content: &[u8]) -> Vec<PrintableByteVec> {
+    names(
+        cst::tokenize(content)
+            .iter()
+            .filter(|token| token.kind == TokenKind::Word)
+            .map(|token| &token.text[..]),
+    )
+}
+
+/// The distinct lowercase `names`, sorted.
+fn names<'a, I: Iterator<Item = &'a [u8]>>(names: I) -> Vec<PrintableByteVec> {
+    let mut names: Vec<Vec<u8>> = names.map(|name| charset().to_lowercase(name)).collect();
+    names.sort();
+    names.dedup();
+    names.into_iter().map(PrintableByteVec).collect()
+}
+
+/// A directory with one entry per script, keyed by its path, plus the list of files of every
+/// src that was checked with it.
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+        Ok(Cache {
+            dir: dir.as_ref().to_owned(),
+        })
+    }
+
+    /// Removes all entries, so that the next run starts from scratch.
+    pub fn clear(&self) -> Result<()> {
+        for file in fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                fs::remove_file(path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The file for `path`. Names are folded depending on the charset, so each has its own.
+    fn path_of(&self, path: &Path, extension: &str) -> PathBuf {
+        let key = hash(format!("{}:{}", charset().name(), path.to_string_lossy()).as_bytes());
+        self.dir.join(format!("{:016x}.{}", key, extension))
+    }
+
+    /// The entry of the script at `path`, with all spans belonging to `file`. Missing, unreadable
+    /// and outdated entries are all treated as if the file was never seen.
+    pub fn load(&self, path: &Path, file: FileId) -> Option<Entry> {
+        let content = fs::read(self.path_of(path, "json")).ok()?;
+        let entry: Entry =
+            with_deserialized_file(file, || serde_json::from_slice(&content)).ok()?;
+        if entry.version == VERSION {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    pub fn store(&self, path: &Path, entry: &Entry) -> Result<()> {
+        let content = serde_json::to_vec(entry).map_err(io::Error::from)?;
+        write_atomically(&self.path_of(path, "json"), &content)
+    }
+
+    /// The files of `src` when it was last checked.
+    pub fn load_files(&self, src: &Path) -> Vec<PathBuf> {
+        fs::read(self.path_of(src, "src.json"))
+            .ok()
+            .and_then(|content| serde_json::from_slice(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn store_files(&self, src: &Path, files: &[PathBuf]) -> Result<()> {
+        let content = serde_json::to_vec(files).map_err(io::Error::from)?;
+        write_atomically(&self.path_of(src, "src.json"), &content)
+    }
+}
+
+/// Writes to a temporary file first, so that an interrupted run cannot leave a truncated entry.
+fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, content)?;
+    fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use types::{Identifier, Span};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            ::std::env::temp_dir().join(format!("parsiphae-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn round_trip() {
+        let content = b"class C { var int Hp; };\nfunc void f() { x = \"\xe4\"; };";
+        let (ast, _) = parse_recovering(content, FileId(0));
+        let mut entry = Entry::new(content, ast.clone());
+        entry.analysis = Some(FileAnalysis {
+            link_errors: vec![LinkerError::UndefinedSymbol(
+                Identifier::new(b"x").with_span(Span::new(FileId(0), 50, 51)),
+            )],
+            type_errors: Vec::new(),
+        });
+
+        let dir = temp_dir("cache");
+        let cache = Cache::open(&dir).unwrap();
+        cache.store(Path::new("a.d"), &entry).unwrap();
+        let loaded = cache.load(Path::new("a.d"), FileId(4)).unwrap();
+
+        assert_eq!(loaded.ast, ast);
+        assert_eq!(loaded.hash, hash(content));
+        let names = |names: &[PrintableByteVec]| -> Vec<Vec<u8>> {
+            names.iter().map(|name| name.0.clone()).collect()
+        };
+        assert_eq!(
+            names(&loaded.exports),
+            vec![b"c".to_vec(), b"f".to_vec(), b"hp".to_vec()]
+        );
+        assert!(loaded.imports_any(&[&b"x"[..]].iter().cloned().collect()));
+        let error = &loaded.analysis.unwrap().link_errors[0];
+        assert!(error.span().same_location(&Span::new(FileId(4), 50, 51)));
+        assert!(cache.load(Path::new("b.d"), FileId(0)).is_none());
+
+        cache
+            .store_files(Path::new("Gothic.src"), &[PathBuf::from("a.d")])
+            .unwrap();
+        assert_eq!(
+            cache.load_files(Path::new("Gothic.src")),
+            vec![PathBuf::from("a.d")]
+        );
+
+        cache.clear().unwrap();
+        assert!(cache.load(Path::new("a.d"), FileId(0)).is_none());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,57 @@
+use super::Compiler;
+use dat::{DataType, SymbolContent};
+use errors::Error;
+use inner_errors::ConstError;
+use ppa::constant::Value;
+use types::SymbolId;
+
+impl<'a> Compiler<'a> {
+    /// Initial values of the constant `id`, which has `count` elements of type `typ`.
+    /// Elements without initializer are left at their default value.
+    pub(super) fn const_content(
+        &mut self,
+        id: SymbolId,
+        typ: DataType,
+        count: u32,
+    ) -> SymbolContent {
+        let mut content = match typ {
+            DataType::Float => SymbolContent::Float(vec![0.0; count as usize]),
+            DataType::String => SymbolContent::String(vec![Vec::new(); count as usize]),
+            _ => SymbolContent::Int(vec![0; count as usize]),
+        };
+
+        let mut errors = Vec::new();
+        let initializers = self.constants.initializers(id).unwrap_or(&[]);
+        for (index, exp) in initializers.iter().enumerate().take(count as usize) {
+            let folded = match content {
+                SymbolContent::Float(ref mut values) => {
+                    self.constants.float(exp).map(|value| values[index] = value)
+                }
+                SymbolContent::String(ref mut values) => self
+                    .constants
+                    .string(exp)
+                    .map(|value| values[index] = value),
+                SymbolContent::Int(ref mut values) => {
+                    self.const_int(exp).map(|value| values[index] = value)
+                }
+                _ => Ok(()),
+            };
+            if let Err(err) = folded {
+                errors.push(err);
+            }
+        }
+
+        self.errors
+            .extend(errors.into_iter().map(Error::ConstError));
+        content
+    }
+
+    /// Value of an integer constant. Functions, instances, prototypes and classes are integers
+    /// as well, namely their symbol index.
+    pub(super) fn const_int(&self, exp: &::types::Expression) -> Result<i32, ConstError> {
+        match self.constants.evaluate(exp)? {
+            Value::Symbol(id) => Ok(self.index(id) as i32),
+            _ => self.constants.int(exp),
+        }
+    }
+}
@@ -0,0 +1,329 @@
+use super::{data_type, Compiler};
+use dat::{DataType, Instruction};
+use errors::Error;
+use inner_errors::CodegenError;
+use types::{
+    AssignmentOperator, BinaryOperator, Declaration, Expression, Function, IfStatement, Instance,
+    Prototype, Statement, SymbolKind, Type, UnaryOperator, VarAccess, AST,
+};
+
+impl<'a> Compiler<'a> {
+    /// Generates the code of all functions, instances and prototypes of `ast`.
+    pub(super) fn code(&mut self, ast: &AST) {
+        for decl in &ast.declarations {
+            match *decl {
+                Declaration::Func(ref func) => self.function(func),
+                Declaration::Inst(ref instances) => {
+                    for instance in instances {
+                        self.instance_code(instance);
+                    }
+                }
+                Declaration::Proto(ref proto) => self.prototype_code(proto),
+                _ => {}
+            }
+        }
+    }
+
+    /// Parameters are passed on the stack, so every function starts by popping them into
+    /// their symbols, last parameter first.
+    fn function(&mut self, func: &Function) {
+        let resolution = self.resolution;
+        let symbols = &resolution.symbols;
+        let id = match symbols.declaration(&func.name) {
+            Some(id) => id,
+            None => return,
+        };
+        self.set_address(id);
+
+        let params = symbols
+            .children(id)
+            .iter()
+            .filter(|param| symbols.get(**param).kind == SymbolKind::Param)
+            .rev();
+        for param in params {
+            let index = self.index(*param);
+            match data_type(&symbols.type_of(symbols.get(*param))) {
+                DataType::Instance => {
+                    self.emit(Instruction::PushInstance(index));
+                    self.emit(Instruction::MovVI);
+                }
+                typ => {
+                    self.emit(Instruction::PushVar(index));
+                    self.emit(assign_instruction(typ));
+                }
+            }
+        }
+
+        self.scope = Some(id);
+        self.statements(&func.body);
+        self.emit(Instruction::Return);
+    }
+
+    /// Instances first run the code of their prototype, if they have one.
+    fn instance_code(&mut self, instance: &Instance) {
+        let symbols = &self.resolution.symbols;
+        let id = match symbols.declaration(&instance.name) {
+            Some(id) => id,
+            None => return,
+        };
+        self.set_address(id);
+
+        let parent = symbols.lookup_global(instance.class.as_bytes());
+        if let Some(parent) = parent {
+            if symbols.get(parent).kind == SymbolKind::Prototype {
+                self.call(parent);
+            }
+        }
+
+        self.scope = Some(id);
+        self.statements(&instance.body);
+        self.emit(Instruction::Return);
+    }
+
+    fn prototype_code(&mut self, proto: &Prototype) {
+        let id = match self.resolution.symbols.declaration(&proto.name) {
+            Some(id) => id,
+            None => return,
+        };
+        self.set_address(id);
+
+        self.scope = Some(id);
+        self.statements(&proto.body);
+        self.emit(Instruction::Return);
+    }
+
+    fn statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match *statement {
+            Statement::Exp(ref exp) => self.expression(exp, &Type::Unknown),
+            Statement::Ass(ref ass) => {
+                let target = self.var_type(&ass.var);
+                self.expression(&ass.exp, &target);
+                self.push_target(&ass.var, &target);
+                self.emit(match ass.op {
+                    AssignmentOperator::Eq => assign_instruction(data_type(&target)),
+                    AssignmentOperator::PlusEq => Instruction::AddMovI,
+                    AssignmentOperator::MinusEq => Instruction::SubMovI,
+                    AssignmentOperator::MultiplyEq => Instruction::MulMovI,
+                    AssignmentOperator::DivideEq => Instruction::DivMovI,
+                });
+            }
+            Statement::If(ref if_statement) => self.if_statement(if_statement),
+            Statement::ReturnStatement(ref exp, _) => {
+                if let Some(ref exp) = *exp {
+                    let returns = self
+                        .scope
+                        .map(|func| {
+                            let symbols = &self.resolution.symbols;
+                            symbols.type_of(symbols.get(func))
+                        })
+                        .unwrap_or(Type::Unknown);
+                    self.expression(exp, &returns);
+                }
+                self.emit(Instruction::Return);
+            }
+            Statement::VarDeclarations(_)
+            | Statement::ConstDeclaration(_)
+            | Statement::ConstArrayDeclaration(_) => {}
+        }
+    }
+
+    fn if_statement(&mut self, if_statement: &IfStatement) {
+        let mut jumps_to_end = Vec::new();
+
+        for (i, branch) in if_statement.branches.iter().enumerate() {
+            self.expression(&branch.cond, &Type::Int);
+            let skip_branch = self.emit(Instruction::JumpIfZero(0));
+            self.statements(&branch.body);
+
+            let is_last = i + 1 == if_statement.branches.len();
+            if !is_last || if_statement.else_branch.is_some() {
+                jumps_to_end.push(self.emit(Instruction::Jump(0)));
+            }
+            self.patch_jump(skip_branch);
+        }
+
+        if let Some(ref else_branch) = if_statement.else_branch {
+            self.statements(else_branch);
+        }
+        for jump in jumps_to_end {
+            self.patch_jump(jump);
+        }
+    }
+
+    fn var_type(&self, var: &VarAccess) -> Type {
+        let symbols = &self.resolution.symbols;
+        match self.resolution.symbol_at(var.name.span) {
+            Some(id) => symbols.type_of(symbols.get(id)),
+            None => Type::Unknown,
+        }
+    }
+
+    /// Pushes the value of `exp`. `expected` is the type the value is used as, which decides
+    /// whether instances are pushed as such or as their symbol index.
+    fn expression(&mut self, exp: &Expression, expected: &Type) {
+        match *exp {
+            Expression::Int(value, _) if *expected == Type::Float => {
+                self.emit(Instruction::PushInt((value as f32).to_bits() as i32));
+            }
+            Expression::Int(value, _) => {
+                self.emit(Instruction::PushInt(value as i32));
+            }
+            Expression::Float(value, _) => {
+                self.emit(Instruction::PushInt(value.to_bits() as i32));
+            }
+            Expression::String(ref string) => {
+                let index = self.string_constant(string.as_bytes());
+                self.emit(Instruction::PushVar(index));
+            }
+            Expression::Identifier(ref var) => self.push_var(var, expected),
+            Expression::Binary(ref bin) => {
+                self.expression(&bin.right, &Type::Int);
+                self.expression(&bin.left, &Type::Int);
+                self.emit(binary_instruction(&bin.op));
+            }
+            Expression::Unary(ref un) => match un.right {
+                Expression::Float(value, _) if un.op == UnaryOperator::Minus => {
+                    self.emit(Instruction::PushInt((-value).to_bits() as i32));
+                }
+                _ => {
+                    self.expression(&un.right, &Type::Int);
+                    self.emit(match un.op {
+                        UnaryOperator::Plus => Instruction::Plus,
+                        UnaryOperator::Minus => Instruction::Negate,
+                        UnaryOperator::Negate => Instruction::Not,
+                        UnaryOperator::Flip => Instruction::Complement,
+                    });
+                }
+            },
+            Expression::Call(ref call) => {
+                let resolution = self.resolution;
+                let symbols = &resolution.symbols;
+                let func = resolution.symbol_at(call.func.span);
+                let params: Vec<Type> = func
+                    .map(|func| {
+                        symbols
+                            .children(func)
+                            .iter()
+                            .map(|param| symbols.get(*param))
+                            .filter(|param| param.kind == SymbolKind::Param)
+                            .map(|param| symbols.type_of(param))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                for (i, arg) in call.params.iter().enumerate() {
+                    self.expression(arg, params.get(i).unwrap_or(&Type::Unknown));
+                }
+                if let Some(func) = func {
+                    self.call(func);
+                }
+            }
+        }
+    }
+
+    /// Selects the instance of a member access like `self.name`.
+    fn set_instance(&mut self, var: &VarAccess) {
+        if let Some(ref instance) = var.instance {
+            if let Some(id) = self.resolution.symbol_at(instance.span) {
+                let index = self.index(id);
+                self.emit(Instruction::SetInstance(index));
+            }
+        }
+    }
+
+    fn push_var(&mut self, var: &VarAccess, expected: &Type) {
+        let id = match self.resolution.symbol_at(var.name.span) {
+            Some(id) => id,
+            None => return,
+        };
+        let index = self.index(id);
+        let symbol = self.resolution.symbols.get(id);
+
+        match symbol.kind {
+            SymbolKind::Func => {
+                self.emit(Instruction::PushInt(index as i32));
+            }
+            SymbolKind::Instance | SymbolKind::Prototype | SymbolKind::Class => {
+                match *expected {
+                    Type::Instance(_) => self.emit(Instruction::PushInstance(index)),
+                    _ => self.emit(Instruction::PushInt(index as i32)),
+                };
+            }
+            _ => {
+                let typ = self.var_type(var);
+                self.push_target(var, &typ);
+            }
+        }
+    }
+
+    /// Pushes a variable as the target of an assignment.
+    fn push_target(&mut self, var: &VarAccess, typ: &Type) {
+        let id = match self.resolution.symbol_at(var.name.span) {
+            Some(id) => id,
+            None => return,
+        };
+        let index = self.index(id);
+        self.set_instance(var);
+
+        match (var.index.as_ref(), typ) {
+            (None, &Type::Instance(_)) => {
+                self.emit(Instruction::PushInstance(index));
+            }
+            (None, _) => {
+                self.emit(Instruction::PushVar(index));
+            }
+            (Some(exp), _) => {
+                let element = match self.constants.int(exp) {
+                    Ok(element) if (0..256).contains(&element) => element as u8,
+                    Ok(_) => {
+                        self.error(CodegenError::IndexTooLarge(exp.span()));
+                        0
+                    }
+                    Err(err) => {
+                        self.errors.push(Error::ConstError(err));
+                        0
+                    }
+                };
+                self.emit(Instruction::PushArrayVar(index, element));
+            }
+        }
+    }
+}
+
+fn assign_instruction(typ: DataType) -> Instruction {
+    match typ {
+        DataType::Float => Instruction::MovF,
+        DataType::String => Instruction::MovS,
+        DataType::Func => Instruction::MovVF,
+        DataType::Instance => Instruction::MovVI,
+        _ => Instruction::MovI,
+    }
+}
+
+fn binary_instruction(op: &BinaryOperator) -> Instruction {
+    match *op {
+        BinaryOperator::Plus => Instruction::Add,
+        BinaryOperator::Minus => Instruction::Sub,
+        BinaryOperator::Multiply => Instruction::Mul,
+        BinaryOperator::Divide => Instruction::Div,
+        BinaryOperator::Mod => Instruction::Mod,
+        BinaryOperator::LSL => Instruction::Lsl,
+        BinaryOperator::LSR => Instruction::Lsr,
+        BinaryOperator::GT => Instruction::Gt,
+        BinaryOperator::LT => Instruction::Lt,
+        BinaryOperator::GE => Instruction::Ge,
+        BinaryOperator::LE => Instruction::Le,
+        BinaryOperator::Eq => Instruction::Eq,
+        BinaryOperator::NotEq => Instruction::NotEq,
+        BinaryOperator::And => Instruction::And,
+        BinaryOperator::BitAnd => Instruction::BitAnd,
+        BinaryOperator::Or => Instruction::Or,
+        BinaryOperator::BitOr => Instruction::BitOr,
+    }
+}
@@ -0,0 +1,579 @@
+//! Compiles resolved ASTs to the DAT format.
+//!
+//! Every symbol of the `SymbolCollection` becomes a DAT symbol with the same relative order,
+//! preceded by the `INSTANCE_HELP` symbol the original compiler always emits first. String
+//! literals become anonymous constants that are appended at the end.
+
+mod constant;
+mod emit;
+
+use dat::{flags, Dat, DatSymbol, DataType, Instruction, SymbolContent};
+use errors::Error;
+use inner_errors::CodegenError;
+use ppa::constant::Evaluator;
+use ppa::layout::{layout, ClassLayout, Target};
+use ppa::name_resolver::Resolution;
+use source_map::SourceMap;
+use types::{charset, Span, Symbol, SymbolId, SymbolKind, Type, AST};
+
+/// Compiles `asts`, given in `.src` order and free of errors, to a DAT file for `target`.
+pub fn compile<'a, I>(
+    asts: I,
+    resolution: &Resolution,
+    sources: &SourceMap,
+    target: Target,
+) -> Result<Dat, Vec<Error>>
+where
+    I: IntoIterator<Item = &'a AST>,
+    I::IntoIter: Clone,
+{
+    let asts = asts.into_iter();
+    let constants = Evaluator::new(asts.clone(), resolution);
+    let (layouts, errors) = layout(&resolution.symbols, &constants, target);
+    let mut compiler = Compiler::new(resolution, sources, constants, layouts);
+    compiler.errors = errors;
+
+    compiler.symbols();
+    for ast in asts {
+        compiler.code(ast);
+    }
+    compiler.link();
+
+    if compiler.errors.is_empty() {
+        Ok(compiler.dat)
+    } else {
+        Err(compiler.errors)
+    }
+}
+
+/// DAT type of values of type `typ`.
+fn data_type(typ: &Type) -> DataType {
+    match *typ {
+        Type::Void => DataType::Void,
+        Type::Float => DataType::Float,
+        Type::String => DataType::String,
+        Type::Func => DataType::Func,
+        Type::Instance(_) => DataType::Instance,
+        Type::Int | Type::Unknown => DataType::Int,
+    }
+}
+
+struct Compiler<'a> {
+    resolution: &'a Resolution,
+    sources: &'a SourceMap,
+    constants: Evaluator<'a>,
+    layouts: Vec<ClassLayout>,
+    dat: Dat,
+    /// Function, instance or prototype whose code is being generated.
+    scope: Option<SymbolId>,
+    /// Code positions of calls, whose target addresses are only known at the end.
+    calls: Vec<(usize, SymbolId)>,
+    errors: Vec<Error>,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(
+        resolution: &'a Resolution,
+        sources: &'a SourceMap,
+        constants: Evaluator<'a>,
+        layouts: Vec<ClassLayout>,
+    ) -> Self {
+        Compiler {
+            resolution,
+            sources,
+            constants,
+            layouts,
+            dat: Dat::new(),
+            scope: None,
+            calls: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn error(&mut self, err: CodegenError) {
+        self.errors.push(Error::CodegenError(err));
+    }
+
+    /// Index of `id` in the DAT symbol table.
+    fn index(&self, id: SymbolId) -> u32 {
+        id.0 as u32 + 1
+    }
+
+    /// DAT name of a symbol: upper case, and qualified with its scope for locals and members.
+    fn dat_name(&self, symbol: &Symbol) -> Vec<u8> {
+        let mut name = Vec::new();
+        if let Some(scope) = symbol.scope {
+            name.extend_from_slice(self.resolution.symbols.get(scope).name.as_bytes());
+            name.push(b'.');
+        }
+        name.extend_from_slice(symbol.name.as_bytes());
+        charset().to_uppercase(&name)
+    }
+
+    /// Number of elements of a variable or constant, if its size is a positive constant.
+    fn array_size(&self, symbol: &Symbol) -> Option<u32> {
+        match symbol.array_size {
+            None => Some(1),
+            Some(ref size) => match self.constants.array_size(size) {
+                Ok(size) if size > 0 => Some(size as u32),
+                _ => None,
+            },
+        }
+    }
+
+    fn layout(&self, class: SymbolId) -> &ClassLayout {
+        self.layouts
+            .iter()
+            .find(|layout| layout.class == class)
+            .unwrap()
+    }
+
+    fn locate(&self, dat_symbol: &mut DatSymbol, span: Span) {
+        if let Some(file) = self.sources.get(span.file) {
+            let start = file.line_col(span.start);
+            let end = file.line_col(span.end);
+            dat_symbol.file_index = span.file.0 as u32;
+            dat_symbol.line_start = start.line as u32;
+            dat_symbol.line_count = (end.line - start.line + 1) as u32;
+            dat_symbol.char_start = span.start as u32;
+            dat_symbol.char_count = span.len() as u32;
+        }
+    }
+
+    /// Creates the DAT symbol of every symbol in the collection.
+    fn symbols(&mut self) {
+        let mut help = DatSymbol::new(b"\xFFINSTANCE_HELP", DataType::Instance);
+        help.content = SymbolContent::Address(0);
+        self.dat.symbols.push(help);
+
+        let resolution = self.resolution;
+        let symbols = &resolution.symbols;
+        for (id, symbol) in symbols.iter() {
+            let dat_symbol = match symbol.kind {
+                SymbolKind::Class => self.class(id, symbol),
+                SymbolKind::Func => self.func(id, symbol),
+                SymbolKind::Prototype | SymbolKind::Instance => self.instance(symbol),
+                SymbolKind::Var
+                | SymbolKind::Param
+                | SymbolKind::Const
+                | SymbolKind::ConstArray => {
+                    let class = symbol
+                        .scope
+                        .filter(|scope| symbols.get(*scope).kind == SymbolKind::Class);
+                    let mut dat_symbol = self.variable(id, symbol);
+                    if let Some(class) = class {
+                        dat_symbol.flags |= flags::CLASS_VAR;
+                        dat_symbol.offset = self.layout(class).field(id).unwrap().offset;
+                        dat_symbol.content = SymbolContent::None;
+                        dat_symbol.parent = self.index(class) as i32;
+                    }
+                    dat_symbol
+                }
+            };
+
+            self.dat.symbols.push(dat_symbol);
+        }
+    }
+
+    fn class(&self, id: SymbolId, symbol: &Symbol) -> DatSymbol {
+        let layout = self.layout(id);
+
+        let mut dat_symbol = DatSymbol::new(&self.dat_name(symbol), DataType::Class);
+        dat_symbol.count = layout.fields.len() as u32;
+        dat_symbol.offset = layout.size;
+        dat_symbol.content = SymbolContent::ClassOffset(layout.offset as i32);
+        self.locate(&mut dat_symbol, symbol.span);
+        dat_symbol
+    }
+
+    fn func(&self, id: SymbolId, symbol: &Symbol) -> DatSymbol {
+        let symbols = &self.resolution.symbols;
+        let return_type = data_type(&symbols.type_of(symbol));
+
+        let mut dat_symbol = DatSymbol::new(&self.dat_name(symbol), DataType::Func);
+        dat_symbol.count = symbols
+            .children(id)
+            .iter()
+            .filter(|param| symbols.get(**param).kind == SymbolKind::Param)
+            .count() as u32;
+        dat_symbol.offset = return_type as u32;
+        dat_symbol.flags = flags::CONST;
+        if return_type != DataType::Void {
+            dat_symbol.flags |= flags::RETURN;
+        }
+        if symbol.external {
+            dat_symbol.flags |= flags::EXTERNAL;
+        }
+        dat_symbol.content = SymbolContent::Address(0);
+        self.locate(&mut dat_symbol, symbol.span);
+        dat_symbol
+    }
+
+    fn instance(&self, symbol: &Symbol) -> DatSymbol {
+        let symbols = &self.resolution.symbols;
+        let typ = match symbol.kind {
+            SymbolKind::Prototype => DataType::Prototype,
+            _ => DataType::Instance,
+        };
+
+        let mut dat_symbol = DatSymbol::new(&self.dat_name(symbol), typ);
+        dat_symbol.flags = flags::CONST;
+        dat_symbol.content = SymbolContent::Address(0);
+        let parent = match symbol.kind {
+            SymbolKind::Prototype => symbol
+                .typ
+                .as_ref()
+                .and_then(|class| symbols.class_of(class.as_bytes())),
+            _ => symbol
+                .typ
+                .as_ref()
+                .and_then(|parent| symbols.lookup_global(parent.as_bytes())),
+        };
+        if let Some(parent) = parent {
+            dat_symbol.parent = self.index(parent) as i32;
+        }
+        self.locate(&mut dat_symbol, symbol.span);
+        dat_symbol
+    }
+
+    /// Variables of instance type refer to their class as parent.
+    fn variable(&mut self, id: SymbolId, symbol: &Symbol) -> DatSymbol {
+        let symbols = &self.resolution.symbols;
+        let typ = data_type(&symbols.type_of(symbol));
+        let count = match (self.array_size(symbol), symbol.array_size.as_ref()) {
+            (Some(count), _) => count,
+            (None, size) => {
+                let span = size.map(|size| size.span()).unwrap_or(symbol.span);
+                self.error(CodegenError::NotConstant(span));
+                1
+            }
+        };
+
+        let mut dat_symbol = DatSymbol::new(&self.dat_name(symbol), typ);
+        dat_symbol.count = count;
+        if let Type::Instance(Some(ref class)) = symbols.type_of(symbol) {
+            if let Some(class) = symbols.class_of(class.as_bytes()) {
+                dat_symbol.parent = self.index(class) as i32;
+            }
+        }
+        dat_symbol.content = match symbol.kind {
+            SymbolKind::Const | SymbolKind::ConstArray => {
+                dat_symbol.flags = flags::CONST;
+                self.const_content(id, typ, count)
+            }
+            _ => match typ {
+                DataType::Float => SymbolContent::Float(vec![0.0; count as usize]),
+                DataType::String => SymbolContent::String(vec![Vec::new(); count as usize]),
+                DataType::Func | DataType::Instance => SymbolContent::Address(0),
+                _ => SymbolContent::Int(vec![0; count as usize]),
+            },
+        };
+        self.locate(&mut dat_symbol, symbol.span);
+        dat_symbol
+    }
+
+    /// Appends an anonymous string constant for a literal and returns its index.
+    fn string_constant(&mut self, value: &[u8]) -> u32 {
+        let index = self.dat.symbols.len() as u32;
+        let literals = index as usize - self.resolution.symbols.len() - 1;
+
+        let mut name = b"\xFF".to_vec();
+        name.extend_from_slice(format!("{}", 10000 + literals).as_bytes());
+        let mut dat_symbol = DatSymbol::new(&name, DataType::String);
+        dat_symbol.count = 1;
+        dat_symbol.flags = flags::CONST;
+        dat_symbol.content = SymbolContent::String(vec![value.to_vec()]);
+        self.dat.symbols.push(dat_symbol);
+
+        index
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        let position = self.dat.code.len();
+        instruction.encode(&mut self.dat.code);
+        position
+    }
+
+    /// Points the symbol `id` to the next instruction.
+    fn set_address(&mut self, id: SymbolId) {
+        let address = self.dat.code.len() as i32;
+        let index = self.index(id) as usize;
+        self.dat.symbols[index].content = SymbolContent::Address(address);
+    }
+
+    /// Emits a call of `id`, whose address is filled in by `link`.
+    /// Externals are called by their index, everything else by its address.
+    fn call(&mut self, id: SymbolId) {
+        if self.resolution.symbols.get(id).external {
+            let index = self.index(id);
+            self.emit(Instruction::CallExternal(index));
+        } else {
+            let position = self.emit(Instruction::Call(0));
+            self.calls.push((position, id));
+        }
+    }
+
+    /// Sets the target of the jump at `position` to the next instruction.
+    fn patch_jump(&mut self, position: usize) {
+        let target = self.dat.code.len() as u32;
+        self.dat.code[position + 1..position + 5].copy_from_slice(&target.to_le_bytes());
+    }
+
+    fn link(&mut self) {
+        for (position, id) in ::std::mem::take(&mut self.calls) {
+            let index = self.index(id) as usize;
+            if let SymbolContent::Address(address) = self.dat.symbols[index].content {
+                self.dat.code[position + 1..position + 5].copy_from_slice(&address.to_le_bytes());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::{parse_externals, parse_recovering};
+    use ppa::name_resolver::{resolve, resolve_files};
+
+    fn compile_source(content: &str) -> Dat {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.d", content.as_bytes().to_vec());
+        let (ast, errors) = parse_recovering(content.as_bytes(), file);
+        assert!(errors.is_empty());
+        let resolution = resolve(Some(&ast));
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+        compile(Some(&ast), &resolution, &sources, Target::default()).unwrap()
+    }
+
+    fn symbol<'a>(dat: &'a Dat, name: &[u8]) -> (u32, &'a DatSymbol) {
+        dat.symbols
+            .iter()
+            .enumerate()
+            .find(|&(_, symbol)| symbol.name == name)
+            .map(|(i, symbol)| (i as u32, symbol))
+            .unwrap_or_else(|| panic!("no symbol {:?}", name))
+    }
+
+    fn code(instructions: &[Instruction]) -> Vec<u8> {
+        let mut code = Vec::new();
+        for instruction in instructions {
+            instruction.encode(&mut code);
+        }
+        code
+    }
+
+    #[test]
+    fn symbol_table() {
+        let dat = compile_source(
+            "class C_NPC { var int id; var string name[2]; var int flags; };
+prototype Npc_Default(C_NPC) {};
+instance Hero(Npc_Default) {};
+var C_NPC self;
+func int Add(var int a, var int b) { var string s; return a; };",
+        );
+
+        assert_eq!(dat.symbols[0].name, b"\xFFINSTANCE_HELP".to_vec());
+
+        let (class, c_npc) = symbol(&dat, b"C_NPC");
+        assert_eq!(
+            (c_npc.typ, c_npc.count, c_npc.offset),
+            (DataType::Class, 3, 48)
+        );
+        let (_, name) = symbol(&dat, b"C_NPC.NAME");
+        assert_eq!(
+            (name.typ, name.count, name.offset),
+            (DataType::String, 2, 4)
+        );
+        assert_eq!(name.flags, flags::CLASS_VAR);
+        assert_eq!(name.parent, class as i32);
+        assert_eq!(symbol(&dat, b"C_NPC.FLAGS").1.offset, 44);
+
+        let (proto, npc_default) = symbol(&dat, b"NPC_DEFAULT");
+        assert_eq!(npc_default.parent, class as i32);
+        assert_eq!(symbol(&dat, b"HERO").1.parent, proto as i32);
+        let self_ = symbol(&dat, b"SELF").1;
+        assert_eq!(
+            (self_.typ, self_.parent),
+            (DataType::Instance, class as i32)
+        );
+
+        let (_, add) = symbol(&dat, b"ADD");
+        assert_eq!((add.count, add.offset), (2, DataType::Int as u32));
+        assert_eq!(add.flags, flags::CONST | flags::RETURN);
+        assert_eq!(symbol(&dat, b"ADD.B").1.typ, DataType::Int);
+        assert_eq!(symbol(&dat, b"ADD.S").1.typ, DataType::String);
+        assert_eq!(add.line_start, 5);
+    }
+
+    #[test]
+    fn constants() {
+        let dat = compile_source(
+            "const int A = 2 * 3;
+const int SIZE = A - 4;
+const int ARR[SIZE] = { A, -A + 1 };
+const string S = \"x\";
+const float F = -1.5;
+func void foo() {};
+const func G = foo;
+var int v[SIZE];",
+        );
+
+        assert_eq!(symbol(&dat, b"A").1.content, SymbolContent::Int(vec![6]));
+        assert_eq!(
+            symbol(&dat, b"ARR").1.content,
+            SymbolContent::Int(vec![6, -5])
+        );
+        assert_eq!(symbol(&dat, b"ARR").1.count, 2);
+        assert_eq!(
+            symbol(&dat, b"S").1.content,
+            SymbolContent::String(vec![b"x".to_vec()])
+        );
+        assert_eq!(
+            symbol(&dat, b"F").1.content,
+            SymbolContent::Float(vec![-1.5])
+        );
+        let (foo, _) = symbol(&dat, b"FOO");
+        assert_eq!(
+            symbol(&dat, b"G").1.content,
+            SymbolContent::Int(vec![foo as i32])
+        );
+        assert_eq!(symbol(&dat, b"V").1.content, SymbolContent::Int(vec![0, 0]));
+    }
+
+    #[test]
+    fn function_code() {
+        let dat = compile_source(
+            "func int Add(var int a, var int b) { return a - b * 2; };
+func void foo() { var string s; s = \"hi\"; if (Add(1, 2)) { s = \"a\"; } else { return; }; };",
+        );
+        let (a, _) = symbol(&dat, b"ADD.A");
+        let (b, _) = symbol(&dat, b"ADD.B");
+        let (s, _) = symbol(&dat, b"FOO.S");
+        let (hi, _) = symbol(&dat, b"\xFF10000");
+        let (lit_a, _) = symbol(&dat, b"\xFF10001");
+
+        use dat::Instruction::*;
+        let add = code(&[
+            PushVar(b),
+            MovI,
+            PushVar(a),
+            MovI,
+            PushInt(2),
+            PushVar(b),
+            Mul,
+            PushVar(a),
+            Sub,
+            Return,
+            Return,
+        ]);
+        let start = add.len() as u32;
+        let foo = code(&[
+            PushVar(hi),
+            PushVar(s),
+            MovS,
+            PushInt(1),
+            PushInt(2),
+            Call(0),
+            JumpIfZero(start + 47),
+            PushVar(lit_a),
+            PushVar(s),
+            MovS,
+            Jump(start + 48),
+            Return,
+            Return,
+        ]);
+
+        assert_eq!(
+            symbol(&dat, b"FOO").1.content,
+            SymbolContent::Address(start as i32)
+        );
+        assert_eq!(dat.code, [add, foo].concat());
+    }
+
+    #[test]
+    fn instance_code() {
+        let dat = compile_source(
+            "class C_NPC { var int id; var func routine; };
+prototype Npc_Default(C_NPC) { id = 1; };
+var C_NPC self;
+func void Rtn() {};
+instance Hero(Npc_Default) { routine = Rtn; self.id = Hero; };",
+        );
+        let (id, _) = symbol(&dat, b"C_NPC.ID");
+        let (routine, _) = symbol(&dat, b"C_NPC.ROUTINE");
+        let (rtn, _) = symbol(&dat, b"RTN");
+        let (self_, _) = symbol(&dat, b"SELF");
+        let (hero, _) = symbol(&dat, b"HERO");
+
+        use dat::Instruction::*;
+        let proto = code(&[PushInt(1), PushVar(id), MovI, Return]);
+        let rtn_code = code(&[Return]);
+        let hero_code = code(&[
+            Call(0),
+            PushInt(rtn as i32),
+            PushVar(routine),
+            MovVF,
+            PushInt(hero as i32),
+            SetInstance(self_),
+            PushVar(id),
+            MovI,
+            Return,
+        ]);
+
+        assert_eq!(dat.code, [proto, rtn_code, hero_code].concat());
+    }
+
+    #[test]
+    fn externals() {
+        let mut sources = SourceMap::new();
+        let externals = sources.add(
+            "externals.d",
+            b"func int Hlp_Random(var int bound);".to_vec(),
+        );
+        let (externals, _) = parse_externals(&sources.get(externals).unwrap().content, externals);
+        let content = "func int Roll() { return Hlp_Random(6); };";
+        let file = sources.add("test.d", content.as_bytes().to_vec());
+        let (ast, _) = parse_recovering(content.as_bytes(), file);
+        let resolution = resolve_files(&externals, Some(&ast), |_| true);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        let dat = compile(Some(&ast), &resolution, &sources, Target::default()).unwrap();
+
+        let (random, symbol) = symbol(&dat, b"HLP_RANDOM");
+        assert_eq!(random, 1);
+        assert_eq!(symbol.flags, flags::CONST | flags::RETURN | flags::EXTERNAL);
+        use dat::Instruction::*;
+        assert_eq!(
+            dat.code,
+            code(&[PushInt(6), CallExternal(random), Return, Return])
+        );
+    }
+
+    #[test]
+    fn array_indices() {
+        let content = "var int a[300]; func void f() { a[255] = a[256]; a[-1] = 1; };";
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.d", content.as_bytes().to_vec());
+        let (ast, _) = parse_recovering(content.as_bytes(), file);
+        let resolution = resolve(Some(&ast));
+
+        let errors = compile(Some(&ast), &resolution, &sources, Target::default()).unwrap_err();
+        let spans: Vec<(&str, &[u8])> = errors
+            .iter()
+            .map(|err| match *err {
+                Error::CodegenError(ref err) => (
+                    err.code(),
+                    &content.as_bytes()[err.span().start..err.span().end],
+                ),
+                ref other => panic!("expected a codegen error, found {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("index-too-large", &b"256"[..]),
+                ("index-too-large", &b"-1"[..])
+            ]
+        );
+    }
+}
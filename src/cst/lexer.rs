@@ -0,0 +1,177 @@
+use cst::{Token, TokenKind, Trivia, TriviaKind};
+use parsers::{IDENTIFIER_BEGIN, IDENTIFIER_END};
+
+/// Operators of two characters, which take precedence over their first character alone.
+const TWO_CHAR_PUNCTS: [&[u8]; 12] = [
+    b"<<", b">>", b"<=", b">=", b"==", b"!=", b"&&", b"||", b"+=", b"-=", b"*=", b"/=",
+];
+
+/// Splits `content` into tokens. Whitespace and comments are attached to the following token,
+/// the trivia at the end of the file to the final `Eof` token. Concatenating everything
+/// yields `content` again.
+pub fn tokenize(content: &[u8]) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let mut leading = Vec::new();
+        while let Some((kind, len)) = trivia(&content[pos..]) {
+            leading.push(Trivia {
+                kind,
+                text: content[pos..pos + len].to_vec(),
+            });
+            pos += len;
+        }
+
+        let (kind, len) = match token(&content[pos..]) {
+            Some(token) => token,
+            None => (TokenKind::Eof, 0),
+        };
+        tokens.push(Token {
+            kind,
+            text: content[pos..pos + len].to_vec(),
+            leading,
+            offset: pos,
+        });
+        pos += len;
+
+        if kind == TokenKind::Eof {
+            return tokens;
+        }
+    }
+}
+
+fn trivia(input: &[u8]) -> Option<(TriviaKind, usize)> {
+    match input {
+        [b' ', ..] | [b'\t', ..] | [b'\r', ..] | [b'\n', ..] | [b'\x0c', ..] => {
+            let len = input
+                .iter()
+                .take_while(|b| b" \t\r\n\x0c".contains(b))
+                .count();
+            Some((TriviaKind::Whitespace, len))
+        }
+        [b'/', b'/', ..] => {
+            let len = input
+                .iter()
+                .position(|b| *b == b'\n')
+                .unwrap_or(input.len());
+            Some((TriviaKind::LineComment, len))
+        }
+        [b'/', b'*', ..] => {
+            let len = input[2..]
+                .windows(2)
+                .position(|w| w == b"*/")
+                .map_or(input.len(), |end| end + 4);
+            Some((TriviaKind::BlockComment, len))
+        }
+        _ => None,
+    }
+}
+
+fn token(input: &[u8]) -> Option<(TokenKind, usize)> {
+    let first = *input.first()?;
+
+    if IDENTIFIER_BEGIN.contains(&first) {
+        let len = 1 + input[1..]
+            .iter()
+            .take_while(|b| IDENTIFIER_END.contains(b))
+            .count();
+        if !input[..len].iter().all(u8::is_ascii_digit) {
+            return Some((TokenKind::Word, len));
+        }
+
+        let fraction = match input.get(len) {
+            Some(b'.') => input[len + 1..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count(),
+            _ => 0,
+        };
+        return Some(match fraction {
+            0 => (TokenKind::Int, len),
+            _ => (TokenKind::Float, len + 1 + fraction),
+        });
+    }
+
+    if first == b'"' {
+        return Some(match input[1..].iter().position(|b| *b == b'"') {
+            Some(end) => (TokenKind::String, end + 2),
+            None => (TokenKind::Unknown, input.len()),
+        });
+    }
+
+    if TWO_CHAR_PUNCTS.iter().any(|punct| input.starts_with(punct)) {
+        return Some((TokenKind::Punct, 2));
+    }
+    if b"(){}[];,.=+-*/%<>!~&|".contains(&first) {
+        return Some((TokenKind::Punct, 1));
+    }
+
+    Some((TokenKind::Unknown, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(content: &[u8]) -> Vec<(TokenKind, &[u8])> {
+        let tokens = tokenize(content);
+        let kinds = tokens.iter().map(|token| token.kind).collect::<Vec<_>>();
+        let mut pos = 0;
+        let mut texts = Vec::new();
+        for token in &tokens {
+            pos += token.leading.iter().map(|t| t.text.len()).sum::<usize>();
+            assert_eq!(token.offset, pos);
+            texts.push(&content[pos..pos + token.text.len()]);
+            pos += token.text.len();
+        }
+        assert_eq!(pos, content.len());
+        kinds.into_iter().zip(texts).collect()
+    }
+
+    #[test]
+    fn tokens() {
+        assert_eq!(
+            kinds(b"a1 += 12.5 // c\n/* d */ 3a \"s\"<<"),
+            vec![
+                (TokenKind::Word, &b"a1"[..]),
+                (TokenKind::Punct, b"+="),
+                (TokenKind::Float, b"12.5"),
+                (TokenKind::Word, b"3a"),
+                (TokenKind::String, b"\"s\""),
+                (TokenKind::Punct, b"<<"),
+                (TokenKind::Eof, b""),
+            ]
+        );
+    }
+
+    #[test]
+    fn trivia_is_kept() {
+        let tokens = tokenize(b"x // c\r\n/* d */ ");
+        let kinds: Vec<TriviaKind> = tokens[1].leading.iter().map(|t| t.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                TriviaKind::Whitespace,
+                TriviaKind::LineComment,
+                TriviaKind::Whitespace,
+                TriviaKind::BlockComment,
+                TriviaKind::Whitespace,
+            ]
+        );
+        assert_eq!(tokens[1].leading[1].text, b"// c\r".to_vec());
+    }
+
+    #[test]
+    fn unterminated() {
+        assert_eq!(
+            kinds(b"a \"b /* c"),
+            vec![
+                (TokenKind::Word, &b"a"[..]),
+                (TokenKind::Unknown, b"\"b /* c"),
+                (TokenKind::Eof, b""),
+            ]
+        );
+        assert_eq!(kinds(b"/* c")[0], (TokenKind::Eof, &b""[..]));
+    }
+}
@@ -0,0 +1,412 @@
+use cst::{Node, NodeKind, Token, TokenKind};
+use types::{
+    ArraySizeDeclaration, Assignment, AssignmentOperator, BinaryExpression, BinaryOperator, Call,
+    Class, ConstArrayDeclaration, ConstArrayInitializer, ConstDeclaration, Declaration, Expression,
+    FileId, Function, Identifier, IfBranch, IfStatement, Instance, Prototype, Span, Statement,
+    StringLiteral, UnaryExpression, UnaryOperator, VarAccess, VarDeclaration, AST,
+};
+
+/// Derives the `AST` from a tree built by `parse`. `Error` nodes are skipped, so the result
+/// matches what `parsers::parse_recovering` returns for the same file, spans included.
+/// Declarations whose nodes are not shaped like `parse` builds them, e.g. after editing the
+/// tree, are skipped as well.
+pub fn lower(root: &Node, file: FileId) -> AST {
+    let lowering = Lowering { file };
+    let declarations = root
+        .nodes()
+        .filter(|node| node.kind != NodeKind::Error)
+        .filter_map(|node| lowering.declaration(node))
+        .collect();
+
+    AST { declarations }
+}
+
+/// Every method returns `None` for a malformed node.
+struct Lowering {
+    file: FileId,
+}
+
+impl Lowering {
+    fn span(&self, node: &Node) -> Span {
+        node.span(self.file)
+    }
+
+    fn identifier(&self, token: &Token) -> Identifier {
+        Identifier::new(&token.text).with_span(token.span(self.file))
+    }
+
+    /// The identifiers among the direct children, in order.
+    fn words<'a>(&self, node: &'a Node) -> Vec<&'a Token> {
+        node.child_tokens()
+            .filter(|token| token.kind == TokenKind::Word)
+            .collect()
+    }
+
+    /// The identifier that is the `i`th word among the direct children.
+    fn word(&self, node: &Node, i: usize) -> Option<Identifier> {
+        self.words(node).get(i).map(|word| self.identifier(word))
+    }
+
+    fn operator<'a>(&self, node: &'a Node) -> Option<&'a Token> {
+        node.child_tokens()
+            .find(|token| token.kind == TokenKind::Punct)
+    }
+
+    fn declaration(&self, node: &Node) -> Option<Declaration> {
+        match node.kind {
+            NodeKind::VarList => self.var_list(node).map(Declaration::Var),
+            NodeKind::Const => match self.constant(node)? {
+                Statement::ConstDeclaration(decl) => Some(Declaration::Const(decl)),
+                Statement::ConstArrayDeclaration(decl) => Some(Declaration::ConstArray(decl)),
+                _ => None,
+            },
+            NodeKind::Func => self.function(node),
+            NodeKind::Class => self.class(node),
+            NodeKind::Prototype => self.prototype(node),
+            NodeKind::Instance => self.instance(node),
+            _ => None,
+        }
+    }
+
+    fn var_list(&self, node: &Node) -> Option<Vec<VarDeclaration>> {
+        let mut decls = Vec::new();
+        for group in node.nodes() {
+            decls.extend(self.var_group(group)?);
+        }
+        Some(decls)
+    }
+
+    /// The first variable spans `var int a`, like in the parser.
+    fn var_group(&self, node: &Node) -> Option<Vec<VarDeclaration>> {
+        let words = self.words(node);
+        let keyword = *words.first()?;
+        let typ = self.identifier(words.get(1)?);
+
+        node.nodes()
+            .enumerate()
+            .map(|(i, declarator)| {
+                let name = self.word(declarator, 0)?;
+                let array_size = match declarator.nodes().next() {
+                    Some(size) => Some(self.array_size(size)?),
+                    None => None,
+                };
+                let span = match i {
+                    0 => keyword.span(self.file).to(self.span(declarator)),
+                    _ => self.span(declarator),
+                };
+                Some(VarDeclaration::new(typ.clone(), name, array_size).with_span(span))
+            })
+            .collect()
+    }
+
+    fn array_size(&self, node: &Node) -> Option<ArraySizeDeclaration> {
+        let size = node.child_tokens().nth(1)?;
+        match size.kind {
+            TokenKind::Int => Some(ArraySizeDeclaration::Size(
+                self.int(size)?,
+                size.span(self.file),
+            )),
+            TokenKind::Word => Some(ArraySizeDeclaration::Identifier(self.identifier(size))),
+            _ => None,
+        }
+    }
+
+    /// A constant or constant array, as a statement since both can appear inside functions.
+    fn constant(&self, node: &Node) -> Option<Statement> {
+        let (typ, name) = (self.word(node, 1)?, self.word(node, 2)?);
+        let mut children = node.nodes();
+        let first = children.next()?;
+
+        if first.kind == NodeKind::ArraySize {
+            let init = children.next()?;
+            let expressions = init
+                .nodes()
+                .map(|exp| self.expression(exp))
+                .collect::<Option<_>>()?;
+            let initializer = ConstArrayInitializer::new(expressions).with_span(self.span(init));
+            Some(Statement::ConstArrayDeclaration(
+                ConstArrayDeclaration::new(typ, name, self.array_size(first)?, initializer)
+                    .with_span(self.span(node)),
+            ))
+        } else {
+            Some(Statement::ConstDeclaration(
+                ConstDeclaration::new(typ, name, self.expression(first)?)
+                    .with_span(self.span(node)),
+            ))
+        }
+    }
+
+    fn function(&self, node: &Node) -> Option<Declaration> {
+        let mut children = node.nodes();
+        let params = children.next()?;
+        let body = children.next()?;
+
+        Some(Declaration::Func(Function {
+            typ: self.word(node, 1)?,
+            name: self.word(node, 2)?,
+            params: self.var_list(params)?,
+            body: self.block(body)?,
+            span: self.span(node),
+        }))
+    }
+
+    fn class(&self, node: &Node) -> Option<Declaration> {
+        let mut members = Vec::new();
+        for list in node.nodes() {
+            members.extend(self.var_list(list)?);
+        }
+        Some(Declaration::Class(Class {
+            name: self.word(node, 1)?,
+            members,
+            span: self.span(node),
+        }))
+    }
+
+    fn prototype(&self, node: &Node) -> Option<Declaration> {
+        Some(Declaration::Proto(Prototype {
+            name: self.word(node, 1)?,
+            class: self.word(node, 2)?,
+            body: self.block(node.nodes().next()?)?,
+            span: self.span(node),
+        }))
+    }
+
+    fn instance(&self, node: &Node) -> Option<Declaration> {
+        let words = self.words(node);
+        let (class, names) = words.get(1..)?.split_last()?;
+        let class = self.identifier(class);
+        let body = match node.nodes().next() {
+            Some(body) => self.block(body)?,
+            None => Vec::new(),
+        };
+        let span = self.span(node);
+
+        Some(Declaration::Inst(
+            names
+                .iter()
+                .map(|name| Instance {
+                    name: self.identifier(name),
+                    class: class.clone(),
+                    body: body.clone(),
+                    span,
+                })
+                .collect(),
+        ))
+    }
+
+    fn block(&self, node: &Node) -> Option<Vec<Statement>> {
+        node.nodes().map(|node| self.statement(node)).collect()
+    }
+
+    fn statement(&self, node: &Node) -> Option<Statement> {
+        match node.kind {
+            NodeKind::VarList => self.var_list(node).map(Statement::VarDeclarations),
+            NodeKind::Const => self.constant(node),
+            NodeKind::If => Some(Statement::If(Box::new(self.if_statement(node)?))),
+            NodeKind::Return => {
+                let exp = match node.nodes().next() {
+                    Some(exp) => Some(self.expression(exp)?),
+                    None => None,
+                };
+                Some(Statement::ReturnStatement(exp, self.span(node)))
+            }
+            NodeKind::Assignment => {
+                let mut children = node.nodes();
+                let var = children.next()?;
+                let exp = children.next()?;
+                Some(Statement::Ass(Assignment {
+                    var: self.var_access(var)?,
+                    op: AssignmentOperator::from_bytes(&self.operator(node)?.text)?,
+                    exp: self.expression(exp)?,
+                    span: self.span(node),
+                }))
+            }
+            NodeKind::ExpressionStatement => {
+                Some(Statement::Exp(self.expression(node.nodes().next()?)?))
+            }
+            _ => None,
+        }
+    }
+
+    fn if_statement(&self, node: &Node) -> Option<IfStatement> {
+        let mut branches = Vec::new();
+        let mut else_branch = None;
+        for child in node.nodes() {
+            let mut parts = child.nodes();
+            if child.kind == NodeKind::ElseBranch {
+                else_branch = Some(self.block(parts.next()?)?);
+                continue;
+            }
+
+            let cond = parts.next()?;
+            let body = parts.next()?;
+            // The span starts at `if`, not at the `else` of an `else if`.
+            let keyword = child.child_tokens().find(|token| token.is_keyword(b"if"))?;
+            branches.push(IfBranch {
+                cond: self.expression(cond)?,
+                body: self.block(body)?,
+                span: Span {
+                    start: keyword.offset,
+                    ..self.span(child)
+                },
+            });
+        }
+
+        Some(IfStatement {
+            branches,
+            else_branch,
+            span: self.span(node),
+        })
+    }
+
+    fn expression(&self, node: &Node) -> Option<Expression> {
+        match node.kind {
+            NodeKind::Binary => {
+                let mut operands = node.nodes();
+                let left = self.expression(operands.next()?)?;
+                let right = self.expression(operands.next()?)?;
+                let op = BinaryOperator::from_bytes(&self.operator(node)?.text)?;
+                Some(Expression::Binary(Box::new(BinaryExpression::new(
+                    op, left, right,
+                ))))
+            }
+            NodeKind::Unary => {
+                let operand = self.expression(node.nodes().next()?)?;
+                Some(Expression::Unary(Box::new(UnaryExpression {
+                    op: UnaryOperator::from_bytes(&self.operator(node)?.text)?,
+                    right: operand,
+                    span: self.span(node),
+                })))
+            }
+            NodeKind::Paren => self.expression(node.nodes().next()?),
+            NodeKind::Call => {
+                let args = node.nodes().next()?;
+                Some(Expression::Call(Box::new(Call {
+                    func: self.word(node, 0)?,
+                    params: args
+                        .nodes()
+                        .map(|arg| self.expression(arg))
+                        .collect::<Option<_>>()?,
+                    span: self.span(node),
+                })))
+            }
+            NodeKind::VarAccess => Some(Expression::Identifier(Box::new(self.var_access(node)?))),
+            NodeKind::Literal => self.literal(node.first_token()?),
+            _ => None,
+        }
+    }
+
+    fn var_access(&self, node: &Node) -> Option<VarAccess> {
+        let mut words = self
+            .words(node)
+            .into_iter()
+            .map(|word| self.identifier(word));
+        let first = words.next()?;
+        let index = match node.nodes().next() {
+            Some(index) => Some(self.expression(index)?),
+            None => None,
+        };
+
+        Some(VarAccess::new(first, words.next(), index).with_span(self.span(node)))
+    }
+
+    fn literal(&self, token: &Token) -> Option<Expression> {
+        let span = token.span(self.file);
+        match token.kind {
+            TokenKind::Int => Some(Expression::Int(self.int(token)?, span)),
+            TokenKind::Float => Some(Expression::Float(
+                String::from_utf8_lossy(&token.text).parse().ok()?,
+                span,
+            )),
+            TokenKind::String if token.text.len() >= 2 => {
+                let content = &token.text[1..token.text.len() - 1];
+                Some(Expression::String(
+                    StringLiteral::new(content).with_span(span),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn int(&self, token: &Token) -> Option<i64> {
+        String::from_utf8_lossy(&token.text).parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cst::parse;
+    use parsers::parse_recovering;
+
+    fn lower_both(content: &[u8]) -> (String, String) {
+        let (root, _) = parse(content, FileId(3));
+        let (ast, _) = parse_recovering(content, FileId(3));
+        // Spans always compare equal, so compare the debug output to check them as well.
+        (
+            format!("{:?}", lower(&root, FileId(3))),
+            format!("{:?}", ast),
+        )
+    }
+
+    #[test]
+    fn matches_parser() {
+        let content: &[u8] = b"// a sample
+var int a, b[MAX], var string c;
+const int MAX = 2 * (3 + 4) - -1;
+const string NAMES[2] = { \"x\", \"y\" };
+class C { var int x; var float y[2], z; };
+func float f(var int x, var C c) {
+    var int i; const float PI = 3.14;
+    if x > 1 && !c.x /* check */ { return 1.5; }
+    else if x { i += c.y[x + 1] << 2; }
+    else { f(1, 2); };
+    if (x) { x = 1; }
+    return;
+};
+prototype P(C) { x = 1; };
+instance I(C) { f(1, self); };
+instance J, K(C);
+";
+        let (lowered, parsed) = lower_both(content);
+        assert_eq!(lowered, parsed);
+    }
+
+    #[test]
+    fn malformed_nodes_are_skipped() {
+        let (mut root, errors) = parse(b"var int a; func void f() { a = 1; };", FileId(0));
+        assert!(errors.is_empty());
+        // Dropping the body of `f` leaves a node that `parse` never builds.
+        if let Some(::cst::Element::Node(ref mut func)) = root.children.get_mut(1) {
+            func.children.retain(|child| match *child {
+                ::cst::Element::Node(ref node) => node.kind != NodeKind::Block,
+                _ => true,
+            });
+        }
+        assert_eq!(lower(&root, FileId(0)).declarations.len(), 1);
+    }
+
+    #[test]
+    fn unknown_operators_are_skipped() {
+        let (mut root, errors) = parse(
+            b"var int a; func void f() { a = 1; }; func int g() { return 1 + 2; }; func int h() { return -1; };",
+            FileId(0),
+        );
+        assert!(errors.is_empty());
+        // Edited tokens are not parsed again, so they can hold any text.
+        root.for_each_token_mut(&mut |token| {
+            if token.is(b"=") || token.is(b"+") || token.is(b"-") {
+                token.text = b"@".to_vec();
+            }
+        });
+        assert_eq!(lower(&root, FileId(0)).declarations.len(), 1);
+    }
+
+    #[test]
+    fn errors_are_skipped() {
+        let content: &[u8] = b"func void foo() {\n    x = ;\n};\nfunc void bar() { bar(); };\n";
+        let (lowered, parsed) = lower_both(content);
+        assert_eq!(lowered, parsed);
+        assert!(lowered.contains("bar"));
+    }
+}
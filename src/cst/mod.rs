@@ -0,0 +1,252 @@
+//! A lossless concrete syntax tree.
+//!
+//! Unlike the `AST`, the tree keeps every byte of the source: whitespace and comments are
+//! attached to the token that follows them, and anything that does not parse ends up in an
+//! `Error` node. Writing the tree back out reproduces the file exactly, so tools can edit
+//! tokens and save the result without losing comments. `lower` derives the `AST` from it.
+
+mod lexer;
+mod lower;
+mod parser;
+
+pub use self::lexer::tokenize;
+pub use self::lower::lower;
+pub use self::parser::parse;
+
+use types::{FileId, Span};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    LineComment,
+    BlockComment,
+}
+
+/// Whitespace or a comment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// Identifiers and keywords, which are only told apart by the parser.
+    Word,
+    Int,
+    Float,
+    String,
+    Punct,
+    /// A byte that starts no token, or an unterminated string.
+    Unknown,
+    /// The empty token at the end of the file, which holds the trailing trivia.
+    Eof,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: Vec<u8>,
+    /// Trivia between the previous token and this one.
+    pub leading: Vec<Trivia>,
+    /// Offset of `text` in the parsed file. It is not updated when tokens are edited.
+    pub offset: usize,
+}
+
+impl Token {
+    pub fn is(&self, text: &[u8]) -> bool {
+        self.kind == TokenKind::Punct && self.text == text
+    }
+
+    /// Whether the token is the word `keyword`, ignoring case.
+    pub fn is_keyword(&self, keyword: &[u8]) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    pub fn span(&self, file: FileId) -> Span {
+        Span::new(file, self.offset, self.offset + self.text.len())
+    }
+
+    /// The line and block comments in front of the token, with their offsets.
+    pub fn comments(&self) -> Vec<(usize, &Trivia)> {
+        let mut offset = self.offset
+            - self
+                .leading
+                .iter()
+                .map(|trivia| trivia.text.len())
+                .sum::<usize>();
+        let mut comments = Vec::new();
+        for trivia in &self.leading {
+            if trivia.kind != TriviaKind::Whitespace {
+                comments.push((offset, trivia));
+            }
+            offset += trivia.text.len();
+        }
+        comments
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    File,
+    /// Tokens that could not be parsed.
+    Error,
+
+    /// `var int a, b[2], var string c;`, made of groups that start with `var`.
+    VarList,
+    VarGroup,
+    /// A name with optional array size.
+    Declarator,
+    ArraySize,
+    /// A constant or constant array.
+    Const,
+    ArrayInit,
+    Func,
+    Params,
+    Class,
+    Prototype,
+    Instance,
+
+    Block,
+    If,
+    /// A condition and block, preceded by `else` for all but the first branch.
+    IfBranch,
+    ElseBranch,
+    Return,
+    Assignment,
+    ExpressionStatement,
+
+    Binary,
+    Unary,
+    Paren,
+    Call,
+    Args,
+    VarAccess,
+    Literal,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Element {
+    Node(Node),
+    Token(Token),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub kind: NodeKind,
+    pub children: Vec<Element>,
+}
+
+impl Node {
+    pub fn new(kind: NodeKind) -> Self {
+        Node {
+            kind,
+            children: Vec::new(),
+        }
+    }
+
+    /// The source this node was parsed from, including edits made to its tokens.
+    pub fn text(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        for token in self.tokens() {
+            for trivia in &token.leading {
+                out.extend_from_slice(&trivia.text);
+            }
+            out.extend_from_slice(&token.text);
+        }
+        out
+    }
+
+    /// All tokens of the node in source order.
+    pub fn tokens(&self) -> Vec<&Token> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens<'a>(&'a self, tokens: &mut Vec<&'a Token>) {
+        for child in &self.children {
+            match *child {
+                Element::Node(ref node) => node.collect_tokens(tokens),
+                Element::Token(ref token) => tokens.push(token),
+            }
+        }
+    }
+
+    /// Calls `f` for every token of the node, e.g. to rename identifiers in place.
+    pub fn for_each_token_mut<F: FnMut(&mut Token)>(&mut self, f: &mut F) {
+        for child in &mut self.children {
+            match *child {
+                Element::Node(ref mut node) => node.for_each_token_mut(f),
+                Element::Token(ref mut token) => f(token),
+            }
+        }
+    }
+
+    /// Direct child nodes.
+    pub fn nodes(&self) -> impl Iterator<Item = &Node> {
+        self.children.iter().filter_map(|child| match *child {
+            Element::Node(ref node) => Some(node),
+            Element::Token(_) => None,
+        })
+    }
+
+    /// Direct child tokens.
+    pub fn child_tokens(&self) -> impl Iterator<Item = &Token> {
+        self.children.iter().filter_map(|child| match *child {
+            Element::Token(ref token) => Some(token),
+            Element::Node(_) => None,
+        })
+    }
+
+    pub fn first_token(&self) -> Option<&Token> {
+        self.children.iter().find_map(|child| match *child {
+            Element::Node(ref node) => node.first_token(),
+            Element::Token(ref token) => Some(token),
+        })
+    }
+
+    pub fn last_token(&self) -> Option<&Token> {
+        self.children.iter().rev().find_map(|child| match *child {
+            Element::Node(ref node) => node.last_token(),
+            Element::Token(ref token) => Some(token),
+        })
+    }
+
+    /// From the first to the last token, without a terminating semicolon.
+    pub fn span(&self, file: FileId) -> Span {
+        let tokens = self.tokens();
+        let tokens = match tokens.split_last() {
+            Some((last, rest)) if last.is(b";") && !rest.is_empty() => rest,
+            _ => &tokens[..],
+        };
+        match (tokens.first(), tokens.last()) {
+            (Some(first), Some(last)) => first.span(file).to(last.span(file)),
+            _ => Span::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rename_keeps_comments() {
+        let content: &[u8] =
+            b"// counter\nvar int count; /* used below */\r\nfunc void f() { count += 1; };";
+        let (mut root, errors) = parse(content, FileId(0));
+        assert!(errors.is_empty());
+
+        root.for_each_token_mut(&mut |token| {
+            if token.kind == TokenKind::Word && token.text == b"count" {
+                token.text = b"total".to_vec();
+            }
+        });
+        assert_eq!(
+            root.text(),
+            b"// counter\nvar int total; /* used below */\r\nfunc void f() { total += 1; };"
+                .to_vec()
+        );
+    }
+}
@@ -0,0 +1,660 @@
+use cst::{tokenize, Element, Node, NodeKind, Token, TokenKind};
+use errors::Error;
+use inner_errors::ParserError;
+use source_map::LineIndex;
+use types::FileId;
+
+/// Words that cannot be identifiers, like in `parsers::identifier_parser`.
+const RESERVED: [&[u8]; 3] = [b"if", b"var", b"return"];
+
+/// Keywords that start a declaration.
+const DECLARATION_KEYWORDS: [&[u8]; 6] = [
+    b"var",
+    b"const",
+    b"func",
+    b"class",
+    b"prototype",
+    b"instance",
+];
+
+/// Operators by precedence level, from loosest to tightest. All of them are left associative.
+const BINARY_LEVELS: [&[&[u8]]; 5] = [
+    &[b"||", b"&&"],
+    &[b">=", b"<=", b"!=", b"==", b">", b"<"],
+    &[b"+", b"-"],
+    &[b"*", b"/", b"%"],
+    &[b"|", b"&", b">>", b"<<"],
+];
+
+const ASSIGNMENT_OPERATORS: [&[u8]; 5] = [b"=", b"+=", b"-=", b"*=", b"/="];
+
+/// Parses `content` into a lossless tree. Declarations that do not parse become `Error` nodes
+/// spanning up to the point where parsing resumes, and are reported as errors.
+pub fn parse(content: &[u8], file: FileId) -> (Node, Vec<Error>) {
+    let mut parser = Parser {
+        tokens: tokenize(content),
+        pos: 0,
+    };
+    let lines = LineIndex::new(content);
+
+    let mut root = Node::new(NodeKind::File);
+    let mut errors = Vec::new();
+    while parser.peek().kind != TokenKind::Eof {
+        let start = parser.pos;
+        match parser.declaration() {
+            Ok(decl) => root.children.push(Element::Node(decl)),
+            Err(failure) => {
+                let token = &parser.tokens[failure.pos];
+                let end = parser.resync(start, failure.pos);
+                errors.push(Error::ParsingError {
+                    err: failure.err,
+                    span: token.span(file),
+                    line: lines.line_col(token.offset).line,
+                    block: parser.open_block(start, end).map(|brace| brace.span(file)),
+                });
+
+                let mut error = Node::new(NodeKind::Error);
+                error.children = parser.tokens[start..end]
+                    .iter()
+                    .cloned()
+                    .map(Element::Token)
+                    .collect();
+                root.children.push(Element::Node(error));
+                parser.pos = end;
+            }
+        }
+    }
+    root.children.push(Element::Token(parser.peek().clone()));
+
+    (root, errors)
+}
+
+/// Where and why parsing failed.
+struct Failure {
+    pos: usize,
+    err: ParserError,
+}
+
+type PResult<T> = Result<T, Failure>;
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        self.peek_at(0)
+    }
+
+    fn peek_at(&self, n: usize) -> &Token {
+        let last = self.tokens.len() - 1;
+        &self.tokens[(self.pos + n).min(last)]
+    }
+
+    fn fail<T>(&self, err: ParserError) -> PResult<T> {
+        Err(Failure { pos: self.pos, err })
+    }
+
+    fn bump(&mut self, node: &mut Node) {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        node.children.push(Element::Token(token));
+    }
+
+    fn expect(&mut self, node: &mut Node, punct: &[u8], err: ParserError) -> PResult<()> {
+        if !self.peek().is(punct) {
+            return self.fail(err);
+        }
+        self.bump(node);
+        Ok(())
+    }
+
+    fn expect_keyword(&mut self, node: &mut Node, keyword: &[u8], err: ParserError) -> PResult<()> {
+        if !self.peek().is_keyword(keyword) {
+            return self.fail(err);
+        }
+        self.bump(node);
+        Ok(())
+    }
+
+    /// Whether an `Int` token is small enough to be lowered, like `parsers::number_parser` checks.
+    fn fits_int(token: &Token) -> bool {
+        ::std::str::from_utf8(&token.text)
+            .ok()
+            .and_then(|text| text.parse::<i64>().ok())
+            .is_some()
+    }
+
+    fn is_identifier(token: &Token) -> bool {
+        token.kind == TokenKind::Word && !RESERVED.iter().any(|word| token.is_keyword(word))
+    }
+
+    fn expect_identifier(&mut self, node: &mut Node, err: ParserError) -> PResult<()> {
+        if !Parser::is_identifier(self.peek()) {
+            return self.fail(err);
+        }
+        self.bump(node);
+        Ok(())
+    }
+
+    fn declaration(&mut self) -> PResult<Node> {
+        let token = self.peek();
+        if token.is_keyword(b"var") {
+            let mut node = self.var_list()?;
+            self.expect(&mut node, b";", ParserError::MissingSemi)?;
+            Ok(node)
+        } else if token.is_keyword(b"const") {
+            self.constant()
+        } else if token.is_keyword(b"func") {
+            self.function()
+        } else if token.is_keyword(b"class") {
+            self.class()
+        } else if token.is_keyword(b"prototype") {
+            self.prototype()
+        } else if token.is_keyword(b"instance") {
+            self.instance()
+        } else {
+            self.fail(ParserError::Declaration)
+        }
+    }
+
+    /// `var int a, b, var string c`, without the semicolon.
+    fn var_list(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::VarList);
+        self.var_groups(&mut node)?;
+        Ok(node)
+    }
+
+    fn var_groups(&mut self, node: &mut Node) -> PResult<()> {
+        loop {
+            let group = self.var_group()?;
+            node.children.push(Element::Node(group));
+            if !(self.peek().is(b",") && self.peek_at(1).is_keyword(b"var")) {
+                return Ok(());
+            }
+            self.bump(node);
+        }
+    }
+
+    fn var_group(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::VarGroup);
+        self.expect_keyword(&mut node, b"var", ParserError::VariableDeclaration)?;
+        self.expect_identifier(&mut node, ParserError::VariableDeclaration)?;
+        loop {
+            let declarator = self.declarator()?;
+            node.children.push(Element::Node(declarator));
+            if !self.peek().is(b",") || self.peek_at(1).is_keyword(b"var") {
+                return Ok(node);
+            }
+            self.bump(&mut node);
+        }
+    }
+
+    fn declarator(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::Declarator);
+        self.expect_identifier(&mut node, ParserError::VariableDeclaration)?;
+        if self.peek().is(b"[") {
+            let size = self.array_size()?;
+            node.children.push(Element::Node(size));
+        }
+        Ok(node)
+    }
+
+    fn array_size(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::ArraySize);
+        self.expect(&mut node, b"[", ParserError::VariableDeclaration)?;
+        match self.peek().kind {
+            TokenKind::Int if !Parser::fits_int(self.peek()) => {
+                return self.fail(ParserError::VariableDeclaration)
+            }
+            TokenKind::Int => self.bump(&mut node),
+            _ => self.expect_identifier(&mut node, ParserError::VariableDeclaration)?,
+        }
+        self.expect(&mut node, b"]", ParserError::VariableDeclaration)?;
+        Ok(node)
+    }
+
+    /// Constants and constant arrays, including the semicolon.
+    fn constant(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::Const);
+        self.expect_keyword(&mut node, b"const", ParserError::Declaration)?;
+        self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+        self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+
+        if self.peek().is(b"[") {
+            let size = self.array_size()?;
+            node.children.push(Element::Node(size));
+            self.expect(&mut node, b"=", ParserError::Declaration)?;
+
+            let mut init = Node::new(NodeKind::ArrayInit);
+            self.expect(&mut init, b"{", ParserError::Declaration)?;
+            loop {
+                let exp = self.expression()?;
+                init.children.push(Element::Node(exp));
+                if !self.peek().is(b",") {
+                    break;
+                }
+                self.bump(&mut init);
+            }
+            self.expect(&mut init, b"}", ParserError::Declaration)?;
+            node.children.push(Element::Node(init));
+        } else {
+            self.expect(&mut node, b"=", ParserError::Declaration)?;
+            let exp = self.expression()?;
+            node.children.push(Element::Node(exp));
+        }
+
+        self.expect(&mut node, b";", ParserError::MissingSemi)?;
+        Ok(node)
+    }
+
+    fn function(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::Func);
+        self.expect_keyword(&mut node, b"func", ParserError::Declaration)?;
+        self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+        self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+
+        let mut params = Node::new(NodeKind::Params);
+        self.expect(&mut params, b"(", ParserError::Declaration)?;
+        if !self.peek().is(b")") {
+            self.var_groups(&mut params)?;
+        }
+        self.expect(&mut params, b")", ParserError::Declaration)?;
+        node.children.push(Element::Node(params));
+
+        let body = self.block()?;
+        node.children.push(Element::Node(body));
+        self.expect(&mut node, b";", ParserError::MissingSemi)?;
+        Ok(node)
+    }
+
+    fn class(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::Class);
+        self.expect_keyword(&mut node, b"class", ParserError::ClassDeclaration)?;
+        self.expect_identifier(&mut node, ParserError::ClassDeclaration)?;
+        self.expect(&mut node, b"{", ParserError::ClassDeclaration)?;
+        while self.peek().is_keyword(b"var") {
+            let mut members = self.var_list()?;
+            self.expect(&mut members, b";", ParserError::MissingSemi)?;
+            node.children.push(Element::Node(members));
+        }
+        self.expect(&mut node, b"}", ParserError::ClassDeclaration)?;
+        self.expect(&mut node, b";", ParserError::MissingSemi)?;
+        Ok(node)
+    }
+
+    fn prototype(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::Prototype);
+        self.expect_keyword(&mut node, b"prototype", ParserError::Declaration)?;
+        self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+        self.expect(&mut node, b"(", ParserError::Declaration)?;
+        self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+        self.expect(&mut node, b")", ParserError::Declaration)?;
+        let body = self.block()?;
+        node.children.push(Element::Node(body));
+        self.expect(&mut node, b";", ParserError::MissingSemi)?;
+        Ok(node)
+    }
+
+    /// A single instance with a body, or a list of instances without one.
+    fn instance(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::Instance);
+        self.expect_keyword(&mut node, b"instance", ParserError::Declaration)?;
+        self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+        let mut names = 1;
+        while self.peek().is(b",") {
+            self.bump(&mut node);
+            self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+            names += 1;
+        }
+        self.expect(&mut node, b"(", ParserError::Declaration)?;
+        self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+        self.expect(&mut node, b")", ParserError::Declaration)?;
+
+        if names == 1 && self.peek().is(b"{") {
+            let body = self.block()?;
+            node.children.push(Element::Node(body));
+        }
+        self.expect(&mut node, b";", ParserError::MissingSemi)?;
+        Ok(node)
+    }
+
+    fn block(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::Block);
+        self.expect(&mut node, b"{", ParserError::IllegalStatement)?;
+        while !self.peek().is(b"}") {
+            if self.peek().kind == TokenKind::Eof {
+                return self.fail(ParserError::IllegalStatement);
+            }
+            let statement = self.statement()?;
+            node.children.push(Element::Node(statement));
+        }
+        self.bump(&mut node);
+        Ok(node)
+    }
+
+    fn statement(&mut self) -> PResult<Node> {
+        let token = self.peek();
+        if token.is_keyword(b"var") {
+            let mut node = self.var_list()?;
+            self.expect(&mut node, b";", ParserError::MissingSemi)?;
+            return Ok(node);
+        }
+        if token.is_keyword(b"const") {
+            return self.constant();
+        }
+        if token.is_keyword(b"if") {
+            return self.if_statement();
+        }
+        if token.is_keyword(b"return") {
+            let mut node = Node::new(NodeKind::Return);
+            self.bump(&mut node);
+            if !self.peek().is(b";") {
+                let exp = self.expression()?;
+                node.children.push(Element::Node(exp));
+            }
+            self.expect(&mut node, b";", ParserError::MissingSemi)?;
+            return Ok(node);
+        }
+
+        let exp = self.expression()?;
+        let is_assignment = exp.kind == NodeKind::VarAccess
+            && ASSIGNMENT_OPERATORS.iter().any(|op| self.peek().is(op));
+        let mut node = if is_assignment {
+            let mut node = Node::new(NodeKind::Assignment);
+            node.children.push(Element::Node(exp));
+            self.bump(&mut node);
+            let value = self.expression()?;
+            node.children.push(Element::Node(value));
+            node
+        } else {
+            let mut node = Node::new(NodeKind::ExpressionStatement);
+            node.children.push(Element::Node(exp));
+            node
+        };
+        self.expect(&mut node, b";", ParserError::MissingSemi)?;
+        Ok(node)
+    }
+
+    /// Branches are separated by `else`, and the semicolon after the last one is optional.
+    fn if_statement(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::If);
+        loop {
+            let mut branch = Node::new(NodeKind::IfBranch);
+            let is_else_if = self.peek().is_keyword(b"else");
+            if is_else_if {
+                self.bump(&mut branch);
+            }
+            self.expect_keyword(&mut branch, b"if", ParserError::IfClause)?;
+            let cond = self.expression().map_err(|failure| Failure {
+                err: ParserError::IllegalExpression,
+                ..failure
+            })?;
+            branch.children.push(Element::Node(cond));
+            let body = self.block()?;
+            branch.children.push(Element::Node(body));
+            node.children.push(Element::Node(branch));
+
+            if !self.peek().is_keyword(b"else") {
+                break;
+            }
+            if !self.peek_at(1).is_keyword(b"if") {
+                let mut else_branch = Node::new(NodeKind::ElseBranch);
+                self.bump(&mut else_branch);
+                let body = self.block().map_err(|failure| Failure {
+                    err: ParserError::ElseClause,
+                    ..failure
+                })?;
+                else_branch.children.push(Element::Node(body));
+                node.children.push(Element::Node(else_branch));
+                break;
+            }
+        }
+
+        if self.peek().is(b";") {
+            self.bump(&mut node);
+        }
+        Ok(node)
+    }
+
+    fn expression(&mut self) -> PResult<Node> {
+        self.binary(0)
+    }
+
+    fn binary(&mut self, level: usize) -> PResult<Node> {
+        let operand = |parser: &mut Parser| match level + 1 {
+            next if next < BINARY_LEVELS.len() => parser.binary(next),
+            _ => parser.unary(),
+        };
+
+        let mut left = operand(self)?;
+        while BINARY_LEVELS[level].iter().any(|op| self.peek().is(op)) {
+            let mut node = Node::new(NodeKind::Binary);
+            node.children.push(Element::Node(left));
+            self.bump(&mut node);
+            let right = operand(self)?;
+            node.children.push(Element::Node(right));
+            left = node;
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> PResult<Node> {
+        let token = self.peek();
+        if [&b"!"[..], b"~", b"-", b"+"].iter().any(|op| token.is(op)) {
+            let mut node = Node::new(NodeKind::Unary);
+            self.bump(&mut node);
+            let operand = self.unary()?;
+            node.children.push(Element::Node(operand));
+            return Ok(node);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> PResult<Node> {
+        let token = self.peek();
+        match token.kind {
+            TokenKind::Int if !Parser::fits_int(token) => self.fail(ParserError::IllegalExpression),
+            TokenKind::Int | TokenKind::Float | TokenKind::String => {
+                let mut node = Node::new(NodeKind::Literal);
+                self.bump(&mut node);
+                Ok(node)
+            }
+            TokenKind::Punct if token.is(b"(") => {
+                let mut node = Node::new(NodeKind::Paren);
+                self.bump(&mut node);
+                let exp = self.expression()?;
+                node.children.push(Element::Node(exp));
+                self.expect(&mut node, b")", ParserError::IllegalExpression)?;
+                Ok(node)
+            }
+            _ if Parser::is_identifier(token) && self.peek_at(1).is(b"(") => self.call(),
+            _ if Parser::is_identifier(token) => self.var_access(),
+            _ => self.fail(ParserError::IllegalExpression),
+        }
+    }
+
+    fn call(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::Call);
+        self.bump(&mut node);
+
+        let mut args = Node::new(NodeKind::Args);
+        self.bump(&mut args);
+        if !self.peek().is(b")") {
+            loop {
+                let arg = self.expression().map_err(|failure| Failure {
+                    err: ParserError::InvalidCall,
+                    ..failure
+                })?;
+                args.children.push(Element::Node(arg));
+                if !self.peek().is(b",") {
+                    break;
+                }
+                self.bump(&mut args);
+            }
+        }
+        self.expect(&mut args, b")", ParserError::InvalidCall)?;
+        node.children.push(Element::Node(args));
+        Ok(node)
+    }
+
+    /// `name`, `instance.name`, each optionally followed by an index.
+    fn var_access(&mut self) -> PResult<Node> {
+        let mut node = Node::new(NodeKind::VarAccess);
+        self.bump(&mut node);
+        if self.peek().is(b".") {
+            self.bump(&mut node);
+            self.expect_identifier(&mut node, ParserError::MissingIdentifier)?;
+        }
+        if self.peek().is(b"[") {
+            self.bump(&mut node);
+            let index = self.expression()?;
+            node.children.push(Element::Node(index));
+            self.expect(&mut node, b"]", ParserError::IllegalExpression)?;
+        }
+        Ok(node)
+    }
+
+    /// The innermost `{` between the tokens `start` and `end` that is not closed before `end`.
+    fn open_block(&self, start: usize, end: usize) -> Option<&Token> {
+        let mut open = Vec::new();
+        for token in &self.tokens[start..end] {
+            if token.is(b"{") {
+                open.push(token);
+            } else if token.is(b"}") {
+                open.pop();
+            }
+        }
+
+        open.pop()
+    }
+
+    /// Index of the token at which parsing resumes after the declaration starting at `start`
+    /// failed at `error`, following the same rules as `parsers::parse_recovering`. The result
+    /// is always past `start`.
+    fn resync(&self, start: usize, error: usize) -> usize {
+        let mut depth = 0usize;
+        let error_line = self.line_start(error);
+        for i in start..self.tokens.len() {
+            let token = &self.tokens[i];
+
+            match token.kind {
+                TokenKind::Eof => return i,
+                TokenKind::Word if i > start && self.line_start(i) >= error_line => {
+                    let is_keyword = DECLARATION_KEYWORDS
+                        .iter()
+                        .any(|keyword| token.is_keyword(keyword));
+                    let is_block_keyword =
+                        is_keyword && !token.is_keyword(b"var") && !token.is_keyword(b"const");
+                    if (is_keyword && depth == 0 && i >= error)
+                        || (is_block_keyword && self.line_start(i) == i)
+                    {
+                        return i;
+                    }
+                }
+                TokenKind::Punct if token.is(b"{") => depth += 1,
+                TokenKind::Punct if token.is(b"}") => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 && i >= error {
+                        return match self.tokens.get(i + 1) {
+                            Some(next) if next.is(b";") => i + 2,
+                            _ => i + 1,
+                        };
+                    }
+                }
+                TokenKind::Punct if token.is(b";") && depth == 0 && i >= error => return i + 1,
+                _ => {}
+            }
+        }
+        self.tokens.len() - 1
+    }
+
+    /// Index of the first token on the line of the token at `index`.
+    fn line_start(&self, index: usize) -> usize {
+        let starts_line = |token: &Token| {
+            token
+                .leading
+                .iter()
+                .any(|trivia| trivia.text.contains(&b'\n'))
+        };
+        (1..=index)
+            .rev()
+            .find(|&i| starts_line(&self.tokens[i]))
+            .unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(node: &Node) -> Vec<NodeKind> {
+        node.nodes().map(|node| node.kind).collect()
+    }
+
+    #[test]
+    fn lossless() {
+        let content: &[u8] = b"// header\r\nvar int a, b[2], var string c;\r\n\
+func int f(var int x) {\n\t/* note */ if (x) { return x; } else if x > 1 { x += 1; } else { f(1); };\n}; \n\
+const int A[2] = { 1, -2 };\ninstance h, i(C);\n  // trailer";
+
+        let (root, errors) = parse(content, FileId(0));
+        assert!(errors.is_empty());
+        assert_eq!(root.text(), content.to_vec());
+        assert_eq!(
+            kinds(&root),
+            vec![
+                NodeKind::VarList,
+                NodeKind::Func,
+                NodeKind::Const,
+                NodeKind::Instance
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_are_kept() {
+        let content: &[u8] = b"func void foo() {\n    x = ;\n};\nfunc void bar() {};\nvar int z";
+
+        let (root, errors) = parse(content, FileId(0));
+        assert_eq!(root.text(), content.to_vec());
+        assert_eq!(
+            kinds(&root),
+            vec![NodeKind::Error, NodeKind::Func, NodeKind::Error]
+        );
+
+        let errors: Vec<(ParserError, usize)> = errors
+            .into_iter()
+            .map(|err| match err {
+                Error::ParsingError { err, line, .. } => (err, line),
+                _ => panic!("unexpected error"),
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                (ParserError::IllegalExpression, 2),
+                (ParserError::MissingSemi, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn oversized_array_sizes() {
+        let content: &[u8] = b"var int a[99999999999999999999];
+const int b[99999999999999999999] = {1};
+var int c[2];";
+
+        let (root, errors) = parse(content, FileId(0));
+        assert_eq!(root.text(), content.to_vec());
+        assert_eq!(
+            kinds(&root),
+            vec![NodeKind::Error, NodeKind::Error, NodeKind::VarList]
+        );
+        assert_eq!(errors.len(), 2);
+        assert_eq!(::cst::lower(&root, FileId(0)).declarations.len(), 1);
+    }
+}
@@ -0,0 +1,717 @@
+//! Reconstructs declarations from a compiled DAT file.
+//!
+//! Names come back in upper case, as the DAT stores them, and local declarations are moved
+//! to the start of their function. Constants of type `func` cannot be told apart from
+//! functions and are decompiled as such.
+
+use dat::{flags, Dat, DatSymbol, DataType, Instruction, SymbolContent};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Range;
+use types::{
+    ArraySizeDeclaration, Assignment, AssignmentOperator, BinaryExpression, BinaryOperator, Call,
+    Class, ConstArrayDeclaration, ConstArrayInitializer, ConstDeclaration, Declaration, Expression,
+    Function, Identifier, IfBranch, IfStatement, Instance, Prototype, Span, Statement,
+    StringLiteral, UnaryExpression, VarAccess, VarDeclaration, AST,
+};
+
+/// Reconstructs the declarations of `dat` in symbol table order. Externals and the anonymous
+/// symbols of string literals are skipped.
+pub fn decompile(dat: &Dat) -> AST {
+    Decompiler::new(dat).ast()
+}
+
+/// What the code that is being decompiled belongs to.
+struct Context {
+    /// Return type of the function, `Void` for instances and prototypes.
+    returns: DataType,
+}
+
+struct Decompiler<'a> {
+    dat: &'a Dat,
+    /// Functions, prototypes and instances by the address of their code.
+    by_address: HashMap<u32, usize>,
+    /// Start of every piece of code, which is also where the previous one ends.
+    starts: BTreeSet<u32>,
+}
+
+impl<'a> Decompiler<'a> {
+    fn new(dat: &'a Dat) -> Self {
+        let mut by_address = HashMap::new();
+        let mut starts = BTreeSet::new();
+        for (index, symbol) in dat.symbols.iter().enumerate() {
+            if let Some(address) = code_address(symbol) {
+                by_address.entry(address).or_insert(index);
+                starts.insert(address);
+            }
+        }
+
+        Decompiler {
+            dat,
+            by_address,
+            starts,
+        }
+    }
+
+    fn ast(&self) -> AST {
+        let symbols = &self.dat.symbols;
+        let mut declarations = Vec::new();
+        let mut index = 0;
+        while index < symbols.len() {
+            let locals = self.locals(index);
+            let symbol = &symbols[index];
+            if !is_anonymous(symbol) && !symbol.name.contains(&b'.') {
+                if let Some(decl) = self.declaration(index, locals.clone()) {
+                    declarations.push(decl);
+                }
+            }
+            index = locals.end;
+        }
+
+        AST { declarations }
+    }
+
+    /// The symbols directly following `index` that are scoped to it: parameters and locals
+    /// of functions or members of classes.
+    fn locals(&self, index: usize) -> Range<usize> {
+        let symbols = &self.dat.symbols;
+        let mut prefix = symbols[index].name.clone();
+        prefix.push(b'.');
+
+        let end = symbols[index + 1..]
+            .iter()
+            .position(|symbol| !symbol.name.starts_with(&prefix))
+            .map_or(symbols.len(), |count| index + 1 + count);
+        index + 1..end
+    }
+
+    fn declaration(&self, index: usize, locals: Range<usize>) -> Option<Declaration> {
+        let symbol = &self.dat.symbols[index];
+        let name = self.ident(index);
+
+        Some(match symbol.typ {
+            DataType::Class => Declaration::Class(Class {
+                name,
+                members: locals.map(|member| self.var_declaration(member)).collect(),
+                span: Span::default(),
+            }),
+            DataType::Func if symbol.has_flag(flags::EXTERNAL) => return None,
+            DataType::Func if symbol.has_flag(flags::CONST) => {
+                Declaration::Func(self.function(index, locals))
+            }
+            DataType::Prototype => Declaration::Proto(Prototype {
+                name,
+                class: self.parent_name(symbol),
+                body: self.code_body(index, locals),
+                span: Span::default(),
+            }),
+            DataType::Instance if code_address(symbol).is_some() => {
+                Declaration::Inst(vec![Instance {
+                    name,
+                    class: self.parent_name(symbol),
+                    body: self.code_body(index, locals),
+                    span: Span::default(),
+                }])
+            }
+            _ if symbol.has_flag(flags::CONST) => self.constant(index),
+            _ => Declaration::Var(vec![self.var_declaration(index)]),
+        })
+    }
+
+    fn function(&self, index: usize, locals: Range<usize>) -> Function {
+        let symbol = &self.dat.symbols[index];
+        let param_count = (symbol.count as usize).min(locals.len());
+        let params = locals.start..locals.start + param_count;
+        let returns = DataType::from_u32(symbol.offset).unwrap_or(DataType::Void);
+
+        let mut body = self.local_declarations(params.end..locals.end);
+        if let SymbolContent::Address(address) = symbol.content {
+            let code = self.code(address as u32);
+            // Every function starts by popping its parameters into their symbols.
+            let prologue = (2 * param_count).min(code.len());
+            body.extend(self.block(&code[prologue..], &Context { returns }, true));
+        }
+
+        Function {
+            name: self.ident(index),
+            typ: Identifier::new(type_name(returns)),
+            params: params.map(|param| self.var_declaration(param)).collect(),
+            body,
+            span: Span::default(),
+        }
+    }
+
+    /// Body of a prototype or instance. Instances start by calling their prototype.
+    fn code_body(&self, index: usize, locals: Range<usize>) -> Vec<Statement> {
+        let symbol = &self.dat.symbols[index];
+        let mut body = self.local_declarations(locals);
+        let address = match code_address(symbol) {
+            Some(address) => address,
+            None => return body,
+        };
+
+        let code = self.code(address);
+        let prototype = self
+            .dat
+            .symbols
+            .get(symbol.parent as usize)
+            .filter(|parent| parent.typ == DataType::Prototype)
+            .and_then(code_address);
+        let skip = match code.first() {
+            Some(&(_, Instruction::Call(target))) if Some(target) == prototype => 1,
+            _ => 0,
+        };
+
+        let context = Context {
+            returns: DataType::Void,
+        };
+        body.extend(self.block(&code[skip..], &context, true));
+        body
+    }
+
+    fn local_declarations(&self, locals: Range<usize>) -> Vec<Statement> {
+        locals
+            .map(|local| {
+                if !self.dat.symbols[local].has_flag(flags::CONST) {
+                    return Statement::VarDeclarations(vec![self.var_declaration(local)]);
+                }
+                match self.constant(local) {
+                    Declaration::ConstArray(decl) => Statement::ConstArrayDeclaration(decl),
+                    Declaration::Const(decl) => Statement::ConstDeclaration(decl),
+                    _ => unreachable!(),
+                }
+            })
+            .collect()
+    }
+
+    /// Constants are always `Declaration::Const` or `Declaration::ConstArray`.
+    fn constant(&self, index: usize) -> Declaration {
+        let symbol = &self.dat.symbols[index];
+        let typ = Identifier::new(self.type_name_of(symbol));
+        let name = self.ident(index);
+        let values: Vec<Expression> = match symbol.content {
+            SymbolContent::Int(ref values) => values
+                .iter()
+                .map(|value| Expression::Int(i64::from(*value), Span::default()))
+                .collect(),
+            SymbolContent::Float(ref values) => values
+                .iter()
+                .map(|value| Expression::Float(*value, Span::default()))
+                .collect(),
+            SymbolContent::String(ref values) => values
+                .iter()
+                .map(|value| Expression::String(StringLiteral::new(value)))
+                .collect(),
+            SymbolContent::Address(value) => vec![self.symbol_or_int(value)],
+            _ => Vec::new(),
+        };
+
+        if symbol.count > 1 {
+            Declaration::ConstArray(ConstArrayDeclaration::new(
+                typ,
+                name,
+                ArraySizeDeclaration::Size(i64::from(symbol.count), Span::default()),
+                ConstArrayInitializer::new(values),
+            ))
+        } else {
+            let value = values
+                .into_iter()
+                .next()
+                .unwrap_or(Expression::Int(0, Span::default()));
+            Declaration::Const(ConstDeclaration::new(typ, name, value))
+        }
+    }
+
+    fn var_declaration(&self, index: usize) -> VarDeclaration {
+        let symbol = &self.dat.symbols[index];
+        let array_size = if symbol.count > 1 {
+            Some(ArraySizeDeclaration::Size(
+                i64::from(symbol.count),
+                Span::default(),
+            ))
+        } else {
+            None
+        };
+
+        VarDeclaration::new(
+            Identifier::new(self.type_name_of(symbol)),
+            self.ident(index),
+            array_size,
+        )
+    }
+
+    /// Declared type of a variable. Instance variables refer to their class as parent.
+    fn type_name_of(&self, symbol: &DatSymbol) -> &'a [u8] {
+        match symbol.typ {
+            DataType::Instance if !symbol.has_flag(flags::CLASS_VAR) => {
+                match self.dat.symbols.get(symbol.parent as usize) {
+                    Some(class) => short_name(&class.name),
+                    None => type_name(symbol.typ),
+                }
+            }
+            typ => type_name(typ),
+        }
+    }
+
+    fn parent_name(&self, symbol: &DatSymbol) -> Identifier {
+        match self.dat.symbols.get(symbol.parent as usize) {
+            Some(parent) => Identifier::new(short_name(&parent.name)),
+            None => Identifier::new(b"instance"),
+        }
+    }
+
+    fn ident(&self, index: usize) -> Identifier {
+        Identifier::new(short_name(&self.dat.symbols[index].name))
+    }
+
+    /// Decodes the code starting at `address` up to the start of the next function. Addresses
+    /// outside of the code, which only malformed files have, yield no code.
+    fn code(&self, address: u32) -> Vec<(u32, Instruction)> {
+        let len = self.dat.code.len();
+        let end = self
+            .starts
+            .range(address.saturating_add(1)..)
+            .next()
+            .map_or(len, |end| (*end as usize).min(len));
+
+        let mut code = Vec::new();
+        let mut position = address as usize;
+        while position < end {
+            let instruction = match Instruction::decode(&self.dat.code[position..]) {
+                Some(instruction) => instruction,
+                None => break,
+            };
+            code.push((position as u32, instruction));
+            position += instruction.size();
+        }
+        code
+    }
+
+    /// Turns straight-line code back into statements by tracking the expressions on the
+    /// stack. Jumps are only expected in the shape that `if` statements compile to.
+    fn block(
+        &self,
+        code: &[(u32, Instruction)],
+        context: &Context,
+        top_level: bool,
+    ) -> Vec<Statement> {
+        use dat::Instruction::*;
+
+        let mut statements = Vec::new();
+        let mut stack = Vec::new();
+        let mut instance = None;
+        let mut i = 0;
+        while i < code.len() {
+            match code[i].1 {
+                PushInt(value) => stack.push(Expression::Int(i64::from(value), Span::default())),
+                PushVar(symbol) | PushInstance(symbol) => {
+                    stack.push(self.var(symbol, instance.take(), None))
+                }
+                PushArrayVar(symbol, element) => stack.push(self.var(
+                    symbol,
+                    instance.take(),
+                    Some(Expression::Int(i64::from(element), Span::default())),
+                )),
+                SetInstance(symbol) => {
+                    // Malformed code may refer to symbols that do not exist.
+                    instance = self
+                        .dat
+                        .symbols
+                        .get(symbol as usize)
+                        .map(|_| self.ident(symbol as usize))
+                }
+                Plus | Negate | Not | Complement => {
+                    let op = match code[i].1 {
+                        Plus => b'+',
+                        Negate => b'-',
+                        Not => b'!',
+                        _ => b'~',
+                    };
+                    let right = pop(&mut stack);
+                    stack.push(Expression::Unary(Box::new(UnaryExpression::new(op, right))));
+                }
+                MovI | MovS | MovSS | MovVF | MovF | MovVI | AddMovI | SubMovI | MulMovI
+                | DivMovI => {
+                    let (op, typ) = match code[i].1 {
+                        AddMovI => (AssignmentOperator::PlusEq, DataType::Int),
+                        SubMovI => (AssignmentOperator::MinusEq, DataType::Int),
+                        MulMovI => (AssignmentOperator::MultiplyEq, DataType::Int),
+                        DivMovI => (AssignmentOperator::DivideEq, DataType::Int),
+                        MovF => (AssignmentOperator::Eq, DataType::Float),
+                        MovVF => (AssignmentOperator::Eq, DataType::Func),
+                        _ => (AssignmentOperator::Eq, DataType::Int),
+                    };
+                    let target = pop(&mut stack);
+                    let value = self.reinterpret(pop(&mut stack), typ);
+                    flush(&mut stack, &mut statements);
+                    if let Expression::Identifier(var) = target {
+                        statements.push(Statement::Ass(Assignment {
+                            var: *var,
+                            op,
+                            exp: value,
+                            span: Span::default(),
+                        }));
+                    }
+                }
+                Call(address) => {
+                    if let Some(&func) = self.by_address.get(&address) {
+                        self.call(func, &mut stack, &mut statements);
+                    }
+                }
+                CallExternal(symbol) => self.call(symbol as usize, &mut stack, &mut statements),
+                Return => {
+                    let value = match context.returns {
+                        DataType::Void => None,
+                        typ => stack.pop().map(|value| self.reinterpret(value, typ)),
+                    };
+                    flush(&mut stack, &mut statements);
+                    let is_last = top_level && i + 1 == code.len();
+                    if !is_last || value.is_some() {
+                        statements.push(Statement::ReturnStatement(value, Span::default()));
+                    }
+                }
+                JumpIfZero(target) => {
+                    let cond = pop(&mut stack);
+                    flush(&mut stack, &mut statements);
+                    let (if_statement, next) = self.if_statement(code, i, target, cond, context);
+                    statements.push(Statement::If(Box::new(if_statement)));
+                    i = next;
+                    continue;
+                }
+                Nop | Jump(_) => {}
+                op => {
+                    let left = pop(&mut stack);
+                    let right = pop(&mut stack);
+                    stack.push(Expression::Binary(Box::new(BinaryExpression::new(
+                        binary_operator(op),
+                        left,
+                        right,
+                    ))));
+                }
+            }
+            i += 1;
+        }
+
+        flush(&mut stack, &mut statements);
+        statements
+    }
+
+    /// Reconstructs the `if` statement whose first condition jumps to `target` at `code[i]`.
+    /// A branch that ends in a jump forward has an `else` part, which becomes further
+    /// branches if it consists of nothing but another `if`. Returns the statement and the
+    /// index of the code following it.
+    fn if_statement(
+        &self,
+        code: &[(u32, Instruction)],
+        i: usize,
+        target: u32,
+        cond: Expression,
+        context: &Context,
+    ) -> (IfStatement, usize) {
+        let skip = position(code, target).max(i + 1);
+        let body = &code[i + 1..skip];
+
+        let mut if_statement = IfStatement {
+            branches: Vec::new(),
+            else_branch: None,
+            span: Span::default(),
+        };
+        let (body, next) = match body.last() {
+            Some(&(_, Instruction::Jump(end))) if end >= target => {
+                let end = position(code, end).max(skip);
+                let mut else_branch = self.block(&code[skip..end], context, false);
+                match (else_branch.len(), else_branch.pop()) {
+                    (1, Some(Statement::If(inner))) => {
+                        let inner = *inner;
+                        if_statement.branches = inner.branches;
+                        if_statement.else_branch = inner.else_branch;
+                    }
+                    (_, last) => {
+                        else_branch.extend(last);
+                        if_statement.else_branch = Some(else_branch);
+                    }
+                }
+                (&body[..body.len() - 1], end)
+            }
+            _ => (body, skip),
+        };
+
+        if_statement.branches.insert(
+            0,
+            IfBranch {
+                cond,
+                body: self.block(body, context, false),
+                span: Span::default(),
+            },
+        );
+        (if_statement, next)
+    }
+
+    /// Pops the arguments of a call to `func`. Calls of void functions are statements,
+    /// anything else stays on the stack as an expression.
+    fn call(&self, func: usize, stack: &mut Vec<Expression>, statements: &mut Vec<Statement>) {
+        let symbol = match self.dat.symbols.get(func) {
+            Some(symbol) => symbol,
+            None => return,
+        };
+        let count = (symbol.count as usize).min(stack.len());
+        let params = self.dat.symbols[func + 1..]
+            .iter()
+            .map(|param| param.typ)
+            .chain(::std::iter::repeat(DataType::Int));
+        let args = stack
+            .split_off(stack.len() - count)
+            .into_iter()
+            .zip(params)
+            .map(|(arg, typ)| self.reinterpret(arg, typ))
+            .collect();
+
+        let call = Expression::Call(Box::new(Call {
+            func: self.ident(func),
+            params: args,
+            span: Span::default(),
+        }));
+        if symbol.offset == DataType::Void as u32 {
+            flush(stack, statements);
+            statements.push(Statement::Exp(call));
+        } else {
+            stack.push(call);
+        }
+    }
+
+    /// Variables are pushed by index, so string literals show up as their anonymous constant.
+    fn var(
+        &self,
+        symbol: u32,
+        instance: Option<Identifier>,
+        index: Option<Expression>,
+    ) -> Expression {
+        let dat_symbol = match self.dat.symbols.get(symbol as usize) {
+            Some(dat_symbol) => dat_symbol,
+            None => return Expression::Int(i64::from(symbol), Span::default()),
+        };
+        if is_anonymous(dat_symbol) {
+            if let SymbolContent::String(ref values) = dat_symbol.content {
+                if let Some(value) = values.first() {
+                    return Expression::String(StringLiteral::new(value));
+                }
+            }
+        }
+
+        let name = self.ident(symbol as usize);
+        let var = match instance {
+            Some(instance) => VarAccess::new(instance, Some(name), index),
+            None => VarAccess::new(name, None, index),
+        };
+        Expression::Identifier(Box::new(var))
+    }
+
+    /// Floats and function references are pushed as plain integers, so their type is only
+    /// known from where they are used.
+    fn reinterpret(&self, exp: Expression, typ: DataType) -> Expression {
+        match (exp, typ) {
+            (Expression::Int(value, span), DataType::Float) => {
+                Expression::Float(f32::from_bits(value as u32), span)
+            }
+            (Expression::Int(value, _), DataType::Func) => self.symbol_or_int(value as i32),
+            (exp, _) => exp,
+        }
+    }
+
+    fn symbol_or_int(&self, value: i32) -> Expression {
+        match self.dat.symbols.get(value as usize) {
+            Some(_) if value >= 0 => self.var(value as u32, None, None),
+            _ => Expression::Int(i64::from(value), Span::default()),
+        }
+    }
+}
+
+/// Address of the code of functions, prototypes and instances. Instance variables share
+/// the type of instances but neither have code nor are constant.
+fn code_address(symbol: &DatSymbol) -> Option<u32> {
+    let has_code = match symbol.typ {
+        DataType::Func => symbol.has_flag(flags::CONST) && !symbol.has_flag(flags::EXTERNAL),
+        DataType::Prototype => true,
+        DataType::Instance => !is_anonymous(symbol) && symbol.has_flag(flags::CONST),
+        _ => false,
+    };
+
+    match symbol.content {
+        SymbolContent::Address(address) if has_code && address >= 0 => Some(address as u32),
+        _ => None,
+    }
+}
+
+/// String literals and `INSTANCE_HELP` have names starting with `0xFF`.
+fn is_anonymous(symbol: &DatSymbol) -> bool {
+    symbol.name.first() == Some(&0xFF)
+}
+
+/// Name without the scope that locals and members are qualified with.
+fn short_name(name: &[u8]) -> &[u8] {
+    match name.iter().rposition(|c| *c == b'.') {
+        Some(dot) => &name[dot + 1..],
+        None => name,
+    }
+}
+
+fn type_name(typ: DataType) -> &'static [u8] {
+    match typ {
+        DataType::Void => b"void",
+        DataType::Float => b"float",
+        DataType::Int => b"int",
+        DataType::String => b"string",
+        DataType::Class => b"class",
+        DataType::Func => b"func",
+        DataType::Prototype => b"prototype",
+        DataType::Instance => b"instance",
+    }
+}
+
+/// Index of the instruction at `address`, or of the first one after it.
+fn position(code: &[(u32, Instruction)], address: u32) -> usize {
+    code.binary_search_by_key(&address, |&(position, _)| position)
+        .unwrap_or_else(|index| index)
+}
+
+/// Malformed code may pop more than it pushed; the missing operands become zero.
+fn pop(stack: &mut Vec<Expression>) -> Expression {
+    stack
+        .pop()
+        .unwrap_or_else(|| Expression::Int(0, Span::default()))
+}
+
+/// Values that are left on the stack when a statement starts are results of calls that
+/// were used as statements.
+fn flush(stack: &mut Vec<Expression>, statements: &mut Vec<Statement>) {
+    statements.extend(stack.drain(..).map(Statement::Exp));
+}
+
+fn binary_operator(instruction: Instruction) -> BinaryOperator {
+    match instruction {
+        Instruction::Add => BinaryOperator::Plus,
+        Instruction::Sub => BinaryOperator::Minus,
+        Instruction::Mul => BinaryOperator::Multiply,
+        Instruction::Div => BinaryOperator::Divide,
+        Instruction::Mod => BinaryOperator::Mod,
+        Instruction::BitOr => BinaryOperator::BitOr,
+        Instruction::BitAnd => BinaryOperator::BitAnd,
+        Instruction::Lt => BinaryOperator::LT,
+        Instruction::Gt => BinaryOperator::GT,
+        Instruction::Or => BinaryOperator::Or,
+        Instruction::And => BinaryOperator::And,
+        Instruction::Lsl => BinaryOperator::LSL,
+        Instruction::Lsr => BinaryOperator::LSR,
+        Instruction::Le => BinaryOperator::LE,
+        Instruction::Eq => BinaryOperator::Eq,
+        Instruction::NotEq => BinaryOperator::NotEq,
+        _ => BinaryOperator::GE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codegen::compile;
+    use dat::{read, write};
+    use parsers::parse_recovering;
+    use ppa::layout::Target;
+    use ppa::name_resolver::resolve;
+    use source_map::SourceMap;
+
+    /// Compiles `content`, writes and reads back the DAT and decompiles it.
+    fn round_trip(content: &str) -> (AST, AST) {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.d", content.as_bytes().to_vec());
+        let (ast, errors) = parse_recovering(content.as_bytes(), file);
+        assert!(errors.is_empty());
+        let resolution = resolve(Some(&ast));
+        let dat = compile(Some(&ast), &resolution, &sources, Target::default()).unwrap();
+
+        let mut out = Vec::new();
+        write(&dat, &mut out).unwrap();
+        let decompiled = decompile(&read(&mut &out[..]).unwrap());
+        (ast, decompiled)
+    }
+
+    #[test]
+    fn declarations() {
+        let (ast, decompiled) = round_trip(
+            "class C_NPC { var int ID; var string NAME[2]; var func ROUTINE; };
+prototype NPC_DEFAULT(C_NPC) { ID = 1; };
+var C_NPC SELF;
+const int MAX = 3;
+const string NAMES[2] = { \"a\", \"b\" };
+const float SPEED = 1.5;
+func void RTN() { };
+instance HERO(NPC_DEFAULT) { ID = 2; ROUTINE = RTN; };",
+        );
+
+        assert_eq!(decompiled, ast);
+    }
+
+    #[test]
+    fn code() {
+        let (ast, decompiled) = round_trip(
+            "class C_NPC { var int ID; var string NAME[2]; var func ROUTINE; };
+var C_NPC SELF;
+const int MAX = 3;
+func int ADD(var int A, var int B) { var int C; C = A + B * 2; return C; };
+func void RTN() { };
+func void TEST(var C_NPC SLF) {
+    var float F;
+    F = 2.5;
+    if (ADD(1, MAX)) { SLF.NAME[1] = \"x\"; }
+    else if (!SELF.ID) { ADD(2, 3); return; }
+    else { SELF.ROUTINE = RTN; };
+    if (1 < 2) { SELF.ID -= ADD(SELF.ID, 1); };
+    RTN();
+};",
+        );
+
+        assert_eq!(decompiled, ast);
+    }
+
+    #[test]
+    fn malformed_code() {
+        let mut dat = Dat::new();
+        let mut outside = DatSymbol::new(b"OUTSIDE", DataType::Func);
+        outside.flags = flags::CONST;
+        outside.content = SymbolContent::Address(1000);
+        let mut unknown = DatSymbol::new(b"UNKNOWN", DataType::Func);
+        unknown.flags = flags::CONST;
+        unknown.content = SymbolContent::Address(0);
+        let mut later = DatSymbol::new(b"LATER", DataType::Func);
+        later.flags = flags::CONST;
+        later.content = SymbolContent::Address(2000);
+        dat.symbols = vec![outside, unknown, later];
+        for instruction in &[
+            Instruction::SetInstance(9999),
+            Instruction::PushVar(1),
+            Instruction::Return,
+        ] {
+            instruction.encode(&mut dat.code);
+        }
+
+        let ast = decompile(&dat);
+        assert_eq!(ast.declarations.len(), 3);
+        match ast.declarations[0] {
+            Declaration::Func(ref func) => assert!(func.body.is_empty()),
+            ref other => panic!("expected a function, found {:?}", other),
+        }
+    }
+
+    #[test]
+    fn int_assigned_to_float() {
+        let (_, decompiled) = round_trip("var float F; func void TEST() { F = 1; };");
+
+        match decompiled.declarations[1] {
+            Declaration::Func(ref func) => match func.body[0] {
+                Statement::Ass(ref ass) => {
+                    assert_eq!(ass.exp, Expression::Float(1.0, Span::default()))
+                }
+                ref other => panic!("expected an assignment, found {:?}", other),
+            },
+            ref other => panic!("expected a function, found {:?}", other),
+        }
+    }
+}
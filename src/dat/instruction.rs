@@ -0,0 +1,225 @@
+/// A single ZenGin bytecode instruction (`zPAR_TOK_*` / `zPAR_OP_*`).
+///
+/// Binary operators pop their left operand first, so it has to be pushed last.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Instruction {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    BitOr,
+    BitAnd,
+    Lt,
+    Gt,
+    MovI,
+    Or,
+    And,
+    Lsl,
+    Lsr,
+    Le,
+    Eq,
+    NotEq,
+    Ge,
+    AddMovI,
+    SubMovI,
+    MulMovI,
+    DivMovI,
+    Plus,
+    Negate,
+    Not,
+    Complement,
+    Nop,
+    Return,
+    Call(u32),
+    CallExternal(u32),
+    PushInt(i32),
+    PushVar(u32),
+    PushInstance(u32),
+    MovS,
+    MovSS,
+    MovVF,
+    MovF,
+    MovVI,
+    Jump(u32),
+    JumpIfZero(u32),
+    SetInstance(u32),
+    PushArrayVar(u32, u8),
+}
+
+impl Instruction {
+    pub fn opcode(&self) -> u8 {
+        use self::Instruction::*;
+
+        match *self {
+            Add => 0,
+            Sub => 1,
+            Mul => 2,
+            Div => 3,
+            Mod => 4,
+            BitOr => 5,
+            BitAnd => 6,
+            Lt => 7,
+            Gt => 8,
+            MovI => 9,
+            Or => 11,
+            And => 12,
+            Lsl => 13,
+            Lsr => 14,
+            Le => 15,
+            Eq => 16,
+            NotEq => 17,
+            Ge => 18,
+            AddMovI => 19,
+            SubMovI => 20,
+            MulMovI => 21,
+            DivMovI => 22,
+            Plus => 30,
+            Negate => 31,
+            Not => 32,
+            Complement => 33,
+            Nop => 45,
+            Return => 60,
+            Call(_) => 61,
+            CallExternal(_) => 62,
+            PushInt(_) => 64,
+            PushVar(_) => 65,
+            PushInstance(_) => 67,
+            MovS => 70,
+            MovSS => 71,
+            MovVF => 72,
+            MovF => 73,
+            MovVI => 74,
+            Jump(_) => 75,
+            JumpIfZero(_) => 76,
+            SetInstance(_) => 80,
+            PushArrayVar(..) => 245,
+        }
+    }
+
+    /// Encoded size in bytes.
+    pub fn size(&self) -> usize {
+        use self::Instruction::*;
+
+        match *self {
+            Call(_) | CallExternal(_) | PushInt(_) | PushVar(_) | PushInstance(_) | Jump(_)
+            | JumpIfZero(_) | SetInstance(_) => 5,
+            PushArrayVar(..) => 6,
+            _ => 1,
+        }
+    }
+
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        use self::Instruction::*;
+
+        out.push(self.opcode());
+        match *self {
+            Call(operand)
+            | CallExternal(operand)
+            | PushVar(operand)
+            | PushInstance(operand)
+            | Jump(operand)
+            | JumpIfZero(operand)
+            | SetInstance(operand) => out.extend_from_slice(&operand.to_le_bytes()),
+            PushInt(value) => out.extend_from_slice(&value.to_le_bytes()),
+            PushArrayVar(symbol, index) => {
+                out.extend_from_slice(&symbol.to_le_bytes());
+                out.push(index);
+            }
+            _ => {}
+        }
+    }
+
+    /// Decodes the instruction at the start of `code`, or `None` if it is truncated or
+    /// its opcode is unknown.
+    pub fn decode(code: &[u8]) -> Option<Instruction> {
+        use self::Instruction::*;
+
+        let operand = || -> Option<u32> {
+            let bytes = code.get(1..5)?;
+            Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        };
+
+        Some(match *code.first()? {
+            0 => Add,
+            1 => Sub,
+            2 => Mul,
+            3 => Div,
+            4 => Mod,
+            5 => BitOr,
+            6 => BitAnd,
+            7 => Lt,
+            8 => Gt,
+            9 => MovI,
+            11 => Or,
+            12 => And,
+            13 => Lsl,
+            14 => Lsr,
+            15 => Le,
+            16 => Eq,
+            17 => NotEq,
+            18 => Ge,
+            19 => AddMovI,
+            20 => SubMovI,
+            21 => MulMovI,
+            22 => DivMovI,
+            30 => Plus,
+            31 => Negate,
+            32 => Not,
+            33 => Complement,
+            45 => Nop,
+            60 => Return,
+            61 => Call(operand()?),
+            62 => CallExternal(operand()?),
+            64 => PushInt(operand()? as i32),
+            65 => PushVar(operand()?),
+            67 => PushInstance(operand()?),
+            70 => MovS,
+            71 => MovSS,
+            72 => MovVF,
+            73 => MovF,
+            74 => MovVI,
+            75 => Jump(operand()?),
+            76 => JumpIfZero(operand()?),
+            80 => SetInstance(operand()?),
+            245 => PushArrayVar(operand()?, *code.get(5)?),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding() {
+        let mut code = Vec::new();
+        for instruction in &[
+            Instruction::PushInt(-2),
+            Instruction::PushArrayVar(0x0102, 3),
+            Instruction::Sub,
+        ] {
+            instruction.encode(&mut code);
+        }
+
+        assert_eq!(
+            code,
+            vec![64, 0xFE, 0xFF, 0xFF, 0xFF, 245, 0x02, 0x01, 0, 0, 3, 1]
+        );
+        assert_eq!(Instruction::PushArrayVar(0, 0).size(), 6);
+    }
+
+    #[test]
+    fn decoding() {
+        let code = [64, 0xFE, 0xFF, 0xFF, 0xFF, 245, 0x02, 0x01, 0, 0, 3, 1];
+        assert_eq!(Instruction::decode(&code), Some(Instruction::PushInt(-2)));
+        assert_eq!(
+            Instruction::decode(&code[5..]),
+            Some(Instruction::PushArrayVar(0x0102, 3))
+        );
+        assert_eq!(Instruction::decode(&code[11..]), Some(Instruction::Sub));
+        assert_eq!(Instruction::decode(&code[..3]), None);
+        assert_eq!(Instruction::decode(&[10]), None);
+    }
+}
@@ -0,0 +1,52 @@
+//! The binary `.dat` format that the ZenGin loads compiled scripts from, e.g. `GOTHIC.DAT`,
+//! `MENU.DAT` or `FIGHT.DAT`.
+
+mod decompiler;
+mod instruction;
+mod reader;
+mod symbol;
+mod writer;
+
+pub use self::decompiler::decompile;
+pub use self::instruction::Instruction;
+pub use self::reader::read;
+pub use self::symbol::{flags, DatSymbol, DataType, SymbolContent};
+pub use self::writer::write;
+
+/// Version byte that Gothic 1 and 2 write at the start of every DAT file.
+pub const VERSION: u8 = 50;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Dat {
+    pub version: u8,
+    pub symbols: Vec<DatSymbol>,
+    pub code: Vec<u8>,
+}
+
+impl Dat {
+    pub fn new() -> Self {
+        Dat {
+            version: VERSION,
+            symbols: Vec::new(),
+            code: Vec::new(),
+        }
+    }
+
+    /// Indices of all symbols, ordered by name. The engine uses this table to
+    /// look up symbols by binary search.
+    pub fn sort_table(&self) -> Vec<u32> {
+        let mut table: Vec<u32> = (0..self.symbols.len() as u32).collect();
+        table.sort_by(|a, b| {
+            self.symbols[*a as usize]
+                .name
+                .cmp(&self.symbols[*b as usize].name)
+        });
+        table
+    }
+}
+
+impl Default for Dat {
+    fn default() -> Self {
+        Dat::new()
+    }
+}
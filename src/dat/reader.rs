@@ -0,0 +1,162 @@
+use dat::{flags, Dat, DatSymbol, DataType, SymbolContent};
+use std::io::{self, Read};
+
+/// Parses a DAT file as written by the original compiler or by `write`.
+pub fn read<R: Read>(input: &mut R) -> io::Result<Dat> {
+    let mut reader = Reader { input };
+
+    let version = reader.u8()?;
+    let count = reader.u32()?;
+    for _ in 0..count {
+        // The sort table is derived from the names and rebuilt when writing.
+        reader.u32()?;
+    }
+
+    let mut symbols = Vec::new();
+    for _ in 0..count {
+        symbols.push(reader.symbol()?);
+    }
+
+    // The size is not trusted to allocate the code up front, the file may be truncated.
+    let code_size = reader.u32()?;
+    let mut code = Vec::new();
+    reader
+        .input
+        .take(u64::from(code_size))
+        .read_to_end(&mut code)?;
+    if code.len() < code_size as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "the code is shorter than its size",
+        ));
+    }
+
+    Ok(Dat {
+        version,
+        symbols,
+        code,
+    })
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+struct Reader<'a, R: Read + 'a> {
+    input: &'a mut R,
+}
+
+impl<'a, R: Read> Reader<'a, R> {
+    fn u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0; 1];
+        self.input.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        let mut buf = [0; 4];
+        self.input.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn i32(&mut self) -> io::Result<i32> {
+        Ok(self.u32()? as i32)
+    }
+
+    fn line(&mut self) -> io::Result<Vec<u8>> {
+        let mut line = Vec::new();
+        loop {
+            match self.u8()? {
+                b'\n' => return Ok(line),
+                byte => line.push(byte),
+            }
+        }
+    }
+
+    fn symbol(&mut self) -> io::Result<DatSymbol> {
+        let name = match self.u32()? {
+            0 => Vec::new(),
+            _ => self.line()?,
+        };
+        let offset = self.u32()?;
+        let properties = self.u32()?;
+        let typ = DataType::from_u32((properties >> 12) & 0xF)
+            .ok_or_else(|| invalid("unknown symbol type"))?;
+
+        let mut symbol = DatSymbol::new(&name, typ);
+        symbol.offset = offset;
+        symbol.count = properties & 0xFFF;
+        symbol.flags = (properties >> 16) & 0x3F;
+        symbol.file_index = self.u32()? & 0x7FFFF;
+        symbol.line_start = self.u32()? & 0x7FFFF;
+        symbol.line_count = self.u32()? & 0x7FFFF;
+        symbol.char_start = self.u32()? & 0xFFFFFF;
+        symbol.char_count = self.u32()? & 0xFFFFFF;
+
+        if !symbol.has_flag(flags::CLASS_VAR) {
+            let count = symbol.count as usize;
+            symbol.content = match typ {
+                DataType::Void => SymbolContent::None,
+                DataType::Float => SymbolContent::Float(
+                    (0..count)
+                        .map(|_| self.u32().map(f32::from_bits))
+                        .collect::<io::Result<_>>()?,
+                ),
+                DataType::Int => {
+                    SymbolContent::Int((0..count).map(|_| self.i32()).collect::<io::Result<_>>()?)
+                }
+                DataType::String => SymbolContent::String(
+                    (0..count).map(|_| self.line()).collect::<io::Result<_>>()?,
+                ),
+                DataType::Class => SymbolContent::ClassOffset(self.i32()?),
+                DataType::Func | DataType::Prototype | DataType::Instance => {
+                    SymbolContent::Address(self.i32()?)
+                }
+            };
+        }
+
+        symbol.parent = self.i32()?;
+        Ok(symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dat::write;
+
+    #[test]
+    fn round_trip() {
+        let mut dat = Dat::new();
+        let mut class = DatSymbol::new(b"C", DataType::Class);
+        class.count = 1;
+        class.offset = 20;
+        class.content = SymbolContent::ClassOffset(0);
+        let mut member = DatSymbol::new(b"C.NAME", DataType::String);
+        member.count = 1;
+        member.flags = flags::CLASS_VAR;
+        member.parent = 0;
+        let mut floats = DatSymbol::new(b"F", DataType::Float);
+        floats.count = 2;
+        floats.flags = flags::CONST;
+        floats.line_start = 3;
+        floats.content = SymbolContent::Float(vec![1.5, -2.0]);
+        let mut func = DatSymbol::new(b"FOO", DataType::Func);
+        func.flags = flags::CONST;
+        func.content = SymbolContent::Address(0);
+        dat.symbols = vec![class, member, floats, func];
+        dat.code = vec![60];
+
+        let mut out = Vec::new();
+        write(&dat, &mut out).unwrap();
+        assert_eq!(read(&mut &out[..]).unwrap(), dat);
+
+        let truncated = read(&mut &out[..out.len() - 1]).unwrap_err();
+        assert_eq!(truncated.kind(), io::ErrorKind::UnexpectedEof);
+
+        let code_size = out.len() - dat.code.len() - 4;
+        out[code_size..code_size + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        let oversized = read(&mut &out[..]).unwrap_err();
+        assert_eq!(oversized.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,99 @@
+/// Type of a DAT symbol, as stored in bits 12 to 15 of its properties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataType {
+    Void = 0,
+    Float = 1,
+    Int = 2,
+    String = 3,
+    Class = 4,
+    Func = 5,
+    Prototype = 6,
+    Instance = 7,
+}
+
+impl DataType {
+    pub fn from_u32(value: u32) -> Option<DataType> {
+        use self::DataType::*;
+
+        Some(match value {
+            0 => Void,
+            1 => Float,
+            2 => Int,
+            3 => String,
+            4 => Class,
+            5 => Func,
+            6 => Prototype,
+            7 => Instance,
+            _ => return None,
+        })
+    }
+}
+
+/// Symbol flags, stored in bits 16 to 21 of the properties.
+pub mod flags {
+    pub const CONST: u32 = 1;
+    pub const RETURN: u32 = 2;
+    pub const CLASS_VAR: u32 = 4;
+    pub const EXTERNAL: u32 = 8;
+    pub const MERGED: u32 = 16;
+}
+
+/// The data of a symbol. Class members have none, everything else has the kind
+/// of data that its type dictates.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SymbolContent {
+    None,
+    Float(Vec<f32>),
+    Int(Vec<i32>),
+    String(Vec<Vec<u8>>),
+    /// Offset of the class within the engine object it is mapped to.
+    ClassOffset(i32),
+    /// Bytecode address of functions, prototypes and instances.
+    Address(i32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DatSymbol {
+    pub name: Vec<u8>,
+    /// Member offset for class members, size for classes and return type for functions.
+    pub offset: u32,
+    pub count: u32,
+    pub typ: DataType,
+    pub flags: u32,
+    pub file_index: u32,
+    pub line_start: u32,
+    pub line_count: u32,
+    pub char_start: u32,
+    pub char_count: u32,
+    pub content: SymbolContent,
+    /// Index of the parent symbol, or -1.
+    pub parent: i32,
+}
+
+impl DatSymbol {
+    pub fn new(name: &[u8], typ: DataType) -> Self {
+        DatSymbol {
+            name: name.to_vec(),
+            offset: 0,
+            count: 0,
+            typ,
+            flags: 0,
+            file_index: 0,
+            line_start: 0,
+            line_count: 0,
+            char_start: 0,
+            char_count: 0,
+            content: SymbolContent::None,
+            parent: -1,
+        }
+    }
+
+    pub fn has_flag(&self, flag: u32) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// Packed count, type and flags, as they are stored in the file.
+    pub fn properties(&self) -> u32 {
+        (self.count & 0xFFF) | ((self.typ as u32) << 12) | ((self.flags & 0x3F) << 16)
+    }
+}
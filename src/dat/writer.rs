@@ -0,0 +1,100 @@
+use dat::{flags, Dat, DatSymbol, SymbolContent};
+use std::io::{self, Write};
+
+/// Serializes `dat` in the layout the ZenGin expects: version, symbol count, sort table,
+/// symbols and finally the size-prefixed bytecode.
+pub fn write<W: Write>(dat: &Dat, out: &mut W) -> io::Result<()> {
+    out.write_all(&[dat.version])?;
+    write_u32(out, dat.symbols.len() as u32)?;
+    for index in dat.sort_table() {
+        write_u32(out, index)?;
+    }
+
+    for symbol in &dat.symbols {
+        write_symbol(symbol, out)?;
+    }
+
+    write_u32(out, dat.code.len() as u32)?;
+    out.write_all(&dat.code)
+}
+
+fn write_u32<W: Write>(out: &mut W, value: u32) -> io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_line<W: Write>(out: &mut W, line: &[u8]) -> io::Result<()> {
+    out.write_all(line)?;
+    out.write_all(b"\n")
+}
+
+fn write_symbol<W: Write>(symbol: &DatSymbol, out: &mut W) -> io::Result<()> {
+    write_u32(out, 1)?;
+    write_line(out, &symbol.name)?;
+    write_u32(out, symbol.offset)?;
+    write_u32(out, symbol.properties())?;
+    write_u32(out, symbol.file_index & 0x7FFFF)?;
+    write_u32(out, symbol.line_start & 0x7FFFF)?;
+    write_u32(out, symbol.line_count & 0x7FFFF)?;
+    write_u32(out, symbol.char_start & 0xFFFFFF)?;
+    write_u32(out, symbol.char_count & 0xFFFFFF)?;
+
+    if !symbol.has_flag(flags::CLASS_VAR) {
+        match symbol.content {
+            SymbolContent::None => {}
+            SymbolContent::Float(ref values) => {
+                for value in values {
+                    out.write_all(&value.to_le_bytes())?;
+                }
+            }
+            SymbolContent::Int(ref values) => {
+                for value in values {
+                    out.write_all(&value.to_le_bytes())?;
+                }
+            }
+            SymbolContent::String(ref values) => {
+                for value in values {
+                    write_line(out, value)?;
+                }
+            }
+            SymbolContent::ClassOffset(value) | SymbolContent::Address(value) => {
+                out.write_all(&value.to_le_bytes())?
+            }
+        }
+    }
+
+    out.write_all(&symbol.parent.to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dat::DataType;
+
+    #[test]
+    fn layout() {
+        let mut dat = Dat::new();
+        let mut b = DatSymbol::new(b"B", DataType::Int);
+        b.count = 1;
+        b.flags = flags::CONST;
+        b.content = SymbolContent::Int(vec![7]);
+        let mut a = DatSymbol::new(b"A", DataType::Func);
+        a.content = SymbolContent::Address(0);
+        dat.symbols = vec![b, a];
+        dat.code = vec![60];
+
+        let mut out = Vec::new();
+        write(&dat, &mut out).unwrap();
+
+        let mut expected = vec![50, 2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0];
+        expected.extend_from_slice(&[1, 0, 0, 0, b'B', b'\n', 0, 0, 0, 0]);
+        expected.extend_from_slice(&[1, 0x20, 1, 0]);
+        expected.extend_from_slice(&[0; 20]);
+        expected.extend_from_slice(&[7, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        expected.extend_from_slice(&[1, 0, 0, 0, b'A', b'\n', 0, 0, 0, 0]);
+        expected.extend_from_slice(&[0, 0x50, 0, 0]);
+        expected.extend_from_slice(&[0; 20]);
+        expected.extend_from_slice(&[0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]);
+        expected.extend_from_slice(&[1, 0, 0, 0, 60]);
+        assert_eq!(out, expected);
+    }
+}
@@ -0,0 +1,499 @@
+//! Errors in a structured form that can be printed for humans or emitted as JSON or SARIF,
+//! e.g. to annotate pull requests in CI.
+
+use errors::Error;
+use inner_errors::LinkerError;
+use serde_json::Value;
+use source_map::{decode, file_uri, SourceFile, SourceMap};
+use std::str::FromStr;
+use types::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Severity::Error),
+            "warning" => Ok(Severity::Warning),
+            "note" => Ok(Severity::Note),
+            other => Err(format!("unknown severity {}", other)),
+        }
+    }
+}
+
+/// A secondary location of a diagnostic, like the previous definition of a duplicate symbol.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Stable name of the kind of problem, e.g. `missing-semicolon`.
+    pub code: &'static str,
+    pub message: String,
+    /// Where the problem is, if it is in a script at all.
+    pub span: Option<Span>,
+    pub related: Vec<Label>,
+}
+
+impl<'a> From<&'a Error> for Diagnostic {
+    fn from(err: &'a Error) -> Self {
+        let (code, message, span) = match *err {
+            Error::ParsingError { err, span, .. } => {
+                (err.code(), err.description().to_owned(), Some(span))
+            }
+            Error::IOError(ref e) => ("io", e.to_string(), None),
+            Error::SrcError(ref e) => (e.code(), e.to_string(), Some(e.span())),
+            Error::LinkingError(ref e) => (e.code(), e.to_string(), Some(e.span())),
+            Error::TypeCheckError(ref e) => (e.code(), e.to_string(), Some(e.span())),
+            Error::ConstError(ref e) => (e.code(), e.to_string(), Some(e.span())),
+            Error::CodegenError(ref e) => (e.code(), e.to_string(), Some(e.span())),
+        };
+
+        let mut related = Vec::new();
+        match *err {
+            Error::LinkingError(LinkerError::DuplicateSymbol { previous, .. }) => {
+                related.push(Label {
+                    span: previous,
+                    message: "previously defined here".to_owned(),
+                })
+            }
+            Error::ParsingError {
+                block: Some(block), ..
+            } => related.push(Label {
+                span: block,
+                message: "block opened here".to_owned(),
+            }),
+            _ => {}
+        }
+
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            span,
+            related,
+        }
+    }
+}
+
+impl Diagnostic {
+    /// The message followed by the source lines it refers to, with the span of the problem
+    /// underlined and the related locations labelled, like rustc does:
+    ///
+    /// ```text
+    /// error[duplicate-symbol]: Symbol is already defined: 'x'
+    ///  --> a.d:2:9
+    ///   |
+    /// 1 | var int x;
+    ///   |         - previously defined here
+    /// 2 | var int x;
+    ///   |         ^
+    /// ```
+    pub fn to_human(&self, sources: &SourceMap) -> String {
+        let mut out = format!(
+            "{}[{}]: {}",
+            self.severity.as_str(),
+            self.code,
+            self.message
+        );
+
+        let primary = self.span.map(|span| Annotation {
+            span,
+            message: "",
+            primary: true,
+        });
+        let secondary = self.related.iter().map(|label| Annotation {
+            span: label.span,
+            message: &label.message,
+            primary: false,
+        });
+
+        // Labels in the same file as the problem share its snippet, the others get their own.
+        let mut snippets: Vec<(&SourceFile, Vec<Annotation>)> = Vec::new();
+        for annotation in primary.into_iter().chain(secondary) {
+            let file = match sources.get(annotation.span.file) {
+                Some(file) => file,
+                None => continue,
+            };
+            match snippets.iter_mut().find(|(other, _)| other.id == file.id) {
+                Some((_, annotations)) => annotations.push(annotation),
+                None => snippets.push((file, vec![annotation])),
+            }
+        }
+
+        let last_line = snippets
+            .iter()
+            .flat_map(|(file, annotations)| {
+                annotations
+                    .iter()
+                    .map(move |annotation| file.line_col(annotation.span.start).line)
+            })
+            .max()
+            .unwrap_or(0);
+        let gutter = " ".repeat(last_line.to_string().len());
+
+        for (i, (file, mut annotations)) in snippets.into_iter().enumerate() {
+            let start = file.line_col(annotations[0].span.start);
+            out.push_str(&format!(
+                "\n{}{} {}:{}:{}",
+                gutter,
+                if i == 0 { "-->" } else { ":::" },
+                file.path.display(),
+                start.line,
+                start.column
+            ));
+            out.push_str(&format!("\n{} |", gutter));
+
+            annotations.sort_by_key(|annotation| annotation.span.start);
+            let mut previous_line = None;
+            for annotation in &annotations {
+                let start = file.line_col(annotation.span.start);
+                let (line_start, line_end) = file.lines.line_range(start.line);
+                if previous_line != Some(start.line) {
+                    if previous_line.is_some_and(|previous| previous + 1 < start.line) {
+                        out.push_str("\n...");
+                    }
+                    let text = decode(trim_cr(&file.content[line_start..line_end]));
+                    out.push_str(&format!(
+                        "\n{:>width$} | {}",
+                        start.line,
+                        text.replace('\t', TAB),
+                        width = gutter.len()
+                    ));
+                    previous_line = Some(start.line);
+                }
+
+                // Spans over several lines are only underlined up to the end of the first one.
+                let end = annotation.span.end.clamp(annotation.span.start, line_end);
+                let indent = display_width(&file.content[line_start..annotation.span.start]);
+                let length = display_width(&file.content[annotation.span.start..end]).max(1);
+                let mark = if annotation.primary { "^" } else { "-" };
+                let underline = format!(
+                    "{} | {}{} {}",
+                    gutter,
+                    " ".repeat(indent),
+                    mark.repeat(length),
+                    annotation.message
+                );
+                out.push('\n');
+                out.push_str(underline.trim_end());
+            }
+        }
+        out
+    }
+
+    /// A JSON object with the file and the 1-based lines and columns of every location.
+    pub fn to_json(&self, sources: &SourceMap) -> Value {
+        let related: Vec<Value> = self
+            .related
+            .iter()
+            .map(|label| {
+                let mut location = json_location(sources, Some(label.span));
+                location["message"] = json!(label.message);
+                location
+            })
+            .collect();
+
+        let mut diagnostic = json_location(sources, self.span);
+        diagnostic["severity"] = json!(self.severity.as_str());
+        diagnostic["code"] = json!(self.code);
+        diagnostic["message"] = json!(self.message);
+        diagnostic["related"] = json!(related);
+        diagnostic
+    }
+}
+
+/// A location to underline in `Diagnostic::to_human`.
+struct Annotation<'a> {
+    span: Span,
+    message: &'a str,
+    primary: bool,
+}
+
+const TAB: &str = "    ";
+
+fn trim_cr(line: &[u8]) -> &[u8] {
+    match line.split_last() {
+        Some((b'\r', rest)) => rest,
+        _ => line,
+    }
+}
+
+/// The number of columns `bytes` take up on a terminal, with tabs expanded to `TAB`.
+fn display_width(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .map(|b| if *b == b'\t' { TAB.len() } else { 1 })
+        .sum()
+}
+
+fn json_location(sources: &SourceMap, span: Option<Span>) -> Value {
+    let resolved = span.and_then(|span| sources.resolve(span).map(|location| (span, location)));
+    match resolved {
+        Some((span, (path, start, end))) => json!({
+            "file": path.to_string_lossy(),
+            "span": {
+                "start": {"offset": span.start, "line": start.line, "column": start.column},
+                "end": {"offset": span.end, "line": end.line, "column": end.column},
+            },
+        }),
+        None => json!({"file": null, "span": null}),
+    }
+}
+
+/// A complete SARIF 2.1.0 log of a run that reported `diagnostics`.
+pub fn to_sarif(diagnostics: &[Diagnostic], sources: &SourceMap) -> Value {
+    let mut rules: Vec<&'static str> = diagnostics.iter().map(|d| d.code).collect();
+    rules.sort();
+    rules.dedup();
+
+    // Paths relative to the working directory, usually the repository, work best in CI.
+    let current_dir = ::std::env::current_dir().unwrap_or_default();
+    let location = |span: Span| {
+        let (path, start, end) = sources.resolve(span)?;
+        let path = path.strip_prefix(&current_dir).unwrap_or(path);
+        let uri = if path.is_absolute() {
+            file_uri(path)
+        } else {
+            path.to_string_lossy().replace('\\', "/")
+        };
+        Some(json!({
+            "physicalLocation": {
+                "artifactLocation": {"uri": uri},
+                "region": {
+                    "startLine": start.line,
+                    "startColumn": start.column,
+                    "endLine": end.line,
+                    "endColumn": end.column,
+                },
+            },
+        }))
+    };
+
+    let results: Vec<Value> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let related: Vec<Value> = diagnostic
+                .related
+                .iter()
+                .filter_map(|label| {
+                    let mut related = location(label.span)?;
+                    related["message"] = json!({"text": label.message});
+                    Some(related)
+                })
+                .collect();
+
+            json!({
+                "ruleId": diagnostic.code,
+                "ruleIndex": rules.binary_search(&diagnostic.code).unwrap(),
+                "level": diagnostic.severity.as_str(),
+                "message": {"text": diagnostic.message},
+                "locations": diagnostic.span.and_then(location).into_iter().collect::<Vec<_>>(),
+                "relatedLocations": related,
+            })
+        })
+        .collect();
+
+    let rules: Vec<Value> = rules.iter().map(|rule| json!({"id": rule})).collect();
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "parsiphae",
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                },
+            },
+            "results": results,
+        }],
+    })
+}
+
+/// How diagnostics are printed by the command line tool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageFormat {
+    /// Text on stderr.
+    Human,
+    /// One JSON object per line on stdout.
+    Json,
+    /// A single SARIF log on stdout, printed once the run is over.
+    Sarif,
+}
+
+impl MessageFormat {
+    pub const VALUES: &'static [&'static str] = &["human", "json", "sarif"];
+}
+
+impl FromStr for MessageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "human" => Ok(MessageFormat::Human),
+            "json" => Ok(MessageFormat::Json),
+            "sarif" => Ok(MessageFormat::Sarif),
+            other => Err(format!("unknown message format {}", other)),
+        }
+    }
+}
+
+/// Prints all `diagnostics` of a run in `format`.
+pub fn emit(diagnostics: &[Diagnostic], sources: &SourceMap, format: MessageFormat) {
+    match format {
+        MessageFormat::Human => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", diagnostic.to_human(sources));
+            }
+        }
+        MessageFormat::Json => {
+            for diagnostic in diagnostics {
+                println!("{}", diagnostic.to_json(sources));
+            }
+        }
+        MessageFormat::Sarif => println!("{}", to_sarif(diagnostics, sources)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inner_errors::ParserError;
+    use parsers::parse_recovering;
+    use types::{FileId, Identifier};
+
+    fn sources() -> SourceMap {
+        let mut sources = SourceMap::new();
+        sources.add("a.d", b"var int x;\nvar int x;".to_vec());
+        sources
+    }
+
+    fn duplicate() -> Diagnostic {
+        let name = Identifier::new(b"x").with_span(Span::new(FileId(0), 19, 20));
+        Diagnostic::from(&Error::LinkingError(LinkerError::DuplicateSymbol {
+            name,
+            previous: Span::new(FileId(0), 8, 9),
+        }))
+    }
+
+    #[test]
+    fn human() {
+        assert_eq!(
+            duplicate().to_human(&sources()),
+            "error[duplicate-symbol]: Symbol is already defined: 'x'
+ --> a.d:2:9
+  |
+1 | var int x;
+  |         - previously defined here
+2 | var int x;
+  |         ^"
+        );
+    }
+
+    #[test]
+    fn human_unclosed_block() {
+        let mut sources = SourceMap::new();
+        let content = b"func void f() {\n\tx = \"\xe4\";\n\n\n\n\n\n\n\nfunc void g() {};";
+        sources.add("a.d", content.to_vec());
+        let (_, errors) = parse_recovering(content, FileId(0));
+
+        assert_eq!(
+            Diagnostic::from(&errors[0]).to_human(&sources),
+            "error[missing-semicolon]: Missing semicolon
+  --> a.d:10:6
+   |
+ 1 | func void f() {
+   |               - block opened here
+...
+10 | func void g() {};
+   |      ^"
+        );
+
+        let missing_semi = Diagnostic::from(&Error::ParsingError {
+            err: ParserError::MissingSemi,
+            span: Span::new(FileId(0), 17, 25),
+            line: 2,
+            block: None,
+        });
+        assert_eq!(
+            missing_semi.to_human(&sources),
+            "error[missing-semicolon]: Missing semicolon
+ --> a.d:2:2
+  |
+2 |     x = \"ä\";
+  |     ^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            duplicate().to_json(&sources()),
+            json!({
+                "severity": "error",
+                "code": "duplicate-symbol",
+                "message": "Symbol is already defined: 'x'",
+                "file": "a.d",
+                "span": {
+                    "start": {"offset": 19, "line": 2, "column": 9},
+                    "end": {"offset": 20, "line": 2, "column": 10},
+                },
+                "related": [{
+                    "message": "previously defined here",
+                    "file": "a.d",
+                    "span": {
+                        "start": {"offset": 8, "line": 1, "column": 9},
+                        "end": {"offset": 9, "line": 1, "column": 10},
+                    },
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn sarif() {
+        let sarif = to_sarif(&[duplicate()], &sources());
+        let run = &sarif["runs"][0];
+
+        assert_eq!(sarif["version"], json!("2.1.0"));
+        assert_eq!(
+            run["tool"]["driver"]["rules"],
+            json!([{"id": "duplicate-symbol"}])
+        );
+        assert_eq!(run["results"][0]["ruleIndex"], json!(0));
+        assert_eq!(
+            run["results"][0]["locations"][0]["physicalLocation"],
+            json!({
+                "artifactLocation": {"uri": "a.d"},
+                "region": {"startLine": 2, "startColumn": 9, "endLine": 2, "endColumn": 10},
+            })
+        );
+        assert_eq!(
+            run["results"][0]["relatedLocations"][0]["message"],
+            json!({"text": "previously defined here"})
+        );
+    }
+}
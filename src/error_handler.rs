@@ -1,9 +1,9 @@
 use errors;
 use inner_errors::ParserError;
-use std::path::Path;
-use types::{Input, PrintableByteSlice};
+use source_map::LineIndex;
+use types::{FileId, Input, Span};
 
-pub fn map_err(input: &[u8], err: ::nom::Err<Input, ParserError>) -> errors::Error {
+pub fn map_err(input: &[u8], file: FileId, err: ::nom::Err<Input, ParserError>) -> errors::Error {
     use nom::Err;
 
     match err {
@@ -11,31 +11,39 @@ pub fn map_err(input: &[u8], err: ::nom::Err<Input, ParserError>) -> errors::Err
 
         Err::Failure(ref context) | Err::Error(ref context) => {
             let errors = ::nom::error_to_list(context);
-            {
-                let printable_errors: Vec<_> = errors.iter().map(|(_leftover, err)| err).collect();
-                println!("{:#?}", printable_errors);
-            }
             let relevant_errors = custom_parser_errors(&errors);
             match &relevant_errors[..] {
-                [.., (leftover, err)] => map_single_error(input, leftover, **err),
+                [.., (leftover, err)] => map_single_error(input, file, leftover, **err),
                 _ => errors::Error::ParsingError {
                     err: ParserError::FromNom,
+                    span: Span::new(file, 0, 0),
                     line: 0,
+                    block: None,
                 },
             }
         }
     }
 }
 
-pub fn map_single_error(input: &[u8], leftover: &Input, err: ParserError) -> errors::Error {
+pub fn map_single_error(
+    input: &[u8],
+    file: FileId,
+    leftover: &Input,
+    err: ParserError,
+) -> errors::Error {
     let offset = input.len() - leftover.0.len();
     let line = get_line_number(input, offset);
 
-    errors::Error::ParsingError { err, line }
+    errors::Error::ParsingError {
+        err,
+        span: Span::new(file, offset, offset),
+        line,
+        block: None,
+    }
 }
 
 fn custom_parser_errors<'a>(
-    errors: &'a Vec<(Input, ::nom::ErrorKind<ParserError>)>,
+    errors: &'a [(Input, ::nom::ErrorKind<ParserError>)],
 ) -> Vec<(&'a Input<'a>, &'a ParserError)> {
     errors
         .iter()
@@ -47,16 +55,15 @@ fn custom_parser_errors<'a>(
 }
 
 fn split_off_nom_errors<'a>(
-    errors: &'a Vec<(Input, ::nom::ErrorKind<ParserError>)>,
+    errors: &'a [(Input<'a>, ::nom::ErrorKind<ParserError>)],
 ) -> &'a [(Input<'a>, ::nom::ErrorKind<ParserError>)] {
-    let pos = errors.iter().rposition(|(_leftover, kind)| match kind {
-        ::nom::ErrorKind::Custom(_) => true,
-        _ => false,
-    });
+    let pos = errors
+        .iter()
+        .rposition(|(_leftover, kind)| matches!(kind, ::nom::ErrorKind::Custom(_)));
 
     match pos {
-        None => return &errors[0..0],
-        Some(pos) => return &errors[0..=pos],
+        None => &errors[0..0],
+        Some(pos) => &errors[0..=pos],
     }
 }
 
@@ -72,32 +79,8 @@ pub fn last_parser_error<I>(
         .last()
 }
 
-fn print_leftover(leftover: &[u8]) {
-    println!("{:#?}", PrintableByteSlice(leftover));
-}
-
-pub fn handle_single_error<P: AsRef<Path>>(
-    path: P,
-    input: &[u8],
-    leftover: &Input,
-    err: &::nom::ErrorKind<ParserError>,
-) {
-    let offset = input.len() - leftover.0.len();
-    let description = match err {
-        ::nom::ErrorKind::Custom(custom) => custom.description(),
-        otherwise => otherwise.description(),
-    };
-
-    println!(
-        "Error on line {} in file {:#?}: {:#?}",
-        get_line_number(input, offset),
-        path.as_ref(),
-        description
-    );
-}
-
 fn get_line_number(content: &[u8], offset: usize) -> usize {
-    content[0..offset].iter().filter(|b| **b == b'\n').count() + 1
+    LineIndex::new(content).line_col(offset).line
 }
 
 #[cfg(test)]
@@ -1,12 +1,23 @@
-use inner_errors::ParserError;
+use inner_errors::{CodegenError, ConstError, LinkerError, ParserError, SrcError, TypeError};
+use types::Span;
 
 pub type Result<O> = ::std::result::Result<O, Error>;
 
 #[derive(Debug)]
 pub enum Error {
-    ParsingError { err: ParserError, line: usize },
-    IOError(::std::io::Error), //LinkingError(LinkerError),
-                               //TypeCheckError(TypeError)
+    ParsingError {
+        err: ParserError,
+        span: Span,
+        line: usize,
+        /// A `{` that was still open where parsing resumed after the error, so it probably lacks its `}`.
+        block: Option<Span>,
+    },
+    IOError(::std::io::Error),
+    SrcError(SrcError),
+    LinkingError(LinkerError),
+    TypeCheckError(TypeError),
+    ConstError(ConstError),
+    CodegenError(CodegenError),
 }
 
 impl From<::std::io::Error> for Error {
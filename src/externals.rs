@@ -0,0 +1,107 @@
+//! Functions that the engine implements and scripts call, like `AI_Output` or `Hlp_GetNpc`.
+//!
+//! Scripts do not declare them, the engine does before compiling them. Parsiphae reads their
+//! declarations from an externals file instead: Daedalus `func` declarations without bodies,
+//! like `func int Hlp_Random(var int bound);`. The externals of the original games are bundled.
+
+use errors::{Error, Result};
+use parsers::parse_externals;
+use source_map::SourceMap;
+use std::path::Path;
+use types::Function;
+
+/// Names of the bundled externals, for Gothic 1, Gothic 2 and Gothic 2 with the Night of the
+/// Raven addon.
+pub const BUILTINS: &[&str] = &["g1", "g2", "g2notr"];
+
+/// The bundled externals called `name`, one of `BUILTINS`.
+pub fn builtin(name: &str) -> Option<&'static str> {
+    let content = match name.to_ascii_lowercase().as_str() {
+        "g1" => concat!(
+            include_str!("externals/common.d"),
+            include_str!("externals/gothic1.d")
+        ),
+        "g2" => concat!(
+            include_str!("externals/common.d"),
+            include_str!("externals/gothic2.d")
+        ),
+        "g2notr" => concat!(
+            include_str!("externals/common.d"),
+            include_str!("externals/gothic2.d"),
+            include_str!("externals/notr.d")
+        ),
+        _ => return None,
+    };
+    Some(content)
+}
+
+/// Loads the externals `name`, which is either one of `BUILTINS` or the path of an externals
+/// file, and adds it to `sources`. Also returns the syntax errors of the file.
+pub fn load(name: &str, sources: &mut SourceMap) -> Result<(Vec<Function>, Vec<Error>)> {
+    let (path, content) = match builtin(name) {
+        Some(content) => (
+            Path::new("externals").join(format!("{}.d", name.to_ascii_lowercase())),
+            content.as_bytes().to_vec(),
+        ),
+        None => (Path::new(name).to_owned(), ::std::fs::read(name)?),
+    };
+
+    let file = sources.add(path, content);
+    Ok(parse_externals(&sources.get(file).unwrap().content, file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ppa::name_resolver::resolve_files;
+    use std::collections::HashSet;
+    use types::FileId;
+
+    #[test]
+    fn builtins_parse() {
+        for name in BUILTINS {
+            let (functions, errors) = parse_externals(builtin(name).unwrap().as_bytes(), FileId(0));
+            assert!(errors.is_empty(), "{}: {:?}", name, errors);
+
+            let resolution = resolve_files(&functions, None, |_| true);
+            assert!(
+                resolution.errors.is_empty(),
+                "{}: {:?}",
+                name,
+                resolution.errors
+            );
+            let names: HashSet<Vec<u8>> = functions
+                .iter()
+                .map(|func| func.name.key().as_bytes().to_vec())
+                .collect();
+            for common in &[
+                &b"AI_OUTPUT"[..],
+                b"AI_PLAYFX",
+                b"AI_STOPFX",
+                b"NPC_CREATESPELL",
+            ] {
+                assert!(names.contains(*common), "{}", name);
+            }
+            assert_eq!(names.contains(&b"DOC_PRINT"[..]), *name == "g1");
+            assert_eq!(names.contains(&b"NPC_SETPERMATTITUDE"[..]), *name == "g1");
+            assert_eq!(names.contains(&b"AI_WAITMS"[..]), *name != "g1");
+            assert_eq!(
+                names.contains(&b"NPC_GETLASTHITSPELLID"[..]),
+                *name == "g2notr"
+            );
+        }
+        assert_eq!(builtin("G2"), builtin("g2"));
+        assert_eq!(builtin("g3"), None);
+    }
+
+    #[test]
+    fn loads_files() {
+        let mut sources = SourceMap::new();
+        let (functions, errors) = load("g1", &mut sources).unwrap();
+        assert!(errors.is_empty());
+        assert!(!functions.is_empty());
+        assert_eq!(sources.files()[0].path, Path::new("externals/g1.d"));
+
+        assert!(load("missing/externals.d", &mut sources).is_err());
+    }
+}
@@ -0,0 +1,574 @@
+//! Prints ASTs as canonical Daedalus source.
+//!
+//! The output uses lower case keywords and built-in types, tab indentation, braces on their
+//! own line and only the parentheses that the operator precedence requires. Comments are
+//! taken from the source: those on the line a declaration or statement ends on stay there,
+//! all others are put on lines of their own in front of the next declaration or statement.
+
+use cst::{tokenize, Trivia};
+use types::{
+    ArraySizeDeclaration, Assignment, AssignmentOperator, BinaryExpression, Class,
+    ConstArrayDeclaration, ConstDeclaration, Declaration, Expression, Function, Identifier,
+    IfStatement, Instance, Prototype, Statement, UnaryOperator, VarAccess, VarDeclaration, AST,
+};
+
+/// Formats `ast`, which was parsed from `content`, as Daedalus source with `\n` line endings.
+pub fn format(ast: &AST, content: &[u8]) -> Vec<u8> {
+    let tokens = tokenize(content);
+    let mut formatter = Formatter {
+        content,
+        comments: tokens.iter().flat_map(|token| token.comments()).collect(),
+        ..Formatter::default()
+    };
+    formatter.ast(ast);
+    formatter.out
+}
+
+/// Names of the built-in types, which are printed in lower case.
+const BUILTIN_TYPES: [&[u8]; 5] = [b"int", b"float", b"string", b"void", b"func"];
+
+#[derive(Default)]
+struct Formatter<'a> {
+    out: Vec<u8>,
+    indent: usize,
+    content: &'a [u8],
+    /// The comments of `content` with their offsets, of which the first `written` are written.
+    comments: Vec<(usize, &'a Trivia)>,
+    written: usize,
+}
+
+impl<'a> Formatter<'a> {
+    fn write(&mut self, text: &[u8]) {
+        self.out.extend_from_slice(text);
+    }
+
+    fn line_start(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push(b'\t');
+        }
+    }
+
+    fn comment(&mut self, comment: &Trivia) {
+        // Line endings are converted for the whole file at once.
+        let text: Vec<u8> = comment
+            .text
+            .iter()
+            .cloned()
+            .filter(|b| *b != b'\r')
+            .collect();
+        self.write(&text);
+    }
+
+    /// Writes the comments that start before `offset` in the source on lines of their own.
+    fn leading_comments(&mut self, offset: usize) {
+        while let Some(&(start, comment)) = self.comments.get(self.written) {
+            if start >= offset {
+                break;
+            }
+            self.line_start();
+            self.comment(comment);
+            self.write(b"\n");
+            self.written += 1;
+        }
+    }
+
+    /// Appends the comments within the source of what was just written, which ends at `end`,
+    /// and those after it on the same line, up to `limit`, to the line just written.
+    fn trailing_comments(&mut self, mut end: usize, limit: usize) {
+        while let Some(&(start, comment)) = self.comments.get(self.written) {
+            let same_line = start < end || !self.content[end..start].contains(&b'\n');
+            if start >= limit || !same_line || self.out.last() != Some(&b'\n') {
+                break;
+            }
+            self.out.pop();
+            self.write(b" ");
+            self.comment(comment);
+            self.write(b"\n");
+            self.written += 1;
+            end = end.max(start + comment.text.len());
+        }
+    }
+
+    fn ast(&mut self, ast: &AST) {
+        let mut previous_multiline = None;
+        for decl in &ast.declarations {
+            let multiline = is_multiline(decl);
+            match previous_multiline {
+                Some(previous) if previous || multiline => self.write(b"\n"),
+                _ => {}
+            }
+            let span = decl.span();
+            self.leading_comments(span.start);
+            self.declaration(decl);
+            self.trailing_comments(span.end, usize::MAX);
+            previous_multiline = Some(multiline);
+        }
+        self.leading_comments(usize::MAX);
+    }
+
+    fn declaration(&mut self, decl: &Declaration) {
+        match *decl {
+            Declaration::Var(ref vars) => self.var_declarations(vars),
+            Declaration::Func(ref func) => self.function(func),
+            Declaration::Class(ref class) => self.class(class),
+            Declaration::Inst(ref instances) => self.instances(instances),
+            Declaration::Proto(ref proto) => self.prototype(proto),
+            Declaration::Const(ref decl) => self.const_declaration(decl),
+            Declaration::ConstArray(ref decl) => self.const_array_declaration(decl),
+        }
+    }
+
+    fn typ(&mut self, typ: &Identifier) {
+        let name = typ.as_bytes();
+        match BUILTIN_TYPES
+            .iter()
+            .find(|builtin| builtin.eq_ignore_ascii_case(name))
+        {
+            Some(builtin) => self.write(builtin),
+            None => self.write(name),
+        }
+    }
+
+    /// `var int a, b` for variables of the same type, `var int a, var string b` otherwise.
+    fn var_list(&mut self, vars: &[VarDeclaration]) {
+        for (i, var) in vars.iter().enumerate() {
+            let same_type = i > 0 && vars[i - 1].typ.same_name(&var.typ);
+            if i > 0 {
+                self.write(b", ");
+            }
+            if !same_type {
+                self.write(b"var ");
+                self.typ(&var.typ);
+                self.write(b" ");
+            }
+            self.write(var.name.as_bytes());
+            if let Some(ref size) = var.array_size {
+                self.array_size(size);
+            }
+        }
+    }
+
+    fn var_declarations(&mut self, vars: &[VarDeclaration]) {
+        self.line_start();
+        self.var_list(vars);
+        self.write(b";\n");
+    }
+
+    fn array_size(&mut self, size: &ArraySizeDeclaration) {
+        self.write(b"[");
+        match *size {
+            ArraySizeDeclaration::Identifier(ref name) => self.write(name.as_bytes()),
+            ArraySizeDeclaration::Size(size, _) => self.write(size.to_string().as_bytes()),
+        }
+        self.write(b"]");
+    }
+
+    fn const_declaration(&mut self, decl: &ConstDeclaration) {
+        self.line_start();
+        self.write(b"const ");
+        self.typ(&decl.typ);
+        self.write(b" ");
+        self.write(decl.name.as_bytes());
+        self.write(b" = ");
+        self.expression(&decl.initializer);
+        self.write(b";\n");
+    }
+
+    fn const_array_declaration(&mut self, decl: &ConstArrayDeclaration) {
+        self.line_start();
+        self.write(b"const ");
+        self.typ(&decl.typ);
+        self.write(b" ");
+        self.write(decl.name.as_bytes());
+        self.array_size(&decl.array_size);
+        self.write(b" = { ");
+        for (i, exp) in decl.initializer.expressions.iter().enumerate() {
+            if i > 0 {
+                self.write(b", ");
+            }
+            self.expression(exp);
+        }
+        self.write(b" };\n");
+    }
+
+    fn function(&mut self, func: &Function) {
+        self.write(b"func ");
+        self.typ(&func.typ);
+        self.write(b" ");
+        self.write(func.name.as_bytes());
+        self.write(b"(");
+        for (i, param) in func.params.iter().enumerate() {
+            if i > 0 {
+                self.write(b", ");
+            }
+            self.var_list(::std::slice::from_ref(param));
+        }
+        self.write(b")\n");
+        self.block(&func.body, func.span.end);
+        self.write(b";\n");
+    }
+
+    fn class(&mut self, class: &Class) {
+        self.write(b"class ");
+        self.write(class.name.as_bytes());
+        self.write(b"\n{\n");
+        self.indent += 1;
+        for member in &class.members {
+            self.leading_comments(member.span.start);
+            self.var_declarations(::std::slice::from_ref(member));
+            self.trailing_comments(member.span.end, class.span.end);
+        }
+        self.leading_comments(class.span.end);
+        self.indent -= 1;
+        self.write(b"};\n");
+    }
+
+    fn prototype(&mut self, proto: &Prototype) {
+        self.write(b"prototype ");
+        self.write(proto.name.as_bytes());
+        self.write(b"(");
+        self.write(proto.class.as_bytes());
+        self.write(b")\n");
+        self.block(&proto.body, proto.span.end);
+        self.write(b";\n");
+    }
+
+    /// A single instance is printed with its body, several share a declaration without one.
+    fn instances(&mut self, instances: &[Instance]) {
+        self.write(b"instance ");
+        for (i, instance) in instances.iter().enumerate() {
+            if i > 0 {
+                self.write(b", ");
+            }
+            self.write(instance.name.as_bytes());
+        }
+        if let Some(instance) = instances.first() {
+            self.write(b"(");
+            self.write(instance.class.as_bytes());
+            self.write(b")");
+        }
+
+        match instances {
+            [instance] => {
+                self.write(b"\n");
+                self.block(&instance.body, instance.span.end);
+                self.write(b";\n");
+            }
+            _ => self.write(b";\n"),
+        }
+    }
+
+    /// Braces on their own lines, without the trailing semicolon. Comments before `end` in the
+    /// source go inside.
+    fn block(&mut self, statements: &[Statement], end: usize) {
+        self.line_start();
+        self.write(b"{\n");
+        self.indent += 1;
+        for statement in statements {
+            let span = statement.span();
+            self.leading_comments(span.start);
+            self.statement(statement);
+            self.trailing_comments(span.end, end);
+        }
+        self.leading_comments(end);
+        self.indent -= 1;
+        self.line_start();
+        self.write(b"}");
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match *statement {
+            Statement::Exp(ref exp) => {
+                self.line_start();
+                self.expression(exp);
+                self.write(b";\n");
+            }
+            Statement::Ass(ref ass) => self.assignment(ass),
+            Statement::If(ref if_statement) => self.if_statement(if_statement),
+            Statement::VarDeclarations(ref vars) => self.var_declarations(vars),
+            Statement::ConstDeclaration(ref decl) => self.const_declaration(decl),
+            Statement::ConstArrayDeclaration(ref decl) => self.const_array_declaration(decl),
+            Statement::ReturnStatement(ref exp, _) => {
+                self.line_start();
+                self.write(b"return");
+                if let Some(ref exp) = *exp {
+                    self.write(b" ");
+                    self.expression(exp);
+                }
+                self.write(b";\n");
+            }
+        }
+    }
+
+    fn assignment(&mut self, ass: &Assignment) {
+        self.line_start();
+        self.var_access(&ass.var);
+        self.write(match ass.op {
+            AssignmentOperator::Eq => b" = ",
+            AssignmentOperator::PlusEq => b" += ",
+            AssignmentOperator::MinusEq => b" -= ",
+            AssignmentOperator::MultiplyEq => b" *= ",
+            AssignmentOperator::DivideEq => b" /= ",
+        });
+        self.expression(&ass.exp);
+        self.write(b";\n");
+    }
+
+    fn if_statement(&mut self, if_statement: &IfStatement) {
+        for (i, branch) in if_statement.branches.iter().enumerate() {
+            self.line_start();
+            if i > 0 {
+                self.write(b"else ");
+            }
+            self.write(b"if (");
+            self.expression(&branch.cond);
+            self.write(b")\n");
+            self.block(&branch.body, branch.span.end);
+            self.write(b"\n");
+        }
+
+        if let Some(ref else_branch) = if_statement.else_branch {
+            self.line_start();
+            self.write(b"else\n");
+            self.block(else_branch, if_statement.span.end);
+            self.write(b"\n");
+        }
+
+        // The semicolon goes after the closing brace of the last branch.
+        self.out.pop();
+        self.write(b";\n");
+    }
+
+    fn var_access(&mut self, var: &VarAccess) {
+        if let Some(ref instance) = var.instance {
+            self.write(instance.as_bytes());
+            self.write(b".");
+        }
+        self.write(var.name.as_bytes());
+        if let Some(ref index) = var.index {
+            self.write(b"[");
+            self.expression(index);
+            self.write(b"]");
+        }
+    }
+
+    fn expression(&mut self, exp: &Expression) {
+        match *exp {
+            Expression::Int(value, _) => self.write(value.to_string().as_bytes()),
+            Expression::Float(value, _) => {
+                let mut text = value.to_string();
+                if !text.contains('.') {
+                    text.push_str(".0");
+                }
+                self.write(text.as_bytes());
+            }
+            Expression::String(ref string) => {
+                self.write(b"\"");
+                self.write(string.as_bytes());
+                self.write(b"\"");
+            }
+            Expression::Identifier(ref var) => self.var_access(var),
+            Expression::Binary(ref bin) => self.binary(bin),
+            Expression::Unary(ref un) => {
+                self.write(match un.op {
+                    UnaryOperator::Plus => b"+",
+                    UnaryOperator::Minus => b"-",
+                    UnaryOperator::Negate => b"!",
+                    UnaryOperator::Flip => b"~",
+                });
+                self.operand(&un.right, matches!(un.right, Expression::Binary(_)));
+            }
+            Expression::Call(ref call) => {
+                self.write(call.func.as_bytes());
+                self.write(b"(");
+                for (i, param) in call.params.iter().enumerate() {
+                    if i > 0 {
+                        self.write(b", ");
+                    }
+                    self.expression(param);
+                }
+                self.write(b")");
+            }
+        }
+    }
+
+    /// Binary operators are left associative, so a right operand of the same precedence
+    /// needs parentheses as well.
+    fn binary(&mut self, bin: &BinaryExpression) {
+        let right_parentheses = bin.op.needs_parentheses(&bin.right)
+            || match bin.right {
+                Expression::Binary(ref right) => right.op.get_order() == bin.op.get_order(),
+                _ => false,
+            };
+
+        self.operand(&bin.left, bin.op.needs_parentheses(&bin.left));
+        self.write(b" ");
+        self.write(bin.op.sign().as_bytes());
+        self.write(b" ");
+        self.operand(&bin.right, right_parentheses);
+    }
+
+    fn operand(&mut self, exp: &Expression, parentheses: bool) {
+        if parentheses {
+            self.write(b"(");
+            self.expression(exp);
+            self.write(b")");
+        } else {
+            self.expression(exp);
+        }
+    }
+}
+
+fn is_multiline(decl: &Declaration) -> bool {
+    match *decl {
+        Declaration::Func(_) | Declaration::Class(_) | Declaration::Proto(_) => true,
+        Declaration::Inst(ref instances) => instances.len() == 1,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use types::FileId;
+
+    fn parse(content: &[u8]) -> AST {
+        let (ast, errors) = parse_recovering(content, FileId(0));
+        assert!(errors.is_empty(), "{:?}", errors);
+        ast
+    }
+
+    /// Formatting has to be stable, and apart from the casing of types, preserve the AST.
+    fn assert_formats(input: &str, expected: &str) -> (AST, Vec<u8>) {
+        let ast = parse(input.as_bytes());
+        let formatted = format(&ast, input.as_bytes());
+        assert_eq!(String::from_utf8_lossy(&formatted), expected);
+        assert_eq!(format(&parse(&formatted), &formatted), formatted);
+        (ast, formatted)
+    }
+
+    #[test]
+    fn declarations() {
+        assert_formats(
+            "VAR INT a,b;var C_NPC self;CONST Int MAX=3;const string S[2]={\"a\",\"b\"};
+CLASS C_NPC{var int id;var string name[MAX];};
+prototype P(C_NPC){id=1;};instance A,B(P);instance H(P){};",
+            "var int a, b;
+var C_NPC self;
+const int MAX = 3;
+const string S[2] = { \"a\", \"b\" };
+
+class C_NPC
+{
+\tvar int id;
+\tvar string name[MAX];
+};
+
+prototype P(C_NPC)
+{
+\tid = 1;
+};
+
+instance A, B(P);
+
+instance H(P)
+{
+};
+",
+        );
+    }
+
+    #[test]
+    fn statements() {
+        let (ast, formatted) = assert_formats(
+            "func int f(var int a,var C_NPC b){var int x;x+=a;
+if a{return 1;}else if(b.id[2]==1){f(1,b);}else{return;};if(1){}
+return -a;};",
+            "func int f(var int a, var C_NPC b)
+{
+\tvar int x;
+\tx += a;
+\tif (a)
+\t{
+\t\treturn 1;
+\t}
+\telse if (b.id[2] == 1)
+\t{
+\t\tf(1, b);
+\t}
+\telse
+\t{
+\t\treturn;
+\t};
+\tif (1)
+\t{
+\t};
+\treturn -a;
+};
+",
+        );
+        assert_eq!(parse(&formatted), ast);
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_formats(
+            "const int A=(1+2)*3-(4-5)+-(6*7)+(8*9)+((1<<2)|3);const float F=2.0;",
+            "const int A = (1 + 2) * 3 - (4 - 5) + -(6 * 7) + 8 * 9 + 1 << 2 | 3;
+const float F = 2.0;
+",
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_formats(
+            "// Header
+var int a; // a
+/* b */ var int b;
+class C { // members
+    var int id; /* id */ /* more */
+    // end
+};
+func void f() {
+    // first
+    f(1, /* inside */ 2); // call
+    if (1) { return; } // if
+    else { /* else */ };
+    const string URL = \"http://x\";
+};
+// footer
+",
+            "// Header
+var int a; // a
+/* b */
+var int b;
+
+class C
+{
+\t// members
+\tvar int id; /* id */ /* more */
+\t// end
+};
+
+func void f()
+{
+\t// first
+\tf(1, 2); /* inside */ // call
+\tif (1)
+\t{
+\t\treturn;
+\t}
+\telse
+\t{
+\t\t// if
+\t\t/* else */
+\t};
+\tconst string URL = \"http://x\";
+};
+// footer
+",
+        );
+    }
+}
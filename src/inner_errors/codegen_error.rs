@@ -0,0 +1,40 @@
+use types::Span;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CodegenError {
+    /// Array sizes, array indices and constant initializers must be known at compile time.
+    NotConstant(Span),
+    /// Array elements are accessed with a 1-byte operand, so constant indices must be below 256.
+    IndexTooLarge(Span),
+}
+
+impl CodegenError {
+    pub fn description(&self) -> &'static str {
+        match *self {
+            CodegenError::NotConstant(_) => "Expression is not constant",
+            CodegenError::IndexTooLarge(_) => {
+                "Array index does not fit the 1-byte operand of PushArrayVar"
+            }
+        }
+    }
+
+    /// Stable name of the error, for tools that consume diagnostics.
+    pub fn code(&self) -> &'static str {
+        match *self {
+            CodegenError::NotConstant(_) => "not-constant",
+            CodegenError::IndexTooLarge(_) => "index-too-large",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match *self {
+            CodegenError::NotConstant(span) | CodegenError::IndexTooLarge(span) => span,
+        }
+    }
+}
+
+impl ::std::fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
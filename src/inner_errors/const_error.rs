@@ -0,0 +1,86 @@
+use types::{Identifier, Span, Type};
+
+/// Reasons an expression that has to be known at compile time could not be evaluated.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ConstError {
+    DivisionByZero(Span),
+    /// Functions are only called at runtime.
+    Call(Span),
+    /// A variable, or anything else whose value is only known at runtime.
+    NotConstant(Span),
+    /// A reference to a constant whose value depends on itself.
+    Cycle(Identifier),
+    IndexOutOfBounds {
+        index: i32,
+        size: usize,
+        span: Span,
+    },
+    /// An operator applied to values it is not defined for, like strings.
+    InvalidOperand(Span),
+    MismatchedType {
+        expected: Type,
+        span: Span,
+    },
+}
+
+impl ConstError {
+    pub fn description(&self) -> &'static str {
+        use self::ConstError::*;
+        match *self {
+            DivisionByZero(_) => "Division by zero",
+            Call(_) => "Calls are not constant",
+            NotConstant(_) => "Expression is not constant",
+            Cycle(_) => "Constant depends on itself",
+            IndexOutOfBounds { .. } => "Index out of bounds",
+            InvalidOperand(_) => "Operator cannot be applied to these values",
+            MismatchedType { .. } => "Constant has the wrong type",
+        }
+    }
+
+    /// Stable name of the error, for tools that consume diagnostics.
+    pub fn code(&self) -> &'static str {
+        use self::ConstError::*;
+        match *self {
+            DivisionByZero(_) => "division-by-zero",
+            Call(_) => "non-constant-call",
+            NotConstant(_) => "not-constant",
+            Cycle(_) => "constant-cycle",
+            IndexOutOfBounds { .. } => "index-out-of-bounds",
+            InvalidOperand(_) => "invalid-operand",
+            MismatchedType { .. } => "constant-type-mismatch",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        use self::ConstError::*;
+        match *self {
+            DivisionByZero(span)
+            | Call(span)
+            | NotConstant(span)
+            | InvalidOperand(span)
+            | IndexOutOfBounds { span, .. }
+            | MismatchedType { span, .. } => span,
+            Cycle(ref name) => name.span,
+        }
+    }
+}
+
+impl ::std::fmt::Display for ConstError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        use self::ConstError::*;
+        match *self {
+            Cycle(ref name) => write!(f, "{}: '{}'", self.description(), name),
+            IndexOutOfBounds { index, size, .. } => write!(
+                f,
+                "{}: {} for an array of size {}",
+                self.description(),
+                index,
+                size
+            ),
+            MismatchedType { ref expected, .. } => {
+                write!(f, "{}, expected {}", self.description(), expected)
+            }
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
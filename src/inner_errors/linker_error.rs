@@ -0,0 +1,73 @@
+use types::{Identifier, Span};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LinkerError {
+    UndefinedSymbol(Identifier),
+    UndefinedMember {
+        instance: Identifier,
+        member: Identifier,
+    },
+    DuplicateSymbol {
+        name: Identifier,
+        previous: Span,
+    },
+    /// The parent of an instance or prototype that is neither a class nor a prototype.
+    InvalidParent(Identifier),
+    /// The parent of a prototype that is a prototype itself.
+    PrototypeParent(Identifier),
+}
+
+impl LinkerError {
+    pub fn description(&self) -> &'static str {
+        use self::LinkerError::*;
+
+        match *self {
+            UndefinedSymbol(_) => "Undefined symbol",
+            UndefinedMember { .. } => "Undefined member",
+            DuplicateSymbol { .. } => "Symbol is already defined",
+            InvalidParent(_) => "Not a class or prototype",
+            PrototypeParent(_) => "A prototype can only derive from a class",
+        }
+    }
+
+    /// Stable name of the error, for tools that consume diagnostics.
+    pub fn code(&self) -> &'static str {
+        use self::LinkerError::*;
+        match *self {
+            UndefinedSymbol(_) => "undefined-symbol",
+            UndefinedMember { .. } => "undefined-member",
+            DuplicateSymbol { .. } => "duplicate-symbol",
+            InvalidParent(_) => "invalid-parent",
+            PrototypeParent(_) => "prototype-parent",
+        }
+    }
+
+    /// Location of the offending identifier.
+    pub fn span(&self) -> Span {
+        use self::LinkerError::*;
+
+        match *self {
+            UndefinedSymbol(ref name) => name.span,
+            UndefinedMember { ref member, .. } => member.span,
+            DuplicateSymbol { ref name, .. } => name.span,
+            InvalidParent(ref parent) | PrototypeParent(ref parent) => parent.span,
+        }
+    }
+}
+
+impl ::std::fmt::Display for LinkerError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        use self::LinkerError::*;
+
+        match *self {
+            UndefinedSymbol(ref name) => write!(f, "{} '{}'", self.description(), name),
+            UndefinedMember {
+                ref instance,
+                ref member,
+            } => write!(f, "{} '{}.{}'", self.description(), instance, member),
+            DuplicateSymbol { ref name, .. }
+            | InvalidParent(ref name)
+            | PrototypeParent(ref name) => write!(f, "{}: '{}'", self.description(), name),
+        }
+    }
+}
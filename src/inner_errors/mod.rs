@@ -1,3 +1,13 @@
+mod codegen_error;
+mod const_error;
+mod linker_error;
 mod parser_error;
+mod src_error;
+mod type_error;
 
+pub use self::codegen_error::CodegenError;
+pub use self::const_error::ConstError;
+pub use self::linker_error::LinkerError;
 pub use self::parser_error::ParserError;
+pub use self::src_error::SrcError;
+pub use self::type_error::TypeError;
@@ -31,6 +31,24 @@ impl ParserError {
             FromNom => "You should never see this",
         }
     }
+
+    /// Stable name of the error, for tools that consume diagnostics.
+    pub fn code(&self) -> &'static str {
+        use self::ParserError::*;
+        match *self {
+            MissingSemi => "missing-semicolon",
+            MissingIdentifier => "missing-identifier",
+            VariableDeclaration => "invalid-variable-declaration",
+            ClassDeclaration => "invalid-class-declaration",
+            IfClause => "invalid-if",
+            ElseClause => "invalid-else",
+            IllegalStatement => "invalid-statement",
+            InvalidCall => "invalid-call",
+            IllegalExpression => "invalid-expression",
+            Declaration => "invalid-declaration",
+            FromNom => "internal-parser-error",
+        }
+    }
 }
 
 impl ::std::fmt::Display for ParserError {
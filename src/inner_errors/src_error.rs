@@ -0,0 +1,84 @@
+use types::Span;
+
+/// Problems with a line of a `.src` file. `name` is the path as written in the line, or for
+/// problems with a file the line matched, the path of that file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SrcError {
+    MissingFile {
+        name: String,
+        span: Span,
+    },
+    /// A wildcard that matches no file.
+    NoMatches {
+        name: String,
+        span: Span,
+    },
+    UnknownExtension {
+        name: String,
+        span: Span,
+    },
+    /// A `.src` that includes itself, directly or through other `.src` files.
+    RecursiveInclude {
+        name: String,
+        span: Span,
+    },
+    Unreadable {
+        name: String,
+        reason: String,
+        span: Span,
+    },
+}
+
+impl SrcError {
+    pub fn description(&self) -> &'static str {
+        use self::SrcError::*;
+        match *self {
+            MissingFile { .. } => "File not found",
+            NoMatches { .. } => "No files match",
+            UnknownExtension { .. } => "Neither a script nor a src",
+            RecursiveInclude { .. } => "Src includes itself",
+            Unreadable { .. } => "Could not read",
+        }
+    }
+
+    /// Stable name of the error, for tools that consume diagnostics.
+    pub fn code(&self) -> &'static str {
+        use self::SrcError::*;
+        match *self {
+            MissingFile { .. } => "missing-file",
+            NoMatches { .. } => "no-matches",
+            UnknownExtension { .. } => "unknown-extension",
+            RecursiveInclude { .. } => "recursive-include",
+            Unreadable { .. } => "unreadable-file",
+        }
+    }
+
+    /// Location of the line in the `.src`.
+    pub fn span(&self) -> Span {
+        use self::SrcError::*;
+        match *self {
+            MissingFile { span, .. }
+            | NoMatches { span, .. }
+            | UnknownExtension { span, .. }
+            | RecursiveInclude { span, .. }
+            | Unreadable { span, .. } => span,
+        }
+    }
+}
+
+impl ::std::fmt::Display for SrcError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        use self::SrcError::*;
+        match *self {
+            MissingFile { ref name, .. }
+            | NoMatches { ref name, .. }
+            | UnknownExtension { ref name, .. }
+            | RecursiveInclude { ref name, .. } => write!(f, "{}: '{}'", self.description(), name),
+            Unreadable {
+                ref name,
+                ref reason,
+                ..
+            } => write!(f, "{} '{}': {}", self.description(), name, reason),
+        }
+    }
+}
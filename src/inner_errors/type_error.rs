@@ -0,0 +1,136 @@
+use inner_errors::ConstError;
+use types::{Identifier, Span, Type};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TypeError {
+    Mismatch {
+        expected: Type,
+        found: Type,
+        span: Span,
+    },
+    WrongArgumentCount {
+        func: Identifier,
+        expected: usize,
+        found: usize,
+    },
+    NotCallable(Identifier),
+    ReturnFromVoid(Span),
+    FloatArithmetic(Span),
+    StringCondition(Span),
+    /// Arrays need at least one element.
+    InvalidArraySize {
+        size: i32,
+        span: Span,
+    },
+    WrongElementCount {
+        array: Identifier,
+        expected: usize,
+        found: usize,
+    },
+    /// An array size or index that could not be evaluated or is out of bounds.
+    Constant(ConstError),
+    /// The members of a class take more than the 4 GiB engine objects can address.
+    ClassTooLarge(Span),
+}
+
+impl TypeError {
+    pub fn description(&self) -> &'static str {
+        use self::TypeError::*;
+
+        match *self {
+            Mismatch { .. } => "Type mismatch",
+            WrongArgumentCount { .. } => "Wrong number of arguments",
+            NotCallable(_) => "Not a function",
+            ReturnFromVoid(_) => "Returning a value from a void function",
+            FloatArithmetic(_) => "Float arithmetic is not supported by the Gothic VM",
+            StringCondition(_) => "A string cannot be used as a condition",
+            InvalidArraySize { .. } => "Array size must be positive",
+            WrongElementCount { .. } => "Wrong number of array elements",
+            Constant(ref err) => err.description(),
+            ClassTooLarge(_) => "Class is too large for engine objects",
+        }
+    }
+
+    /// Stable name of the error, for tools that consume diagnostics.
+    pub fn code(&self) -> &'static str {
+        use self::TypeError::*;
+        match *self {
+            Mismatch { .. } => "type-mismatch",
+            WrongArgumentCount { .. } => "wrong-argument-count",
+            NotCallable(_) => "not-callable",
+            ReturnFromVoid(_) => "return-from-void",
+            FloatArithmetic(_) => "float-arithmetic",
+            StringCondition(_) => "string-condition",
+            InvalidArraySize { .. } => "invalid-array-size",
+            WrongElementCount { .. } => "wrong-element-count",
+            Constant(ref err) => err.code(),
+            ClassTooLarge(_) => "class-too-large",
+        }
+    }
+
+    /// Location of the offending expression.
+    pub fn span(&self) -> Span {
+        use self::TypeError::*;
+
+        match *self {
+            Mismatch { span, .. } => span,
+            WrongArgumentCount { ref func, .. } => func.span,
+            NotCallable(ref func) => func.span,
+            ReturnFromVoid(span) => span,
+            FloatArithmetic(span) => span,
+            StringCondition(span) => span,
+            InvalidArraySize { span, .. } => span,
+            WrongElementCount { ref array, .. } => array.span,
+            Constant(ref err) => err.span(),
+            ClassTooLarge(span) => span,
+        }
+    }
+}
+
+impl ::std::fmt::Display for TypeError {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        use self::TypeError::*;
+
+        match *self {
+            Mismatch {
+                ref expected,
+                ref found,
+                ..
+            } => write!(
+                f,
+                "{}: expected {}, found {}",
+                self.description(),
+                expected,
+                found
+            ),
+            WrongArgumentCount {
+                ref func,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} for '{}': expected {}, found {}",
+                self.description(),
+                func,
+                expected,
+                found
+            ),
+            NotCallable(ref func) => write!(f, "{}: '{}'", self.description(), func),
+            InvalidArraySize { size, .. } => write!(f, "{}, found {}", self.description(), size),
+            WrongElementCount {
+                ref array,
+                expected,
+                found,
+            } => write!(
+                f,
+                "{} for '{}': expected {}, found {}",
+                self.description(),
+                array,
+                expected,
+                found
+            ),
+            Constant(ref err) => write!(f, "{}", err),
+            _ => write!(f, "{}", self.description()),
+        }
+    }
+}
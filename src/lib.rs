@@ -5,13 +5,28 @@ extern crate lazy_static;
 #[macro_use]
 extern crate nom;
 extern crate encoding;
-extern crate glob;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+#[macro_use]
+extern crate serde_json;
 
+pub mod cache;
+pub mod codegen;
+pub mod cst;
+pub mod dat;
+pub mod diagnostics;
 pub mod error_handler;
 pub mod errors;
+pub mod externals;
+pub mod formatter;
 pub mod inner_errors;
+pub mod lint;
+pub mod lsp;
+pub mod parallel;
 pub mod parsers;
 pub mod ppa;
+pub mod source_map;
 pub mod src_parser;
 mod tests;
 pub mod types;
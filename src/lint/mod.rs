@@ -0,0 +1,315 @@
+//! Style and correctness checks that are not errors, like unused functions or unreachable code.
+//!
+//! Every rule has a name and a default severity, which a config file can change:
+//!
+//! ```json
+//! {"rules": {"unused-instance": "warning", "self-assignment": "error", "unused-constant": false}}
+//! ```
+//!
+//! `false` or `"allow"` disables a rule, `true` enables it with its default severity. Single
+//! findings are silenced with a comment on their line or on its own line right above them:
+//! `// parsiphae: allow(unused-function, unused-constant)`.
+
+pub mod rules;
+
+use cst::{tokenize, TriviaKind};
+use diagnostics::{Diagnostic, Severity};
+use ppa::name_resolver::Resolution;
+use serde_json::Value;
+use source_map::{LineIndex, SourceMap};
+use std::collections::HashMap;
+use types::{Span, AST};
+
+/// What a rule checks: all scripts of a src after they were resolved without errors.
+pub struct Context<'a> {
+    pub asts: &'a [AST],
+    pub resolution: &'a Resolution,
+}
+
+/// A problem a rule found.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub span: Span,
+    pub message: String,
+}
+
+pub trait Lint {
+    /// Stable name of the rule, used in configs, in allow comments and as diagnostic code.
+    fn name(&self) -> &'static str;
+
+    fn description(&self) -> &'static str;
+
+    fn default_severity(&self) -> Severity {
+        Severity::Warning
+    }
+
+    /// Whether the rule runs unless the config enables it.
+    fn enabled_by_default(&self) -> bool {
+        true
+    }
+
+    fn check(&self, cx: &Context, findings: &mut Vec<Finding>);
+}
+
+/// The rules that can be run.
+pub struct Registry {
+    rules: Vec<Box<dyn Lint>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry { rules: Vec::new() }
+    }
+
+    /// All rules that come with Parsiphae.
+    pub fn builtin() -> Self {
+        let mut registry = Registry::new();
+        for rule in rules::all() {
+            registry.register(rule);
+        }
+        registry
+    }
+
+    /// Adds `rule`, which must not have the name of a rule already registered.
+    pub fn register(&mut self, rule: Box<dyn Lint>) {
+        assert!(
+            self.get(rule.name()).is_none(),
+            "lint {} is registered twice",
+            rule.name()
+        );
+        self.rules.push(rule);
+    }
+
+    pub fn rules(&self) -> &[Box<dyn Lint>] {
+        &self.rules
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Lint> {
+        self.rules
+            .iter()
+            .find(|rule| rule.name() == name)
+            .map(|rule| rule.as_ref())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        Registry::builtin()
+    }
+}
+
+/// Severities of the rules, where they differ from their defaults. `None` disables a rule.
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    levels: HashMap<String, Option<Severity>>,
+}
+
+impl Config {
+    /// Reads a config file, rejecting rules that are not in `registry`.
+    pub fn from_json(content: &[u8], registry: &Registry) -> Result<Self, String> {
+        let value: Value = serde_json::from_slice(content).map_err(|e| e.to_string())?;
+        let rules = match value.get("rules") {
+            Some(Value::Object(rules)) => rules,
+            Some(_) => return Err("\"rules\" must be an object".to_owned()),
+            None => return Ok(Config::default()),
+        };
+
+        let mut config = Config::default();
+        for (name, level) in rules {
+            let rule = registry
+                .get(name)
+                .ok_or_else(|| format!("unknown lint {}", name))?;
+            let level = match *level {
+                Value::Bool(true) => Some(rule.default_severity()),
+                Value::Bool(false) => None,
+                Value::String(ref level) if level == "allow" => None,
+                Value::String(ref level) => Some(level.parse()?),
+                _ => return Err(format!("invalid level for lint {}", name)),
+            };
+            config.set(name, level);
+        }
+        Ok(config)
+    }
+
+    pub fn set(&mut self, rule: &str, level: Option<Severity>) {
+        self.levels.insert(rule.to_owned(), level);
+    }
+
+    /// The severity `rule` is reported with, if it is enabled.
+    pub fn severity(&self, rule: &dyn Lint) -> Option<Severity> {
+        match self.levels.get(rule.name()) {
+            Some(level) => *level,
+            None if rule.enabled_by_default() => Some(rule.default_severity()),
+            None => None,
+        }
+    }
+}
+
+/// Runs the rules of `registry` that `config` enables, and returns what they found, except
+/// what allow comments in `sources` silence, ordered by rule.
+pub fn run(
+    cx: &Context,
+    registry: &Registry,
+    config: &Config,
+    sources: &SourceMap,
+) -> Vec<Diagnostic> {
+    let mut allowed = HashMap::new();
+    let mut diagnostics = Vec::new();
+    for rule in registry.rules() {
+        let severity = match config.severity(rule.as_ref()) {
+            Some(severity) => severity,
+            None => continue,
+        };
+
+        let mut findings = Vec::new();
+        rule.check(cx, &mut findings);
+        for finding in findings {
+            let source = sources.get(finding.span.file);
+            let allows = allowed.entry(finding.span.file).or_insert_with(|| {
+                source.map_or_else(Vec::new, |source| allow_comments(&source.content))
+            });
+            let line = source.map_or(0, |source| source.line_col(finding.span.start).line);
+            if allows
+                .iter()
+                .any(|allow| allow.0 == line && allow.1 == rule.name())
+            {
+                continue;
+            }
+
+            diagnostics.push(Diagnostic {
+                severity,
+                code: rule.name(),
+                message: finding.message,
+                span: Some(finding.span),
+                related: Vec::new(),
+            });
+        }
+    }
+    diagnostics
+}
+
+/// The 1-based lines that `// parsiphae: allow(...)` comments in `content` apply to, with each
+/// rule they allow. A comment on a line of its own applies to the next line.
+fn allow_comments(content: &[u8]) -> Vec<(usize, String)> {
+    let lines = LineIndex::new(content);
+    let mut allows = Vec::new();
+    for token in tokenize(content) {
+        for (offset, comment) in token.comments() {
+            if comment.kind != TriviaKind::LineComment {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&comment.text[2..]);
+            let rules = match text
+                .trim()
+                .strip_prefix("parsiphae:")
+                .and_then(|text| text.trim_start().strip_prefix("allow("))
+                .and_then(|text| text.split(')').next())
+            {
+                Some(rules) => rules,
+                None => continue,
+            };
+
+            let line = lines.line_col(offset).line;
+            let (line_start, _) = lines.line_range(line);
+            let own_line = content[line_start..offset]
+                .iter()
+                .all(|b| b.is_ascii_whitespace());
+            let line = if own_line { line + 1 } else { line };
+            allows.extend(
+                rules
+                    .split(',')
+                    .map(|rule| (line, rule.trim().to_owned()))
+                    .filter(|allow| !allow.1.is_empty()),
+            );
+        }
+    }
+    allows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use ppa::name_resolver::resolve;
+
+    const SCRIPTS: &str = "func void Unused() {};
+// parsiphae: allow(unused-function)
+func void Allowed() {};
+func void AlsoAllowed() {}; // parsiphae: allow(unreachable-code, unused-function)
+const int MAX_LEVEL = 1;
+";
+
+    fn lint(config: &Config) -> Vec<(&'static str, Severity, usize)> {
+        let mut sources = SourceMap::new();
+        let file = sources.add("test.d", SCRIPTS.as_bytes().to_vec());
+        let (ast, errors) = parse_recovering(SCRIPTS.as_bytes(), file);
+        assert!(errors.is_empty(), "{:?}", errors);
+        let asts = vec![ast];
+        let resolution = resolve(&asts);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        let cx = Context {
+            asts: &asts,
+            resolution: &resolution,
+        };
+
+        run(&cx, &Registry::builtin(), config, &sources)
+            .into_iter()
+            .map(|diagnostic| {
+                let line = sources.resolve(diagnostic.span.unwrap()).unwrap().1.line;
+                (diagnostic.code, diagnostic.severity, line)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn allow_comments_silence_findings() {
+        assert_eq!(
+            lint(&Config::default()),
+            vec![
+                ("unused-function", Severity::Warning, 1),
+                ("unused-constant", Severity::Warning, 5),
+            ]
+        );
+    }
+
+    #[test]
+    fn configs() {
+        let registry = Registry::builtin();
+        let config = Config::from_json(
+            br#"{"rules": {"unused-function": "error", "unused-constant": false}}"#,
+            &registry,
+        )
+        .unwrap();
+        assert_eq!(lint(&config), vec![("unused-function", Severity::Error, 1)]);
+
+        let config = Config::from_json(br#"{"rules": {"unused-instance": true}}"#, &registry);
+        let instances = registry.get("unused-instance").unwrap();
+        assert_eq!(config.unwrap().severity(instances), Some(Severity::Warning));
+        assert_eq!(Config::default().severity(instances), None);
+
+        assert!(Config::from_json(br#"{"rules": {"no-such-rule": true}}"#, &registry).is_err());
+        assert!(
+            Config::from_json(br#"{"rules": {"unused-constant": "fatal"}}"#, &registry).is_err()
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_eq!(
+            allow_comments(
+                b"var int x; // parsiphae: allow(a, b)\n  //parsiphae: allow(c)\n// other"
+            ),
+            vec![
+                (1, "a".to_owned()),
+                (1, "b".to_owned()),
+                (3, "c".to_owned()),
+            ]
+        );
+        assert_eq!(
+            allow_comments(
+                b"Print(\"http://x\"); // parsiphae: allow(d)\n/* // parsiphae: allow(e) */"
+            ),
+            vec![(1, "d".to_owned())]
+        );
+    }
+}
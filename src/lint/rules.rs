@@ -0,0 +1,288 @@
+//! The rules that come with Parsiphae.
+
+use lint::{Context, Finding, Lint};
+use ppa::dead_code;
+use ppa::name_resolver::Resolution;
+use ppa::visitor::{visit_ast, VisitorMut};
+use types::{
+    AssignmentOperator, Expression, Function, Identifier, Instance, Prototype, Statement,
+    SymbolKind,
+};
+
+pub fn all() -> Vec<Box<dyn Lint>> {
+    vec![
+        Box::new(Unused {
+            kind: SymbolKind::Func,
+            description: "Functions that are never called or assigned",
+            enabled: true,
+        }),
+        Box::new(Unused {
+            kind: SymbolKind::Const,
+            description: "Constants that are never read",
+            enabled: true,
+        }),
+        // Items that are only placed in worlds would all be reported.
+        Box::new(Unused {
+            kind: SymbolKind::Instance,
+            description: "Instances that are never referenced",
+            enabled: false,
+        }),
+        Box::new(UnreachableCode),
+        Box::new(SelfAssignment),
+    ]
+}
+
+/// Unused symbols of one kind, as found by `ppa::dead_code`. Constants include constant arrays.
+struct Unused {
+    kind: SymbolKind,
+    description: &'static str,
+    enabled: bool,
+}
+
+impl Lint for Unused {
+    fn name(&self) -> &'static str {
+        dead_code::code(self.kind)
+    }
+
+    fn description(&self) -> &'static str {
+        self.description
+    }
+
+    fn enabled_by_default(&self) -> bool {
+        self.enabled
+    }
+
+    fn check(&self, cx: &Context, findings: &mut Vec<Finding>) {
+        let symbols = &cx.resolution.symbols;
+        for id in dead_code::unused(cx.resolution) {
+            let symbol = symbols.get(id);
+            if dead_code::code(symbol.kind) == self.name() {
+                findings.push(Finding {
+                    span: symbol.name.span,
+                    message: dead_code::message(symbol),
+                });
+            }
+        }
+    }
+}
+
+/// Statements after a `return` in the same block.
+struct UnreachableCode;
+
+impl Lint for UnreachableCode {
+    fn name(&self) -> &'static str {
+        "unreachable-code"
+    }
+
+    fn description(&self) -> &'static str {
+        "Statements after a return that are never executed"
+    }
+
+    fn check(&self, cx: &Context, findings: &mut Vec<Finding>) {
+        let mut visitor = UnreachableVisitor { findings };
+        for ast in cx.asts {
+            visit_ast(ast, &mut visitor);
+        }
+    }
+}
+
+struct UnreachableVisitor<'a> {
+    findings: &'a mut Vec<Finding>,
+}
+
+impl<'a> UnreachableVisitor<'a> {
+    /// Reports the first statement of `block` after a return; the rest is unreachable too.
+    fn block(&mut self, block: &[Statement]) {
+        let first_return = block
+            .iter()
+            .position(|statement| matches!(*statement, Statement::ReturnStatement(..)));
+        if let Some(unreachable) = first_return.and_then(|i| block.get(i + 1)) {
+            self.findings.push(Finding {
+                span: unreachable.span(),
+                message: "Unreachable statement".to_owned(),
+            });
+        }
+    }
+}
+
+impl<'a> VisitorMut for UnreachableVisitor<'a> {
+    fn visit_func_decl(&mut self, decl: &Function) {
+        self.block(&decl.body);
+    }
+
+    fn visit_inst_decl(&mut self, decl: &Instance) {
+        self.block(&decl.body);
+    }
+
+    fn visit_proto_decl(&mut self, decl: &Prototype) {
+        self.block(&decl.body);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement, _scope: &Identifier) {
+        if let Statement::If(ref if_statement) = *statement {
+            for branch in &if_statement.branches {
+                self.block(&branch.body);
+            }
+            if let Some(ref else_branch) = if_statement.else_branch {
+                self.block(else_branch);
+            }
+        }
+    }
+}
+
+/// Assignments of a variable to itself, like `x = x;`, which are usually typos.
+struct SelfAssignment;
+
+impl Lint for SelfAssignment {
+    fn name(&self) -> &'static str {
+        "self-assignment"
+    }
+
+    fn description(&self) -> &'static str {
+        "Assignments of a variable to itself"
+    }
+
+    fn check(&self, cx: &Context, findings: &mut Vec<Finding>) {
+        let mut visitor = SelfAssignmentVisitor {
+            resolution: cx.resolution,
+            findings,
+        };
+        for ast in cx.asts {
+            visit_ast(ast, &mut visitor);
+        }
+    }
+}
+
+struct SelfAssignmentVisitor<'a> {
+    resolution: &'a Resolution,
+    findings: &'a mut Vec<Finding>,
+}
+
+impl<'a> VisitorMut for SelfAssignmentVisitor<'a> {
+    fn visit_statement(&mut self, statement: &Statement, _scope: &Identifier) {
+        let ass = match *statement {
+            Statement::Ass(ref ass) if ass.op == AssignmentOperator::Eq => ass,
+            _ => return,
+        };
+        let value = match ass.exp {
+            Expression::Identifier(ref value) => value,
+            _ => return,
+        };
+
+        let target = self.resolution.symbol_at(ass.var.name.span);
+        let same_instance = match (&ass.var.instance, &value.instance) {
+            (Some(a), Some(b)) => a.key() == b.key(),
+            (None, None) => true,
+            _ => false,
+        };
+        // Spans are ignored when comparing expressions, so this compares the indices only.
+        if target.is_some()
+            && target == self.resolution.symbol_at(value.name.span)
+            && same_instance
+            && ass.var.index == value.index
+        {
+            self.findings.push(Finding {
+                span: ass.span,
+                message: format!("Variable is assigned to itself: '{}'", ass.var.name),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use ppa::name_resolver::resolve;
+    use types::FileId;
+
+    fn check(rule: &dyn Lint, content: &str) -> Vec<String> {
+        let (ast, errors) = parse_recovering(content.as_bytes(), FileId(0));
+        assert!(errors.is_empty(), "{:?}", errors);
+        let asts = vec![ast];
+        let resolution = resolve(&asts);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+        let mut findings = Vec::new();
+        rule.check(
+            &Context {
+                asts: &asts,
+                resolution: &resolution,
+            },
+            &mut findings,
+        );
+        findings
+            .into_iter()
+            .map(|finding| {
+                let text = &content.as_bytes()[finding.span.start..finding.span.end];
+                format!("{}: {}", finding.message, String::from_utf8_lossy(text))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn unreachable_code() {
+        let findings = check(
+            &UnreachableCode,
+            "func int f(var int x) {
+    if (x) { return 1; x = 2; } else { return 2; };
+    return 3;
+    x = 4;
+    x = 5;
+};",
+        );
+        assert_eq!(
+            findings,
+            vec![
+                "Unreachable statement: x = 4",
+                "Unreachable statement: x = 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn self_assignment() {
+        let findings = check(
+            &SelfAssignment,
+            "class C_NPC { var int attribute[2]; };
+instance Hero(C_NPC) {};
+instance Other(C_NPC) {};
+var int x;
+func void f(var int y) {
+    x = x; x += x; x = y; y = y;
+    Hero.attribute[0] = Hero.attribute[0];
+    Hero.attribute[0] = Hero.attribute[1];
+    Hero.attribute[0] = Other.attribute[0];
+};",
+        );
+        assert_eq!(
+            findings,
+            vec![
+                "Variable is assigned to itself: 'x': x = x",
+                "Variable is assigned to itself: 'y': y = y",
+                "Variable is assigned to itself: 'attribute': Hero.attribute[0] = Hero.attribute[0]",
+            ]
+        );
+    }
+
+    #[test]
+    fn unused_rules() {
+        let rules = all();
+        let names: Vec<&str> = rules.iter().map(|rule| rule.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "unused-function",
+                "unused-constant",
+                "unused-instance",
+                "unreachable-code",
+                "self-assignment",
+            ]
+        );
+        let findings = check(
+            rules[2].as_ref(),
+            "class C_NPC { var int id; }; instance Mud(C_NPC) {}; func void Unused() {};",
+        );
+        assert_eq!(findings, vec!["Instance is never used: 'Mud': Mud"]);
+    }
+}
@@ -0,0 +1,759 @@
+//! A language server for Daedalus, spoken over stdin and stdout by the `parsiphae-lsp` binary.
+//!
+//! The server reads the `Gothic.src` it finds in the workspace (or the one passed as `src` in
+//! the initialization options) and analyses all of its files together. Diagnostics are
+//! published when a file is saved; go to definition, hover, document and workspace symbols work
+//! on the contents of the open editors.
+//!
+//! Columns of the protocol count UTF-16 code units, which are converted from and to byte
+//! offsets with the charset of the scripts, as a character may take several bytes in UTF-8.
+
+mod transport;
+mod workspace;
+
+pub use self::transport::{read_message, write_message};
+pub use self::workspace::{Analysis, Workspace};
+
+use diagnostics::{Diagnostic, Severity};
+use serde_json::Value;
+use source_map::{decode, encode, file_uri, path_from_uri, LineCol, SourceFile};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use types::{charset, set_charset, Charset, FileId, Span, SymbolId, SymbolKind};
+
+const SERVER_NOT_INITIALIZED: i64 = -32002;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// How deep below the workspace root to look for a `Gothic.src`.
+const SRC_SEARCH_DEPTH: usize = 8;
+
+type RequestResult = Result<Value, (i64, String)>;
+
+/// Serves requests from `input` until the client sends `exit`. Returns whether the client
+/// asked for a `shutdown` before, which the binary reports as its exit code.
+pub fn run<R: BufRead, W: Write>(mut input: R, output: W) -> io::Result<bool> {
+    let mut server = Server::new(output);
+    while let Some(message) = read_message(&mut input)? {
+        if !server.handle(&message)? {
+            break;
+        }
+    }
+    Ok(server.shutdown)
+}
+
+struct Server<W> {
+    output: W,
+    workspace: Option<Workspace>,
+    /// Files with diagnostics, which have to be cleared once they are fixed.
+    published: HashSet<PathBuf>,
+    shutdown: bool,
+}
+
+impl<W: Write> Server<W> {
+    fn new(output: W) -> Self {
+        Server {
+            output,
+            workspace: None,
+            published: HashSet::new(),
+            shutdown: false,
+        }
+    }
+
+    /// Returns whether to continue serving.
+    fn handle(&mut self, message: &Value) -> io::Result<bool> {
+        let method = match message["method"].as_str() {
+            Some(method) => method,
+            // A response to a request of ours, which we never send.
+            None => return Ok(true),
+        };
+        let params = &message["params"];
+
+        match message.get("id") {
+            Some(id) => {
+                let result = if self.shutdown {
+                    Err((INVALID_REQUEST, "the server is shutting down".to_owned()))
+                } else if self.workspace.is_none() && method != "initialize" {
+                    Err((
+                        SERVER_NOT_INITIALIZED,
+                        "the server is not initialized".to_owned(),
+                    ))
+                } else {
+                    self.request(method, params)
+                };
+
+                let response = match result {
+                    Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+                    Err((code, message)) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {"code": code, "message": message},
+                    }),
+                };
+                write_message(&mut self.output, &response)?;
+            }
+            None if method == "exit" => return Ok(false),
+            None if self.workspace.is_some() => self.notification(method, params)?,
+            None => {}
+        }
+
+        Ok(true)
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> RequestResult {
+        match method {
+            "initialize" => self.initialize(params),
+            "shutdown" => {
+                self.shutdown = true;
+                Ok(Value::Null)
+            }
+            "textDocument/definition" => self.definition(params),
+            "textDocument/hover" => self.hover(params),
+            "textDocument/documentSymbol" => self.document_symbols(params),
+            "workspace/symbol" => Ok(self.workspace_symbols(params)),
+            _ => Err((METHOD_NOT_FOUND, format!("unknown method {}", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        let path = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(path_from_uri);
+        let workspace = self.workspace.as_mut().unwrap();
+
+        match (method, path) {
+            ("initialized", _) => self.publish_diagnostics(),
+            ("textDocument/didOpen", Some(path)) => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or("");
+                workspace.open(&path, encode(text));
+                Ok(())
+            }
+            ("textDocument/didChange", Some(path)) => {
+                // Only full synchronization is announced, so the last change is the whole text.
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|c| c.last())
+                    .and_then(|c| c["text"].as_str())
+                {
+                    workspace.open(&path, encode(text));
+                }
+                Ok(())
+            }
+            ("textDocument/didSave", Some(path)) => {
+                if let Some(text) = params["text"].as_str() {
+                    workspace.open(&path, encode(text));
+                }
+                self.publish_diagnostics()
+            }
+            ("textDocument/didClose", Some(path)) => {
+                workspace.close(&path);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn initialize(&mut self, params: &Value) -> RequestResult {
+        let encoding = params["initializationOptions"]["encoding"].as_str();
+        match encoding.map(str::parse::<Charset>) {
+            Some(Ok(charset)) => set_charset(charset),
+            Some(Err(e)) => eprintln!("{}, using {}", e, Charset::Windows1252.name()),
+            None => {}
+        }
+
+        let src = match params["initializationOptions"]["src"].as_str() {
+            Some(src) => Some(PathBuf::from(src)),
+            None => {
+                let root = params["rootUri"]
+                    .as_str()
+                    .and_then(path_from_uri)
+                    .or_else(|| params["rootPath"].as_str().map(PathBuf::from));
+                root.and_then(|root| find_src(&root))
+            }
+        };
+
+        let externals = params["initializationOptions"]["externals"]
+            .as_str()
+            .unwrap_or("g2notr");
+        let workspace = match src {
+            Some(ref src) => Workspace::new(Some(src), externals),
+            None => {
+                eprintln!("No Gothic.src found, only open files are analysed");
+                Workspace::new(None, externals)
+            }
+        };
+        self.workspace = Some(workspace.map_err(|e| {
+            (
+                INVALID_PARAMS,
+                format!("could not read {:?}: {:?}", src.unwrap_or_default(), e),
+            )
+        })?);
+
+        Ok(json!({
+            "capabilities": {
+                "textDocumentSync": {
+                    "openClose": true,
+                    "change": 1,
+                    "save": {"includeText": false},
+                },
+                "definitionProvider": true,
+                "hoverProvider": true,
+                "documentSymbolProvider": true,
+                "workspaceSymbolProvider": true,
+            },
+            "serverInfo": {"name": "parsiphae-lsp", "version": env!("CARGO_PKG_VERSION")},
+        }))
+    }
+
+    fn publish_diagnostics(&mut self) -> io::Result<()> {
+        let analysis = self.workspace.as_mut().unwrap().analysis();
+
+        let mut diagnostics: HashMap<FileId, Vec<Value>> = HashMap::new();
+        for err in &analysis.errors {
+            if let Some((span, diagnostic)) = lsp_diagnostic(analysis, &Diagnostic::from(err)) {
+                diagnostics.entry(span.file).or_default().push(diagnostic);
+            }
+        }
+
+        let mut published = HashSet::new();
+        for file in analysis.sources.files() {
+            let diagnostics = diagnostics.remove(&file.id).unwrap_or_default();
+            if diagnostics.is_empty() && !self.published.contains(&file.path) {
+                continue;
+            }
+            if !diagnostics.is_empty() {
+                published.insert(file.path.clone());
+            }
+
+            let notification = json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": {"uri": file_uri(&file.path), "diagnostics": diagnostics},
+            });
+            write_message(&mut self.output, &notification)?;
+        }
+
+        self.published = published;
+        Ok(())
+    }
+
+    /// The analysis and the file and offset of a `TextDocumentPositionParams`.
+    fn position(&mut self, params: &Value) -> Result<(&Analysis, FileId, usize), (i64, String)> {
+        let path = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(path_from_uri)
+            .ok_or_else(|| (INVALID_PARAMS, "invalid document".to_owned()))?;
+        let position = &params["position"];
+        let (line, character) = match (position["line"].as_u64(), position["character"].as_u64()) {
+            (Some(line), Some(character)) => (line as usize, character as usize),
+            _ => return Err((INVALID_PARAMS, "invalid position".to_owned())),
+        };
+
+        let analysis = self.workspace.as_mut().unwrap().analysis();
+        let file = analysis.file_id(&path).ok_or_else(|| {
+            (
+                INVALID_PARAMS,
+                format!("{:?} is not part of the workspace", path),
+            )
+        })?;
+        let offset = utf16_offset(
+            analysis.sources.get(file).unwrap(),
+            line,
+            character,
+            charset(),
+        );
+
+        Ok((analysis, file, offset))
+    }
+
+    fn definition(&mut self, params: &Value) -> RequestResult {
+        let (analysis, file, offset) = self.position(params)?;
+        Ok(match analysis.symbol_at(file, offset) {
+            // Externals are not declared in any file the client could open.
+            Some((_, symbol)) if !analysis.resolution.symbols.get(symbol).external => {
+                let name = analysis.resolution.symbols.get(symbol).name.span;
+                location(analysis, name)
+            }
+            _ => Value::Null,
+        })
+    }
+
+    fn hover(&mut self, params: &Value) -> RequestResult {
+        let (analysis, file, offset) = self.position(params)?;
+        let (span, symbol) = match analysis.symbol_at(file, offset) {
+            Some(found) => found,
+            None => return Ok(Value::Null),
+        };
+
+        let mut value = format!("```daedalus\n{}\n```", analysis.signature(symbol));
+        if let Some(scope) = analysis.resolution.symbols.get(symbol).scope {
+            let scope = &analysis.resolution.symbols.get(scope).name;
+            value.push_str(&format!("\n\nDeclared in `{}`", decode(scope.as_bytes())));
+        }
+
+        Ok(json!({
+            "contents": {"kind": "markdown", "value": value},
+            "range": range(analysis.sources.get(file).unwrap(), span),
+        }))
+    }
+
+    /// The global symbols of a file, with their parameters, locals and members as children.
+    fn document_symbols(&mut self, params: &Value) -> RequestResult {
+        let path = params["textDocument"]["uri"]
+            .as_str()
+            .and_then(path_from_uri)
+            .ok_or_else(|| (INVALID_PARAMS, "invalid document".to_owned()))?;
+        let analysis = self.workspace.as_mut().unwrap().analysis();
+        let file = match analysis.file_id(&path) {
+            Some(file) => file,
+            None => return Ok(json!([])),
+        };
+
+        let source = analysis.sources.get(file).unwrap();
+        let symbols = &analysis.resolution.symbols;
+        let document_symbol = |id: SymbolId, children: Vec<Value>| {
+            let symbol = symbols.get(id);
+            json!({
+                "name": decode(symbol.name.as_bytes()),
+                "detail": analysis.signature(id),
+                "kind": symbol_kind(analysis, id),
+                "range": range(source, symbol.span.to(symbol.name.span)),
+                "selectionRange": range(source, symbol.name.span),
+                "children": children,
+            })
+        };
+
+        let result: Vec<Value> = symbols
+            .iter()
+            .filter(|(_, symbol)| symbol.is_global() && symbol.name.span.file == file)
+            .map(|(id, _)| {
+                let children = symbols
+                    .children(id)
+                    .iter()
+                    .map(|child| document_symbol(*child, Vec::new()))
+                    .collect();
+                document_symbol(id, children)
+            })
+            .collect();
+        Ok(Value::Array(result))
+    }
+
+    /// Global symbols of the scripts whose name contains the query, ignoring case.
+    fn workspace_symbols(&mut self, params: &Value) -> Value {
+        let query = params["query"].as_str().unwrap_or("").to_uppercase();
+        let analysis = self.workspace.as_mut().unwrap().analysis();
+
+        let result: Vec<Value> = analysis
+            .resolution
+            .symbols
+            .iter()
+            .filter(|(_, symbol)| symbol.is_global() && !symbol.external)
+            .filter(|(_, symbol)| {
+                decode(symbol.name.as_bytes())
+                    .to_uppercase()
+                    .contains(&query)
+            })
+            .map(|(id, symbol)| {
+                json!({
+                    "name": decode(symbol.name.as_bytes()),
+                    "kind": symbol_kind(analysis, id),
+                    "location": location(analysis, symbol.name.span),
+                })
+            })
+            .collect();
+        Value::Array(result)
+    }
+}
+
+/// The location and LSP representation of a diagnostic, if it has a location.
+fn lsp_diagnostic(analysis: &Analysis, diagnostic: &Diagnostic) -> Option<(Span, Value)> {
+    let span = diagnostic.span?;
+    let source = analysis.sources.get(span.file)?;
+    let severity = match diagnostic.severity {
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Note => 3,
+    };
+    let related: Vec<Value> = diagnostic
+        .related
+        .iter()
+        .map(|label| json!({"location": location(analysis, label.span), "message": label.message}))
+        .collect();
+
+    Some((
+        span,
+        json!({
+            "range": range(source, span),
+            "severity": severity,
+            "code": diagnostic.code,
+            "source": "parsiphae",
+            "message": diagnostic.message,
+            "relatedInformation": related,
+        }),
+    ))
+}
+
+fn symbol_kind(analysis: &Analysis, id: SymbolId) -> u8 {
+    let symbols = &analysis.resolution.symbols;
+    let symbol = symbols.get(id);
+    let in_class = symbol
+        .scope
+        .is_some_and(|scope| symbols.get(scope).kind == SymbolKind::Class);
+
+    match symbol.kind {
+        SymbolKind::Var if in_class => 8,                 // Field
+        SymbolKind::Var | SymbolKind::Param => 13,        // Variable
+        SymbolKind::Const | SymbolKind::ConstArray => 14, // Constant
+        SymbolKind::Func => 12,                           // Function
+        SymbolKind::Class => 5,                           // Class
+        SymbolKind::Prototype => 11,                      // Interface
+        SymbolKind::Instance => 19,                       // Object
+    }
+}
+
+fn range(file: &SourceFile, span: Span) -> Value {
+    let position = |offset| {
+        let (line, character) = utf16_position(file, offset, charset());
+        json!({"line": line, "character": character})
+    };
+    json!({"start": position(span.start), "end": position(span.end)})
+}
+
+/// The 0-based line and UTF-16 column of the byte `offset` in `file`, which is encoded in
+/// `charset`.
+fn utf16_position(file: &SourceFile, offset: usize, charset: Charset) -> (usize, usize) {
+    let offset = offset.min(file.content.len());
+    let position = file.line_col(offset);
+    let line_start = offset - (position.column - 1);
+    let character = characters(&file.content[line_start..offset], charset)
+        .map(|(_, units)| units)
+        .sum();
+    (position.line - 1, character)
+}
+
+/// Inverse of `utf16_position`; columns past the end of a line are clamped to it.
+fn utf16_offset(file: &SourceFile, line: usize, character: usize, charset: Charset) -> usize {
+    let start = file.lines.offset(LineCol {
+        line: line + 1,
+        column: 1,
+    });
+    if line >= file.lines.line_count() {
+        return start;
+    }
+    let (_, end) = file.lines.line_range(line + 1);
+
+    let mut offset = start;
+    let mut units = 0;
+    for (len, char_units) in characters(&file.content[start..end], charset) {
+        if units >= character {
+            break;
+        }
+        units += char_units;
+        offset += len;
+    }
+    offset
+}
+
+/// The characters of `bytes` in `charset` as their length in bytes and in UTF-16 code units.
+/// Bytes that do not decode count as one replacement character each, as editors show them.
+fn characters(bytes: &[u8], charset: Charset) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let mut pos = 0;
+    ::std::iter::from_fn(move || {
+        let rest = &bytes[pos..];
+        if rest.is_empty() {
+            return None;
+        }
+        let character = (1..=rest.len().min(4))
+            .find_map(|len| {
+                let text = charset.decode(&rest[..len]);
+                // A replacement character re-encodes differently from the bytes it replaced.
+                if charset.encode(&text) == rest[..len] {
+                    Some((len, text.encode_utf16().count()))
+                } else {
+                    None
+                }
+            })
+            .unwrap_or((1, 1));
+        pos += character.0;
+        Some(character)
+    })
+}
+
+fn location(analysis: &Analysis, span: Span) -> Value {
+    match analysis.sources.get(span.file) {
+        Some(file) => json!({"uri": file_uri(&file.path), "range": range(file, span)}),
+        None => Value::Null,
+    }
+}
+
+/// Breadth-first search for a `Gothic.src` below `root`, ignoring case.
+fn find_src(root: &Path) -> Option<PathBuf> {
+    let mut queue = VecDeque::new();
+    queue.push_back((root.to_owned(), 0));
+
+    while let Some((dir, depth)) = queue.pop_front() {
+        let mut entries: Vec<PathBuf> = match ::std::fs::read_dir(&dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+            Err(_) => continue,
+        };
+        entries.sort();
+
+        for entry in entries {
+            let is_src = entry
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case("gothic.src"));
+            if is_src && entry.is_file() {
+                return Some(entry);
+            }
+            if entry.is_dir() && depth < SRC_SEARCH_DEPTH {
+                queue.push_back((entry, depth + 1));
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inner_errors::ParserError;
+    use source_map::LineIndex;
+    use std::fs;
+
+    /// A client that sends a fixed script of messages and collects everything the server says.
+    struct ScriptedClient {
+        input: Vec<u8>,
+        next_id: u64,
+    }
+
+    impl ScriptedClient {
+        fn new() -> Self {
+            ScriptedClient {
+                input: Vec::new(),
+                next_id: 1,
+            }
+        }
+
+        fn request(&mut self, method: &str, params: Value) -> u64 {
+            let id = self.next_id;
+            self.next_id += 1;
+            let message = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+            write_message(&mut self.input, &message).unwrap();
+            id
+        }
+
+        fn notify(&mut self, method: &str, params: Value) {
+            let message = json!({"jsonrpc": "2.0", "method": method, "params": params});
+            write_message(&mut self.input, &message).unwrap();
+        }
+
+        /// Runs the server on the script and returns whether it exited cleanly, plus its output.
+        fn run(&self) -> (bool, Vec<Value>) {
+            let mut output = Vec::new();
+            let clean = run(&self.input[..], &mut output).unwrap();
+
+            let mut messages = Vec::new();
+            let mut output = &output[..];
+            while let Some(message) = read_message(&mut output).unwrap() {
+                messages.push(message);
+            }
+            (clean, messages)
+        }
+    }
+
+    fn response(messages: &[Value], id: u64) -> &Value {
+        let message = messages
+            .iter()
+            .find(|message| message["id"] == json!(id))
+            .expect("no response");
+        &message["result"]
+    }
+
+    fn diagnostics(messages: &[Value]) -> Vec<(String, Vec<String>)> {
+        messages
+            .iter()
+            .filter(|message| message["method"] == "textDocument/publishDiagnostics")
+            .map(|message| {
+                let uri = message["params"]["uri"].as_str().unwrap();
+                let file = uri.rsplit('/').next().unwrap().to_owned();
+                let messages = message["params"]["diagnostics"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|d| d["message"].as_str().unwrap().to_owned())
+                    .collect();
+                (file, messages)
+            })
+            .collect()
+    }
+
+    fn position(uri: &str, line: u64, character: u64) -> Value {
+        json!({"textDocument": {"uri": uri}, "position": {"line": line, "character": character}})
+    }
+
+    #[test]
+    fn utf16_columns() {
+        let text = "const string A = \"Grüße 😀\"; var int b;\nvar int c;";
+        let file = SourceFile {
+            id: FileId(0),
+            path: PathBuf::from("test.d"),
+            content: text.as_bytes().to_vec(),
+            lines: LineIndex::new(text.as_bytes()),
+        };
+
+        let b = text.find('b').unwrap();
+        assert_eq!(utf16_position(&file, b, Charset::Utf8), (0, 37));
+        assert_eq!(utf16_offset(&file, 0, 37, Charset::Utf8), b);
+        assert_eq!(
+            utf16_offset(&file, 0, 500, Charset::Utf8),
+            text.find('\n').unwrap()
+        );
+        assert_eq!(
+            utf16_offset(&file, 1, 8, Charset::Utf8),
+            text.rfind('c').unwrap()
+        );
+        assert_eq!(utf16_offset(&file, 5, 0, Charset::Utf8), text.len());
+
+        // Every invalid byte is one character, the columns after it are unaffected.
+        let invalid = b"var int \xFF\xFEa; var int b;";
+        let file = SourceFile {
+            content: invalid.to_vec(),
+            lines: LineIndex::new(invalid),
+            ..file
+        };
+        let b = invalid.len() - 2;
+        assert_eq!(utf16_position(&file, b, Charset::Utf8), (0, b));
+        assert_eq!(utf16_offset(&file, 0, b, Charset::Utf8), b);
+
+        // In Windows-1252, every byte is one character.
+        assert_eq!(utf16_position(&file, b, Charset::Windows1252), (0, b));
+        assert_eq!(utf16_offset(&file, 0, b, Charset::Windows1252), b);
+    }
+
+    #[test]
+    fn session() {
+        let root = ::std::env::temp_dir().join(format!("parsiphae-lsp-{}", ::std::process::id()));
+        let scripts = root.join("Scripts");
+        fs::create_dir_all(&scripts).unwrap();
+        fs::write(scripts.join("Gothic.src"), "classes.d\r\nstory.d\r\n").unwrap();
+        fs::write(
+            scripts.join("classes.d"),
+            "class C_NPC { var int id; };\nfunc int add(var int a, var int b) { return a + b; };\n",
+        )
+        .unwrap();
+        let story = "instance Hero(C_NPC) {\n\tid = add(1, 2);\n};\n";
+        fs::write(scripts.join("story.d"), story).unwrap();
+
+        let classes_uri = file_uri(&scripts.join("classes.d"));
+        let story_uri = file_uri(&scripts.join("story.d"));
+        let mut client = ScriptedClient::new();
+        let initialize = client.request("initialize", json!({"rootUri": file_uri(&root)}));
+        client.notify("initialized", json!({}));
+        client.notify(
+            "textDocument/didOpen",
+            json!({"textDocument": {"uri": story_uri, "languageId": "daedalus", "version": 1, "text": story}}),
+        );
+        let definition = client.request("textDocument/definition", position(&story_uri, 1, 7));
+        let hover = client.request("textDocument/hover", position(&story_uri, 1, 8));
+        let member = client.request("textDocument/hover", position(&story_uri, 1, 1));
+        let document_symbols = client.request(
+            "textDocument/documentSymbol",
+            json!({"textDocument": {"uri": classes_uri}}),
+        );
+        let workspace_symbols = client.request("workspace/symbol", json!({"query": "hE"}));
+
+        let broken = "instance Hero(C_NPC) {\n\tid = sub(1, 2)\n};\n";
+        client.notify(
+            "textDocument/didChange",
+            json!({"textDocument": {"uri": story_uri, "version": 2}, "contentChanges": [{"text": broken}]}),
+        );
+        client.notify(
+            "textDocument/didSave",
+            json!({"textDocument": {"uri": story_uri}}),
+        );
+        client.notify(
+            "textDocument/didChange",
+            json!({"textDocument": {"uri": story_uri, "version": 3}, "contentChanges": [{"text": story}]}),
+        );
+        client.notify(
+            "textDocument/didSave",
+            json!({"textDocument": {"uri": story_uri}}),
+        );
+        let oversized =
+            "var int a[99999999999999999999];\nconst int a[99999999999999999999] = {1};\n";
+        client.notify(
+            "textDocument/didChange",
+            json!({"textDocument": {"uri": story_uri, "version": 4}, "contentChanges": [{"text": oversized}]}),
+        );
+        let after_oversized = client.request("textDocument/hover", position(&story_uri, 0, 8));
+        let unknown = client.request("textDocument/rename", json!({}));
+        let shutdown = client.request("shutdown", Value::Null);
+        client.notify("exit", Value::Null);
+
+        let (clean, messages) = client.run();
+        fs::remove_dir_all(&root).unwrap();
+        assert!(clean);
+
+        assert_eq!(
+            response(&messages, initialize)["capabilities"]["definitionProvider"],
+            json!(true)
+        );
+        assert_eq!(
+            response(&messages, definition),
+            &json!({
+                "uri": classes_uri,
+                "range": {"start": {"line": 1, "character": 9}, "end": {"line": 1, "character": 12}},
+            })
+        );
+        assert_eq!(
+            response(&messages, hover)["contents"]["value"],
+            json!("```daedalus\nfunc int add(var int a, var int b)\n```")
+        );
+        assert_eq!(
+            response(&messages, member)["contents"]["value"],
+            json!("```daedalus\nvar int id\n```\n\nDeclared in `C_NPC`")
+        );
+
+        let document_symbols = response(&messages, document_symbols);
+        assert_eq!(document_symbols[0]["name"], json!("C_NPC"));
+        assert_eq!(document_symbols[0]["children"][0]["kind"], json!(8));
+        assert_eq!(
+            document_symbols[1]["detail"],
+            json!("func int add(var int a, var int b)")
+        );
+        assert_eq!(document_symbols[1]["children"].as_array().unwrap().len(), 2);
+        assert_eq!(
+            response(&messages, workspace_symbols),
+            &json!([{
+                "name": "Hero",
+                "kind": 19,
+                "location": {
+                    "uri": story_uri,
+                    "range": {"start": {"line": 0, "character": 9}, "end": {"line": 0, "character": 13}},
+                },
+            }])
+        );
+
+        // Nothing to report after `initialized`, then the broken save and the fix.
+        assert_eq!(
+            diagnostics(&messages),
+            vec![
+                (
+                    "story.d".to_owned(),
+                    vec![ParserError::MissingSemi.description().to_owned()]
+                ),
+                ("story.d".to_owned(), vec![]),
+            ]
+        );
+
+        // Oversized array sizes are syntax errors, which leave nothing to hover.
+        assert_eq!(response(&messages, after_oversized), &Value::Null);
+
+        let unknown = messages.iter().find(|m| m["id"] == json!(unknown)).unwrap();
+        assert_eq!(unknown["error"]["code"], json!(METHOD_NOT_FOUND));
+        assert_eq!(response(&messages, shutdown), &Value::Null);
+    }
+}
@@ -0,0 +1,65 @@
+use serde_json::{self, Value};
+use std::io::{self, BufRead, Write};
+
+/// Reads the next message, framed by a `Content-Length` header like every LSP message.
+/// Returns `None` once the input is closed.
+pub fn read_message<R: BufRead>(input: &mut R) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let mut parts = line.splitn(2, ':');
+        if let (Some(name), Some(value)) = (parts.next(), parts.next()) {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+
+    let length = length
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length"))?;
+    let mut content = vec![0; length];
+    input.read_exact(&mut content)?;
+
+    serde_json::from_slice(&content)
+        .map(Some)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+pub fn write_message<W: Write>(output: &mut W, message: &Value) -> io::Result<()> {
+    let content = message.to_string();
+    write!(
+        output,
+        "Content-Length: {}\r\n\r\n{}",
+        content.len(),
+        content
+    )?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn framing() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({"id": 1, "method": "ä"})).unwrap();
+        write_message(&mut buffer, &json!(null)).unwrap();
+
+        let mut input = &buffer[..];
+        assert_eq!(
+            read_message(&mut input).unwrap(),
+            Some(json!({"id": 1, "method": "ä"}))
+        );
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!(null)));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+}
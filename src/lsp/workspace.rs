@@ -0,0 +1,224 @@
+use cst::{self, TokenKind};
+use errors::{Error, Result};
+use externals;
+use ppa::array_checker;
+use ppa::constant::Evaluator;
+use ppa::inheritance;
+use ppa::name_resolver::{resolve_files, Resolution};
+use ppa::type_checker::check;
+use source_map::{decode, SourceMap};
+use src_parser;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use types::{ArraySizeDeclaration, FileId, Span, SymbolId, SymbolKind, AST};
+
+/// The scripts listed in a `Gothic.src`, plus the files opened in the editor, whose unsaved
+/// contents take precedence over the ones on disk.
+pub struct Workspace {
+    files: Vec<PathBuf>,
+    /// Bundled externals or the path of an externals file, as for `externals::load`.
+    externals: String,
+    open: HashMap<PathBuf, Vec<u8>>,
+    analysis: Option<Analysis>,
+}
+
+impl Workspace {
+    pub fn new(src: Option<&Path>, externals: &str) -> Result<Self> {
+        let files = match src {
+            Some(src) => src_parser::parse_src(src)?
+                .iter()
+                .map(|path| normalize(path))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        Ok(Workspace {
+            files,
+            externals: externals.to_owned(),
+            open: HashMap::new(),
+            analysis: None,
+        })
+    }
+
+    pub fn open(&mut self, path: &Path, content: Vec<u8>) {
+        self.open.insert(normalize(path), content);
+        self.analysis = None;
+    }
+
+    pub fn close(&mut self, path: &Path) {
+        self.open.remove(&normalize(path));
+        self.analysis = None;
+    }
+
+    /// Analyses the workspace if anything changed since the last call.
+    pub fn analysis(&mut self) -> &Analysis {
+        if self.analysis.is_none() {
+            let mut paths = self.files.clone();
+            let mut opened: Vec<&PathBuf> = self
+                .open
+                .keys()
+                .filter(|path| !self.files.contains(path))
+                .collect();
+            opened.sort();
+            paths.extend(opened.into_iter().cloned());
+
+            let files = paths
+                .into_iter()
+                .filter_map(|path| {
+                    let content = match self.open.get(&path) {
+                        Some(content) => content.clone(),
+                        None => match ::std::fs::read(&path) {
+                            Ok(content) => content,
+                            Err(e) => {
+                                eprintln!("Could not read {:?}: {}", path, e);
+                                return None;
+                            }
+                        },
+                    };
+                    Some((path, content))
+                })
+                .collect();
+            self.analysis = Some(Analysis::new(&self.externals, files));
+        }
+
+        self.analysis.as_ref().unwrap()
+    }
+}
+
+/// Everything known about the workspace at one point in time.
+pub struct Analysis {
+    pub sources: SourceMap,
+    pub asts: Vec<AST>,
+    pub resolution: Resolution,
+    /// Syntax, linker and type errors of all files.
+    pub errors: Vec<Error>,
+}
+
+impl Analysis {
+    /// Analyses `files`, which must be given in `.src` order, with the `externals`.
+    pub fn new(externals: &str, files: Vec<(PathBuf, Vec<u8>)>) -> Self {
+        let mut sources = SourceMap::new();
+        let mut asts = Vec::new();
+        let (externals, mut errors) = match externals::load(externals, &mut sources) {
+            Ok(externals) => externals,
+            Err(e) => {
+                eprintln!("Could not read the externals {:?}: {:?}", externals, e);
+                (Vec::new(), Vec::new())
+            }
+        };
+        for (path, content) in files {
+            let file = sources.add(path, content);
+            let (root, parse_errors) = cst::parse(&sources.get(file).unwrap().content, file);
+            asts.push(cst::lower(&root, file));
+            errors.extend(parse_errors);
+        }
+
+        let mut resolution = resolve_files(&externals, &asts, |_| true);
+        errors.append(&mut resolution.errors);
+        errors.extend(inheritance::check(&asts, &resolution));
+        errors.extend(check(&asts, &resolution));
+        let evaluator = Evaluator::new(&asts, &resolution);
+        errors.extend(array_checker::check(&asts, &resolution, &evaluator));
+
+        Analysis {
+            sources,
+            asts,
+            resolution,
+            errors,
+        }
+    }
+
+    pub fn file_id(&self, path: &Path) -> Option<FileId> {
+        let path = normalize(path);
+        self.sources
+            .files()
+            .iter()
+            .find(|file| file.path == path)
+            .map(|file| file.id)
+    }
+
+    /// The name at `offset` and the symbol it declares or refers to.
+    pub fn symbol_at(&self, file: FileId, offset: usize) -> Option<(Span, SymbolId)> {
+        let content = &self.sources.get(file)?.content;
+        let token = cst::tokenize(content).into_iter().find(|token| {
+            token.kind == TokenKind::Word
+                && token.offset <= offset
+                && offset <= token.offset + token.text.len()
+        })?;
+        let span = token.span(file);
+
+        let symbols = &self.resolution.symbols;
+        let symbol = self.resolution.symbol_at(span).or_else(|| {
+            symbols
+                .iter()
+                .find(|(_, symbol)| symbol.name.span.same_location(&span))
+                .map(|(id, _)| id)
+        })?;
+        Some((span, symbol))
+    }
+
+    /// The declaration of `symbol` as it would be written in a script, without its body.
+    pub fn signature(&self, id: SymbolId) -> String {
+        let symbols = &self.resolution.symbols;
+        let symbol = symbols.get(id);
+        let name = decode(symbol.name.as_bytes());
+        let typ = symbol
+            .typ
+            .as_ref()
+            .map(|typ| decode(typ.as_bytes()))
+            .unwrap_or_default();
+        let array_size = match symbol.array_size {
+            Some(ArraySizeDeclaration::Size(size, _)) => format!("[{}]", size),
+            Some(ArraySizeDeclaration::Identifier(ref size)) => {
+                format!("[{}]", decode(size.as_bytes()))
+            }
+            None => String::new(),
+        };
+
+        match symbol.kind {
+            SymbolKind::Var | SymbolKind::Param => format!("var {} {}{}", typ, name, array_size),
+            SymbolKind::Const => {
+                let source = self
+                    .sources
+                    .get(symbol.span.file)
+                    .map(|file| decode(file.slice(symbol.span)))
+                    .unwrap_or_default();
+                source.split_whitespace().collect::<Vec<_>>().join(" ")
+            }
+            SymbolKind::ConstArray => format!("const {} {}{}", typ, name, array_size),
+            SymbolKind::Func => {
+                let params: Vec<String> = symbols
+                    .children(id)
+                    .iter()
+                    .filter(|param| symbols.get(**param).kind == SymbolKind::Param)
+                    .map(|param| self.signature(*param))
+                    .collect();
+                format!("func {} {}({})", typ, name, params.join(", "))
+            }
+            SymbolKind::Class => format!("class {}", name),
+            SymbolKind::Prototype => format!("prototype {}({})", name, typ),
+            SymbolKind::Instance => format!("instance {}({})", name, typ),
+        }
+    }
+}
+
+/// Makes `path` absolute and removes `.` and `..`, so paths from the `.src` and from the editor
+/// can be compared.
+pub fn normalize(path: &Path) -> PathBuf {
+    let path = match ::std::env::current_dir() {
+        Ok(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_owned(),
+    };
+
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+    normalized
+}
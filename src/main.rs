@@ -6,20 +6,38 @@ extern crate time;
 extern crate parsiphae;
 mod processor;
 
-use clap::{App, Arg};
+use clap::{App, Arg, ArgMatches};
 use time::PreciseTime;
 
-use parsiphae::{errors, types};
+use parsiphae::cache::Cache;
+use parsiphae::diagnostics::MessageFormat;
+use parsiphae::errors;
+use parsiphae::lint;
+use parsiphae::ppa::call_graph::GraphFormat;
+use parsiphae::ppa::layout::Target;
+use parsiphae::types::Charset;
 
 fn main() {
     let start_time = PreciseTime::now();
 
-    let mut exitcode = 0;
-    if let Err(ref e) = run() {
-        match e {
-            errors::Error::ParsingError{ .. } => {
-                exitcode = 2;
-            }
+    let arguments = arguments();
+    let format: MessageFormat = arguments
+        .value_of("MESSAGE_FORMAT")
+        .unwrap()
+        .parse()
+        .unwrap();
+    let charset: Charset = arguments.value_of("ENCODING").unwrap().parse().unwrap();
+    parsiphae::types::set_charset(charset);
+
+    let exitcode = match run(&arguments, format) {
+        Ok(code) => code,
+        Err(ref e) => match e {
+            errors::Error::ParsingError { .. }
+            | errors::Error::SrcError(_)
+            | errors::Error::LinkingError(_)
+            | errors::Error::TypeCheckError(_)
+            | errors::Error::ConstError(_)
+            | errors::Error::CodegenError(_) => 2,
             _ => {
                 use std::io::Write;
                 let stderr = &mut ::std::io::stderr();
@@ -29,17 +47,19 @@ fn main() {
 
                 ::std::process::exit(1);
             }
-        }
-    }
+        },
+    };
 
-    let ms = start_time.to(PreciseTime::now()).num_milliseconds() as f64;
-    println!("parsing took {} seconds", ms / 1000.0);
+    if format == MessageFormat::Human {
+        let ms = start_time.to(PreciseTime::now()).num_milliseconds() as f64;
+        println!("parsing took {} seconds", ms / 1000.0);
+    }
 
     ::std::process::exit(exitcode);
 }
 
-fn run() -> errors::Result<()> {
-    let arguments = App::new("Parsiphae (nom)")
+fn arguments() -> ArgMatches<'static> {
+    App::new("Parsiphae (nom)")
         .version("0.2")
         .author("Leon von Mulert <leonvonmulert@gmail.com")
         .about("An experimental Daedalus parser using nom")
@@ -59,18 +79,238 @@ fn run() -> errors::Result<()> {
                 .value_name("FILE")
                 .required_unless("SRC"),
         )
-        .get_matches();
+        .arg(
+            Arg::with_name("OUTPUT")
+                .help("Compiles the scripts of the src and writes them to this .dat-file")
+                .short("o")
+                .long("output")
+                .value_name("FILE")
+                .requires("SRC"),
+        )
+        .arg(
+            Arg::with_name("LAYOUT")
+                .help("Writes the memory layout of all classes of the src to this file")
+                .long("layout")
+                .value_name("FILE")
+                .requires("SRC"),
+        )
+        .arg(
+            Arg::with_name("TARGET")
+                .help("The engine to compile for, which decides where class members are stored")
+                .long("target")
+                .value_name("ENGINE")
+                .possible_values(Target::VALUES)
+                .default_value("g2"),
+        )
+        .arg(
+            Arg::with_name("EXTERNALS")
+                .help(
+                    "Declares the engine functions from this file, or from the bundled g1, g2 or \
+                     g2notr externals [default: g1 for Gothic 1, g2notr otherwise]",
+                )
+                .long("externals")
+                .value_name("FILE")
+                .requires("SRC"),
+        )
+        .arg(
+            Arg::with_name("DEAD_CODE")
+                .help("Warns about functions, constants and instances that are never used")
+                .long("dead-code")
+                .requires("SRC"),
+        )
+        .arg(
+            Arg::with_name("LINT")
+                .help("Runs the lints, which find likely mistakes and style problems")
+                .long("lint")
+                .requires("SRC")
+                .conflicts_with("DEAD_CODE"),
+        )
+        .arg(
+            Arg::with_name("LINT_CONFIG")
+                .help("Enables, disables or changes the severity of lints as this JSON file says")
+                .long("lint-config")
+                .value_name("FILE")
+                .requires("LINT"),
+        )
+        .arg(
+            Arg::with_name("CALL_GRAPH")
+                .help("Writes which functions, instances and prototypes call which functions to this file")
+                .long("call-graph")
+                .value_name("FILE")
+                .requires("SRC"),
+        )
+        .arg(
+            Arg::with_name("CALL_GRAPH_FORMAT")
+                .help("Writes the call graph as Graphviz DOT or as JSON")
+                .long("call-graph-format")
+                .value_name("FORMAT")
+                .possible_values(GraphFormat::VALUES)
+                .default_value("dot"),
+        )
+        .arg(
+            Arg::with_name("CALLERS_OF")
+                .help("Only puts this function and everything calling it into the call graph")
+                .long("callers-of")
+                .value_name("NAME")
+                .requires("CALL_GRAPH"),
+        )
+        .arg(
+            Arg::with_name("FORMAT")
+                .help("Rewrites the input file or all files of the src in canonical formatting")
+                .long("format")
+                .conflicts_with_all(&[
+                    "CHECK_FORMAT",
+                    "OUTPUT",
+                    "LAYOUT",
+                    "CALL_GRAPH",
+                    "DEAD_CODE",
+                    "LINT",
+                ]),
+        )
+        .arg(
+            Arg::with_name("CHECK_FORMAT")
+                .help("Lists the files that --format would change and fails if there are any")
+                .long("check-format")
+                .conflicts_with_all(&["OUTPUT", "LAYOUT", "CALL_GRAPH", "DEAD_CODE", "LINT"]),
+        )
+        .arg(
+            Arg::with_name("MESSAGE_FORMAT")
+                .help("Prints errors as text, as one JSON object per line or as a SARIF log")
+                .long("message-format")
+                .value_name("FORMAT")
+                .possible_values(MessageFormat::VALUES)
+                .default_value("human"),
+        )
+        .arg(
+            Arg::with_name("ENCODING")
+                .help("The charset the scripts are written in")
+                .long("encoding")
+                .value_name("CHARSET")
+                .possible_values(Charset::VALUES)
+                .default_value("cp1252"),
+        )
+        .arg(
+            Arg::with_name("JOBS")
+                .help("Parses the files of the src on this many threads [default: number of CPUs]")
+                .short("j")
+                .long("jobs")
+                .value_name("N")
+                .validator(|jobs| match jobs.parse::<usize>() {
+                    Ok(jobs) if jobs > 0 => Ok(()),
+                    _ => Err("must be a positive number".to_owned()),
+                }),
+        )
+        .arg(
+            Arg::with_name("CACHE_DIR")
+                .help("Caches results in this directory, so the next run only checks what changed")
+                .long("cache-dir")
+                .value_name("DIR")
+                .requires("SRC"),
+        )
+        .arg(
+            Arg::with_name("WATCH")
+                .help("Checks the src again whenever it or one of its scripts changes")
+                .long("watch")
+                .requires("SRC")
+                .conflicts_with_all(&["FORMAT", "CHECK_FORMAT"]),
+        )
+        .arg(
+            Arg::with_name("CLEAR_CACHE")
+                .help("Empties the cache before checking the src")
+                .long("clear-cache")
+                .requires("SRC"),
+        )
+        .get_matches()
+}
+
+/// Returns the exit code for runs without errors.
+fn run(arguments: &ArgMatches, format: MessageFormat) -> errors::Result<i32> {
+    let check = arguments.is_present("CHECK_FORMAT");
+    if check || arguments.is_present("FORMAT") {
+        let paths = match arguments.value_of("INPUT") {
+            Some(path) => vec![path.into()],
+            None => parsiphae::src_parser::parse_src(arguments.value_of("SRC").unwrap())?,
+        };
+        let formatted = processor::format_files(&paths, check, format)?;
+        return Ok(if check && !formatted { 1 } else { 0 });
+    }
 
     let d_path = arguments.value_of("INPUT");
     match d_path {
         Some(path) => {
-            processor::process_single_file(path)?;
+            processor::process_single_file(path, format)?;
         }
         None => {
             let path = arguments.value_of("SRC").unwrap();
-            processor::process_src(path)?;
+            let jobs = match arguments.value_of("JOBS") {
+                Some(jobs) => jobs.parse().unwrap(),
+                None => parsiphae::parallel::default_jobs(),
+            };
+            let watch = arguments.is_present("WATCH");
+            // Watching always uses a cache, by default one shared by all projects.
+            let cache_dir = match arguments.value_of("CACHE_DIR") {
+                Some(dir) => Some(dir.into()),
+                None if watch => Some(::std::env::temp_dir().join("parsiphae-cache")),
+                None => None,
+            };
+            let cache = match cache_dir {
+                Some(dir) => {
+                    let cache = Cache::open(dir)?;
+                    if arguments.is_present("CLEAR_CACHE") {
+                        cache.clear()?;
+                    }
+                    Some(cache)
+                }
+                None => None,
+            };
+
+            let lint_config = match arguments.value_of("LINT_CONFIG") {
+                Some(path) => {
+                    let content = ::std::fs::read(path)?;
+                    let config = lint::Config::from_json(&content, &lint::Registry::builtin())
+                        .map_err(|e| {
+                            ::std::io::Error::new(
+                                ::std::io::ErrorKind::InvalidData,
+                                format!("invalid lint config {}: {}", path, e),
+                            )
+                        })?;
+                    Some(config)
+                }
+                None if arguments.is_present("LINT") => Some(lint::Config::default()),
+                None => None,
+            };
+            let target: Target = arguments.value_of("TARGET").unwrap().parse().unwrap();
+            let options = processor::Options {
+                dat: arguments.value_of("OUTPUT"),
+                layout: arguments.value_of("LAYOUT"),
+                target,
+                externals: arguments.value_of("EXTERNALS").unwrap_or(match target {
+                    Target::Gothic1 => "g1",
+                    Target::Gothic2 => "g2notr",
+                }),
+                call_graph: arguments.value_of("CALL_GRAPH"),
+                call_graph_format: arguments
+                    .value_of("CALL_GRAPH_FORMAT")
+                    .unwrap()
+                    .parse()
+                    .unwrap(),
+                callers_of: arguments.value_of("CALLERS_OF"),
+                dead_code: arguments.is_present("DEAD_CODE"),
+                lint: lint_config.as_ref(),
+            };
+
+            match cache {
+                Some(ref cache) if watch => {
+                    processor::watch_src(path, &options, format, jobs, cache)
+                }
+                _ => {
+                    if !processor::process_src(path, &options, format, jobs, cache.as_ref())? {
+                        return Ok(1);
+                    }
+                }
+            }
         }
     }
 
-    Ok(())
+    Ok(0)
 }
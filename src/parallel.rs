@@ -0,0 +1,86 @@
+//! A minimal thread pool for work that is independent per file, like parsing.
+
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Stack size of the worker threads. The parsers recurse deeply on nested expressions,
+/// so this matches the main thread rather than the smaller default for spawned threads.
+const STACK_SIZE: usize = 8 * 1024 * 1024;
+
+/// The number of threads to use if the user did not choose one.
+pub fn default_jobs() -> usize {
+    thread::available_parallelism()
+        .map(|jobs| jobs.get())
+        .unwrap_or(1)
+}
+
+/// Applies `f` to every item on up to `jobs` threads. The results are in the order of `items`,
+/// no matter which thread finished first.
+pub fn map_ordered<T, R, F>(items: &[T], jobs: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let jobs = jobs.clamp(1, items.len().max(1));
+    if jobs == 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let work = || {
+        let mut done = Vec::new();
+        loop {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            match items.get(i) {
+                Some(item) => done.push((i, f(item))),
+                None => return done,
+            }
+        }
+    };
+
+    let done: Vec<(usize, R)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs)
+            .map(|_| {
+                thread::Builder::new()
+                    .stack_size(STACK_SIZE)
+                    .spawn_scoped(scope, work)
+                    .expect("failed to spawn a worker thread")
+            })
+            .collect();
+        workers
+            .into_iter()
+            .flat_map(|worker| worker.join().unwrap_or_else(|e| panic::resume_unwind(e)))
+            .collect()
+    });
+
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+    for (i, result) in done {
+        results[i] = Some(result);
+    }
+    results
+        .into_iter()
+        .map(|result| result.expect("every item is processed"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_order() {
+        let items: Vec<u64> = (0..100).collect();
+
+        for jobs in &[1, 3, 200] {
+            let squares = map_ordered(&items, *jobs, |i| {
+                // Finish out of order.
+                thread::sleep(::std::time::Duration::from_micros((100 - i) * 10));
+                i * i
+            });
+            assert_eq!(squares, items.iter().map(|i| i * i).collect::<Vec<_>>());
+        }
+        assert!(map_ordered(&[] as &[u64], 4, |i| *i).is_empty());
+    }
+}
@@ -1,4 +1,5 @@
 use inner_errors::ParserError;
+use parsers::position::spanned;
 use types::*;
 
 fn is_not_quote(input: u8) -> bool {
@@ -6,10 +7,10 @@ fn is_not_quote(input: u8) -> bool {
 }
 
 fn convert_string_literal(input: Input) -> StringLiteral {
-    StringLiteral::new(&input.0)
+    StringLiteral::new(input.0)
 }
 
-named!(pub string_parser<Input, StringLiteral, ParserError>, fix_error!(ParserError, map!(
+named!(string_content<Input, StringLiteral, ParserError>, fix_error!(ParserError, map!(
     delimited!(
         tag!("\""),
         take_while!(is_not_quote),
@@ -18,6 +19,11 @@ named!(pub string_parser<Input, StringLiteral, ParserError>, fix_error!(ParserEr
     convert_string_literal
 )));
 
+named!(pub string_parser<Input, StringLiteral, ParserError>, map!(
+    call!(spanned, string_content),
+    |(literal, span): (StringLiteral, Span)| literal.with_span(span)
+));
+
 named!(pub number_parser<Input, i64, ParserError>, fix_error!(ParserError, flat_map!(
     recognize!(
         tuple!(
@@ -86,5 +92,4 @@ mod tests {
 
         test_parser_error(float_parser, b"xxx", failure_result(b"xxx", ErrorKind::IsA));
     }
-
 }
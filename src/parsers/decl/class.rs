@@ -1,14 +1,17 @@
 use inner_errors::ParserError;
 use nom::ErrorKind;
+use parsers::position::{position, span};
 use parsers::util::flatten_vec;
 use parsers::{identifier_parser, replacements::*, var_decl_list};
-use types::{Class, Input};
+use types::{Class, Input, Span};
 
 named!(pub class<Input, Class, ParserError>, do_parse!(
+    start: position >>
     tag_no_case_e!("class") >>
     multispace0 >>
     cl: return_error!(ErrorKind::Custom(ParserError::ClassDeclaration), class_real) >>
-    (cl)
+    end: position >>
+    (Class { span: span(start, end), ..cl })
 ));
 
 named!(class_real<Input, Class, ParserError>, fix_error!(ParserError, gws!(do_parse!(
@@ -20,14 +23,15 @@ named!(class_real<Input, Class, ParserError>, fix_error!(ParserError, gws!(do_pa
     char_e!('}') >>
     (Class {
         name,
-        members: flatten_vec(members)
+        members: flatten_vec(members),
+        span: Span::default(),
     })
 ))));
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use types::{Identifier, VarDeclaration};
+    use types::{Identifier, Span, VarDeclaration};
 
     #[test]
     fn simple() {
@@ -39,6 +43,7 @@ mod tests {
                 Identifier::new(b"bar"),
                 None,
             )],
+            span: Span::default(),
         };
 
         let actual = class(input).unwrap().1;
@@ -51,6 +56,7 @@ mod tests {
         let expected = Class {
             name: Identifier::new(b"foo"),
             members: Vec::new(),
+            span: Span::default(),
         };
 
         let actual = class(input).unwrap().1;
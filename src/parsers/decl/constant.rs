@@ -1,9 +1,11 @@
 use inner_errors::ParserError;
+use parsers::position::{position, span, spanned};
 use parsers::replacements::*;
 use parsers::{array_size_decl, expression, identifier_parser};
-use types::{ConstArrayDeclaration, ConstArrayInitializer, ConstDeclaration, Input};
+use types::{ConstArrayDeclaration, ConstArrayInitializer, ConstDeclaration, Expression, Input};
 
 named!(pub const_decl<Input, ConstDeclaration, ParserError>, fix_error!(ParserError, do_parse!(
+    start: position >>
     tag_no_case_e!("const") >>
     multispace1 >>
     typ: identifier_parser >>
@@ -12,11 +14,13 @@ named!(pub const_decl<Input, ConstDeclaration, ParserError>, fix_error!(ParserEr
     multispace0 >>
     char_e!('=') >>
     multispace0 >>
-    initializer: expression >> multispace0 >>
-    (ConstDeclaration::new(typ, name, initializer))
+    initializer: expression >>
+    end: position >> multispace0 >>
+    (ConstDeclaration::new(typ, name, initializer).with_span(span(start, end)))
 )));
 
 named!(pub const_array_decl<Input, ConstArrayDeclaration, ParserError>, fix_error!(ParserError, do_parse!(
+    start: position >>
     tag_no_case_e!("const") >>
     multispace1 >>
     typ: identifier_parser >>
@@ -28,40 +32,47 @@ named!(pub const_array_decl<Input, ConstArrayDeclaration, ParserError>, fix_erro
     char_e!('=') >>
     multispace0 >>
     initializer: const_array_init >>
-    (ConstArrayDeclaration::new(typ, name, array_size, initializer))
+    end: position >>
+    (ConstArrayDeclaration::new(typ, name, array_size, initializer).with_span(span(start, end)))
 )));
 
-named!(pub const_array_init<Input, ConstArrayInitializer, ParserError>, fix_error!(ParserError, map!(gws!(delimited!(
+named!(pub const_array_init<Input, ConstArrayInitializer, ParserError>, fix_error!(ParserError, map!(
+    call!(spanned, const_array_elements),
+    |(expressions, span)| ConstArrayInitializer::new(expressions).with_span(span)
+)));
+
+named!(const_array_elements<Input, Vec<Expression>, ParserError>, fix_error!(ParserError, gws!(delimited!(
        char_e!('{'),
        gws!(separated_nonempty_list!(gws!(char_e!(',')), expression)),
        char_e!('}')
-    )),
-    ConstArrayInitializer::new
-)));
+))));
 
 #[cfg(test)]
 mod const_tests {
     use super::*;
     use tests::utility::*;
-    use types::{Expression, Identifier, UnaryExpression};
+    use types::{Expression, Identifier, Span, UnaryExpression};
 
     #[test]
     pub fn decl_int_foo() {
-        let init = Expression::Int(5);
+        let init = Expression::Int(5, Span::default());
         let decl = ConstDeclaration::new(Identifier::new(b"int"), Identifier::new(b"foo"), init);
         test_parser_done(const_decl, b"const int foo = 5", decl, b"");
     }
 
     #[test]
     pub fn decl_uppercase_const_int_foo() {
-        let init = Expression::Int(14);
+        let init = Expression::Int(14, Span::default());
         let decl = ConstDeclaration::new(Identifier::new(b"int"), Identifier::new(b"foo"), init);
         test_parser_done(const_decl, b"CONST int foo= 14", decl, b"");
     }
 
     #[test]
     pub fn decl_zcvob_foo_unary() {
-        let init = Expression::Unary(Box::new(UnaryExpression::new(b'!', Expression::Int(5))));
+        let init = Expression::Unary(Box::new(UnaryExpression::new(
+            b'!',
+            Expression::Int(5, Span::default()),
+        )));
         let decl = ConstDeclaration::new(Identifier::new(b"zCVob"), Identifier::new(b"foo"), init);
         test_parser_done(const_decl, b"CONST zCVob foo = !5", decl, b"");
     }
@@ -76,11 +87,14 @@ mod const_array_tests {
     #[test]
     pub fn decl_int_index() {
         let init = vec![
-            Expression::Int(5),
-            Expression::Int(6),
-            Expression::Unary(Box::new(UnaryExpression::new(b'+', Expression::Int(12)))),
+            Expression::Int(5, Span::default()),
+            Expression::Int(6, Span::default()),
+            Expression::Unary(Box::new(UnaryExpression::new(
+                b'+',
+                Expression::Int(12, Span::default()),
+            ))),
         ];
-        let array_size = ArraySizeDeclaration::Size(3);
+        let array_size = ArraySizeDeclaration::Size(3, Span::default());
         let decl = ConstArrayDeclaration::new(
             Identifier::new(b"int"),
             Identifier::new(b"foo"),
@@ -98,9 +112,12 @@ mod const_array_tests {
     #[test]
     pub fn decl_identifier_index() {
         let init = vec![
-            Expression::Int(5),
-            Expression::Int(6),
-            Expression::Unary(Box::new(UnaryExpression::new(b'+', Expression::Int(12)))),
+            Expression::Int(5, Span::default()),
+            Expression::Int(6, Span::default()),
+            Expression::Unary(Box::new(UnaryExpression::new(
+                b'+',
+                Expression::Int(12, Span::default()),
+            ))),
         ];
         let array_size = ArraySizeDeclaration::Identifier(Identifier::new(b"MAX_SIZE"));
         let decl = ConstArrayDeclaration::new(
@@ -121,8 +138,11 @@ mod const_array_tests {
     pub fn decl_string_initializer() {
         let init = vec![
             Expression::String(StringLiteral::new(b"hello")),
-            Expression::Float(6.0),
-            Expression::Unary(Box::new(UnaryExpression::new(b'+', Expression::Int(12)))),
+            Expression::Float(6.0, Span::default()),
+            Expression::Unary(Box::new(UnaryExpression::new(
+                b'+',
+                Expression::Int(12, Span::default()),
+            ))),
         ];
         let array_size = ArraySizeDeclaration::Identifier(Identifier::new(b"MAX_SIZE"));
         let decl = ConstArrayDeclaration::new(
@@ -1,22 +1,39 @@
 use inner_errors::ParserError;
+use parsers::position::{position, span};
 use parsers::{identifier_parser, replacements::*, statement_block, var_decl_list_0};
 use types::{Function, Input};
 
 named!(pub func<Input, Function, ParserError>, fix_error!(ParserError, do_parse!(
+    start: position >>
     tag_no_case_e!("func") >>   multispace1 >>
     typ: identifier_parser >>   multispace1 >>
     name: identifier_parser >>  multispace0 >>
     char_e!('(') >>             multispace0 >>
     params: var_decl_list_0 >>  multispace0 >>
     char_e!(')') >>             multispace0 >>
-    body: statement_block >>    multispace0 >>
-    (Function {typ, name, params, body})
+    body: statement_block >>    end: position >> multispace0 >>
+    (Function {typ, name, params, body, span: span(start, end)})
+)));
+
+// A function implemented by the engine, as declared in externals files: like `func`, but the
+// body may be left out, and is ignored otherwise. Includes the terminating semicolon.
+named!(pub external<Input, Function, ParserError>, fix_error!(ParserError, do_parse!(
+    start: position >>
+    tag_no_case_e!("func") >>   multispace1 >>
+    typ: identifier_parser >>   multispace1 >>
+    name: identifier_parser >>  multispace0 >>
+    char_e!('(') >>             multispace0 >>
+    params: var_decl_list_0 >>  multispace0 >>
+    char_e!(')') >>             end: position >> multispace0 >>
+    opt!(terminated!(statement_block, multispace0)) >>
+    char_e!(';') >>
+    (Function {typ, name, params, body: Vec::new(), span: span(start, end)})
 )));
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use types::{Expression, Identifier, Statement, VarDeclaration};
+    use types::{Expression, Identifier, Span, Statement, VarDeclaration};
 
     #[test]
     fn empty_void() {
@@ -26,6 +43,7 @@ mod tests {
             name: Identifier::new(b"foo"),
             params: Vec::new(),
             body: Vec::new(),
+            span: Span::default(),
         };
 
         let actual = func(input).unwrap().1;
@@ -45,6 +63,7 @@ mod tests {
                 None,
             )],
             body: Vec::new(),
+            span: Span::default(),
         };
 
         let actual = func(input).unwrap().1;
@@ -59,7 +78,8 @@ mod tests {
             typ: Identifier::new(b"void"),
             name: Identifier::new(b"foo"),
             params: Vec::new(),
-            body: vec![Statement::Exp(Expression::Int(3))],
+            body: vec![Statement::Exp(Expression::Int(3, Span::default()))],
+            span: Span::default(),
         };
 
         let actual = func(input).unwrap().1;
@@ -67,4 +87,27 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn externals() {
+        let expected = Function {
+            typ: Identifier::new(b"int"),
+            name: Identifier::new(b"Hlp_Random"),
+            params: vec![VarDeclaration::new(
+                Identifier::new(b"int"),
+                Identifier::new(b"bound"),
+                None,
+            )],
+            body: Vec::new(),
+            span: Span::default(),
+        };
+
+        for input in &[
+            &b"func int Hlp_Random(var int bound);"[..],
+            b"func int Hlp_Random(var int bound) {};",
+            b"FUNC int Hlp_Random (var int bound) { return 0; } ;",
+        ] {
+            assert_eq!(external(Input(input)).unwrap().1, expected);
+        }
+        assert!(external(Input(b"func int Hlp_Random(var int bound)")).is_err());
+    }
 }
@@ -1,25 +1,30 @@
 use inner_errors::ParserError;
+use parsers::position::{position, span};
 use parsers::{identifier_list, identifier_parser, statement_block};
 use types::{Input, Instance};
 
 //
 named!(instance_init<Input, Instance, ParserError>, fix_error!(ParserError, gws!(do_parse!(
+    start: position >>
     tag_no_case_e!("instance") >>
     name: identifier_parser >>
     char_e!('(') >>
     class: identifier_parser >>
     char_e!(')') >>
     body: statement_block >>
-    (Instance {name, class, body})
+    end: position >>
+    (Instance {name, class, body, span: span(start, end)})
 ))));
 
 named!(instance_list<Input, Vec<Instance>, ParserError>, fix_error!(ParserError, gws!(do_parse!(
+    start: position >>
     tag_no_case_e!("instance") >>
     names: identifier_list >>
     char_e!('(') >>
     class: identifier_parser >>
     char_e!(')') >>
-    (names.into_iter().map(|name|Instance { name, class: class.clone(), body: Vec::new()}).collect())
+    end: position >>
+    (names.into_iter().map(|name|Instance { name, class: class.clone(), body: Vec::new(), span: span(start, end)}).collect())
 ))));
 
 named!(pub instance<Input, Vec<Instance>, ParserError>,
@@ -32,7 +37,9 @@ named!(pub instance<Input, Vec<Instance>, ParserError>,
 #[cfg(test)]
 mod tests {
     use super::*;
-    use types::{Assignment, AssignmentOperator, Expression, Identifier, Statement, VarAccess};
+    use types::{
+        Assignment, AssignmentOperator, Expression, Identifier, Span, Statement, VarAccess,
+    };
 
     #[test]
     fn simple() {
@@ -41,6 +48,7 @@ mod tests {
             name: Identifier::new(b"foo"),
             class: Identifier::new(b"bar"),
             body: Vec::new(),
+            span: Span::default(),
         }];
 
         let actual = instance(input).unwrap().1;
@@ -57,8 +65,10 @@ mod tests {
             body: vec![Statement::Ass(Assignment {
                 var: VarAccess::new(Identifier::new(b"name"), None, None),
                 op: AssignmentOperator::Eq,
-                exp: Expression::Int(3),
+                exp: Expression::Int(3, Span::default()),
+                span: Span::default(),
             })],
+            span: Span::default(),
         }];
 
         let actual = instance(input).unwrap().1;
@@ -73,6 +83,7 @@ mod tests {
             name: Identifier::new(b"foo"),
             class: Identifier::new(b"bar"),
             body: Vec::new(),
+            span: Span::default(),
         }];
 
         let actual = instance(input).unwrap().1;
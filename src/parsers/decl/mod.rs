@@ -9,7 +9,7 @@ mod var_decl;
 pub use self::class::class;
 pub use self::constant::{const_array_decl, const_decl};
 pub use self::declaration::declaration;
-pub use self::func::func;
+pub use self::func::{external, func};
 pub use self::instance::instance;
 pub use self::prototype::prototype;
 pub use self::var_decl::{array_size_decl, var_decl, var_decl_list, var_decl_list_0};
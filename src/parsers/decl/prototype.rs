@@ -1,8 +1,10 @@
 use inner_errors::ParserError;
+use parsers::position::{position, span};
 use parsers::{identifier_parser, statement};
 use types::{Input, Prototype};
 
 named!(pub prototype<Input, Prototype, ParserError>, fix_error!(ParserError, gws!(do_parse!(
+    start: position >>
     tag_no_case_e!("prototype") >>
     name: identifier_parser >>
     char_e!('(') >>
@@ -11,13 +13,16 @@ named!(pub prototype<Input, Prototype, ParserError>, fix_error!(ParserError, gws
     char_e!('{') >>
     body: many0!(statement) >>
     char_e!('}') >>
-    (Prototype {name, class, body})
+    end: position >>
+    (Prototype {name, class, body, span: span(start, end)})
 ))));
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use types::{Assignment, AssignmentOperator, Expression, Identifier, Statement, VarAccess};
+    use types::{
+        Assignment, AssignmentOperator, Expression, Identifier, Span, Statement, VarAccess,
+    };
 
     #[test]
     fn simple() {
@@ -26,6 +31,7 @@ mod tests {
             name: Identifier::new(b"foo"),
             class: Identifier::new(b"bar"),
             body: Vec::new(),
+            span: Span::default(),
         };
 
         let actual = prototype(input).unwrap().1;
@@ -42,8 +48,10 @@ mod tests {
             body: vec![Statement::Ass(Assignment {
                 var: VarAccess::new(Identifier::new(b"name"), None, None),
                 op: AssignmentOperator::Eq,
-                exp: Expression::Int(3),
+                exp: Expression::Int(3, Span::default()),
+                span: Span::default(),
             })],
+            span: Span::default(),
         };
 
         let actual = prototype(input).unwrap().1;
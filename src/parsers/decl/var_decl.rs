@@ -1,26 +1,42 @@
 use inner_errors::ParserError;
 use nom::ErrorKind;
+use parsers::position::{position, span, spanned};
 use parsers::replacements::*;
 use parsers::{identifier_parser, number_parser};
-use types::{ArraySizeDeclaration, Identifier, Input, VarDeclaration};
+use types::{ArraySizeDeclaration, Identifier, Input, PResult, Span, VarDeclaration};
 
 named!(pub var_decl<Input, Vec<VarDeclaration>, ParserError>, do_parse!(
+    start: position >>
     tag_no_case_e!("var") >> multispace1 >>
-    decls: return_error!(ErrorKind::Custom(ParserError::VariableDeclaration), var_decl_real) >>
+    decls: return_error!(ErrorKind::Custom(ParserError::VariableDeclaration), call!(var_decl_real, start)) >>
     (decls)
 ));
 
-named!(var_decl_real<Input, Vec<VarDeclaration>, ParserError>, fix_error!(ParserError, do_parse!(
-    typ: identifier_parser >>
-    multispace1 >>
-    identifier_decls: separated_nonempty_list!(
-        gws!(char_e!(',')),
-        var_identifier_decl
-    ) >>
-    (identifier_decls.into_iter().map(|(name, array)|VarDeclaration::new(typ.clone(), name, array)).collect())
+// The first variable of `var int a, b` spans `var int a`, every further one only its own name and array size.
+fn var_decl_real<'a>(input: Input<'a>, start: Input<'a>) -> PResult<'a, Vec<VarDeclaration>> {
+    fix_error!(
+        input,
+        ParserError,
+        do_parse!(
+            typ: identifier_parser >>
+            multispace1 >>
+            identifier_decls: separated_nonempty_list!(
+                gws!(char_e!(',')),
+                var_identifier_decl
+            ) >>
+            (identifier_decls.into_iter().enumerate().map(|(i, ((name, array), decl_span))| {
+                let decl_span = if i == 0 { span(start, input).to(decl_span) } else { decl_span };
+                VarDeclaration::new(typ.clone(), name, array).with_span(decl_span)
+            }).collect())
+        )
+    )
+}
+
+named!(var_identifier_decl<Input, ((Identifier, Option<ArraySizeDeclaration>), Span), ParserError>, fix_error!(ParserError, gws!(
+    call!(spanned, var_identifier_decl_inner)
 )));
 
-named!(var_identifier_decl<Input, (Identifier, Option<ArraySizeDeclaration>), ParserError>, fix_error!(ParserError, gws!(
+named!(var_identifier_decl_inner<Input, (Identifier, Option<ArraySizeDeclaration>), ParserError>, fix_error!(ParserError, gws!(
     pair!(
         identifier_parser,
         opt!(array_size_decl)
@@ -47,7 +63,7 @@ named!(pub array_size_decl<Input, ArraySizeDeclaration, ParserError>, fix_error!
     char_e!('[') >> multispace0 >>
     size: alt!(
           map!(identifier_parser, ArraySizeDeclaration::Identifier)
-        | map!(number_parser, ArraySizeDeclaration::Size)
+        | map!(call!(spanned, number_parser), |(size, span): (i64, Span)| ArraySizeDeclaration::Size(size, span))
     ) >> multispace0 >>
     char_e!(']') >> multispace0 >>
     (size)
@@ -78,7 +94,7 @@ mod tests {
             VarDeclaration::new(
                 Identifier::new(b"int"),
                 Identifier::new(b"foo"),
-                Some(ArraySizeDeclaration::Size(3)),
+                Some(ArraySizeDeclaration::Size(3, Span::default())),
             ),
             VarDeclaration::new(Identifier::new(b"zCVob"), Identifier::new(b"bar"), None),
         ];
@@ -96,7 +112,7 @@ mod tests {
             VarDeclaration::new(
                 Identifier::new(b"int"),
                 Identifier::new(b"foo"),
-                Some(ArraySizeDeclaration::Size(3)),
+                Some(ArraySizeDeclaration::Size(3, Span::default())),
             ),
             VarDeclaration::new(Identifier::new(b"zCVob"), Identifier::new(b"bar"), None),
         ];
@@ -113,7 +129,7 @@ mod tests {
             VarDeclaration::new(
                 Identifier::new(b"int"),
                 Identifier::new(b"foo"),
-                Some(ArraySizeDeclaration::Size(3)),
+                Some(ArraySizeDeclaration::Size(3, Span::default())),
             ),
             VarDeclaration::new(
                 Identifier::new(b"zCVob"),
@@ -144,7 +160,7 @@ mod tests {
     #[test]
     fn int_array_size() {
         let input = Input(b"[13]");
-        let expected = ArraySizeDeclaration::Size(13);
+        let expected = ArraySizeDeclaration::Size(13, Span::default());
 
         let actual = array_size_decl(input).unwrap().1;
 
@@ -160,5 +176,4 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
-
 }
@@ -24,9 +24,9 @@ pub fn Bit(input: Input) -> PResult<Expression> {
 }
 
 pub fn left_associative_binary<'a>(
-    ops: fn(Input<'a>) -> PResult<Input<'a>>,
-    next_level: fn(Input<'a>) -> PResult<Expression>,
-) -> impl Fn(Input<'a>) -> PResult<Expression> {
+    ops: fn(Input<'a>) -> PResult<'a, Input<'a>>,
+    next_level: fn(Input<'a>) -> PResult<'a, Expression>,
+) -> impl Fn(Input<'a>) -> PResult<'a, Expression> {
     let parser = move |input: Input<'a>| {
         fix_error!(
             input,
@@ -39,7 +39,7 @@ pub fn left_associative_binary<'a>(
                             first,
                             |acc, (op, exp): (Input, Expression)| {
                                 Expression::Binary(Box::new(BinaryExpression::new(
-                                    BinaryOperator::from(&op.0),
+                                    BinaryOperator::from(op.0),
                                     acc,
                                     exp,
                                 )))
@@ -56,7 +56,7 @@ pub fn left_associative_binary<'a>(
 mod tests {
     use super::*;
     use tests::utility::*;
-    use types::UnaryExpression;
+    use types::{Span, UnaryExpression};
 
     #[test]
     fn two_ops() {
@@ -108,8 +108,8 @@ mod tests {
     fn make_binary_exp_int(op: &[u8], left: i64, right: i64) -> Expression {
         Expression::Binary(Box::new(BinaryExpression::new(
             BinaryOperator::from(op),
-            Expression::Int(left),
-            Expression::Int(right),
+            Expression::Int(left, Span::default()),
+            Expression::Int(right, Span::default()),
         )))
     }
 
@@ -136,14 +136,17 @@ mod tests {
     #[test]
     fn three_node_tree() {
         let inner = make_binary_exp_int(b"|", 1, 1);
-        let outer = make_binary_exp(b"&", inner, Expression::Int(2));
+        let outer = make_binary_exp(b"&", inner, Expression::Int(2, Span::default()));
         test_parser_done(Bit, b"1|1&2", outer, b"");
     }
 
     #[test]
     fn tree_with_unary() {
-        let inner = Expression::Unary(Box::new(UnaryExpression::new(b'!', Expression::Int(2))));
-        let exp = make_binary_exp(b">>", Expression::Int(1), inner);
+        let inner = Expression::Unary(Box::new(UnaryExpression::new(
+            b'!',
+            Expression::Int(2, Span::default()),
+        )));
+        let exp = make_binary_exp(b">>", Expression::Int(1, Span::default()), inner);
         test_parser_done(Bit, b"1>>!2", exp, b"");
     }
 }
@@ -1,14 +1,17 @@
 use inner_errors::ParserError;
 use nom::ErrorKind;
+use parsers::position::{position, span};
 use parsers::{expression, identifier_parser, replacements::*};
 use types::{Call, Expression, Input};
 
 named!(pub call_parser<Input, Call, ParserError>, do_parse!(
+    start: position >>
     name: identifier_parser >> multispace0 >>
     char_e!('(') >> multispace0 >>
     params: return_error!(ErrorKind::Custom(ParserError::InvalidCall), call_parser_real) >>
+    end: position >>
 
-    (Call { func: name, params })
+    (Call { func: name, params, span: span(start, end) })
 ));
 
 named!(call_parser_real<Input, Vec<Expression>, ParserError>, fix_error!(ParserError, gws!(do_parse!(
@@ -23,7 +26,7 @@ named!(call_parser_real<Input, Vec<Expression>, ParserError>, fix_error!(ParserE
 #[cfg(test)]
 mod tests {
     use super::*;
-    use types::{Expression, Identifier, StringLiteral};
+    use types::{Expression, Identifier, Span, StringLiteral};
 
     #[test]
     fn no_param() {
@@ -31,6 +34,7 @@ mod tests {
         let expected = Call {
             func: Identifier::new(b"foo"),
             params: Vec::new(),
+            span: Span::default(),
         };
 
         let actual = call_parser(input).unwrap().1;
@@ -43,7 +47,8 @@ mod tests {
         let input = Input(b"foo(3)");
         let expected = Call {
             func: Identifier::new(b"foo"),
-            params: vec![Expression::Int(3)],
+            params: vec![Expression::Int(3, Span::default())],
+            span: Span::default(),
         };
 
         let actual = call_parser(input).unwrap().1;
@@ -57,9 +62,10 @@ mod tests {
         let expected = Call {
             func: Identifier::new(b"foo"),
             params: vec![
-                Expression::Int(3),
+                Expression::Int(3, Span::default()),
                 Expression::String(StringLiteral::new(b"hello")),
             ],
+            span: Span::default(),
         };
 
         let actual = call_parser(input).unwrap().1;
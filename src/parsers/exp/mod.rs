@@ -5,7 +5,7 @@ mod unary;
 mod value;
 mod var_access;
 
-pub use self::binary::{Add, Bit, Boolean, Cmp, Mul};
+pub use self::binary::{Bit, Boolean, Cmp, Mul};
 pub use self::call::call_parser;
 pub use self::expression::expression;
 pub use self::unary::Unary;
@@ -1,12 +1,15 @@
 use inner_errors::ParserError;
+use parsers::position::{position, span};
 use parsers::Value;
 use types::{Expression, Input, UnaryExpression};
 
 named!(pub Unary<Input, Expression, ParserError>, fix_error!(ParserError, alt!(
     gws!(do_parse!(
+        start: position >>
         op: one_of_e!(b"!~-+") >>
         exp: Unary >>
-        (Expression::Unary(Box::new(UnaryExpression::new(op as u8, exp))))
+        end: position >>
+        (Expression::Unary(Box::new(UnaryExpression::new(op as u8, exp).with_span(span(start, end)))))
     ))
 
     | Value
@@ -16,6 +19,7 @@ named!(pub Unary<Input, Expression, ParserError>, fix_error!(ParserError, alt!(
 mod tests {
     use super::*;
     use tests::utility::*;
+    use types::Span;
 
     #[test]
     fn single() {
@@ -29,17 +33,28 @@ mod tests {
 
     #[test]
     pub fn test_unary_parser() {
-        let exp = Expression::Unary(Box::new(UnaryExpression::new(b'-', Expression::Int(1))));
+        let exp = Expression::Unary(Box::new(UnaryExpression::new(
+            b'-',
+            Expression::Int(1, Span::default()),
+        )));
         test_parser_done(Unary, b"-1", exp, b"");
 
-        let exp = Expression::Unary(Box::new(UnaryExpression::new(b'!', Expression::Int(7))));
+        let exp = Expression::Unary(Box::new(UnaryExpression::new(
+            b'!',
+            Expression::Int(7, Span::default()),
+        )));
         test_parser_done(Unary, b"!7", exp, b"");
 
-        let exp = Expression::Unary(Box::new(UnaryExpression::new(b'~', Expression::Int(123))));
+        let exp = Expression::Unary(Box::new(UnaryExpression::new(
+            b'~',
+            Expression::Int(123, Span::default()),
+        )));
         test_parser_done(Unary, b"~ 123", exp, b"");
 
-        let exp_inner =
-            Expression::Unary(Box::new(UnaryExpression::new(b'~', Expression::Int(123))));
+        let exp_inner = Expression::Unary(Box::new(UnaryExpression::new(
+            b'~',
+            Expression::Int(123, Span::default()),
+        )));
         let exp = Expression::Unary(Box::new(UnaryExpression::new(b'!', exp_inner)));
         test_parser_done(Unary, b"! ~ 123", exp, b"");
 
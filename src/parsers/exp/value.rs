@@ -1,13 +1,14 @@
 use inner_errors::ParserError;
 use parsers::exp::expression::parenthesis;
+use parsers::position::spanned;
 use parsers::{call_parser, float_parser, number_parser, var_access};
-use types::{Expression, Input};
+use types::{Expression, Input, Span};
 
 named!(pub Value<Input, Expression, ParserError>, fix_error!(ParserError, alt!(
         map!(call_parser, |call| Expression::Call(Box::new(call)))
        | map!(var_access, |va| Expression::Identifier(Box::new(va)))
-       | map!(float_parser, Expression::Float)
-       | map!(number_parser, Expression::Int)
+       | map!(call!(spanned, float_parser), |(f, span): (f32, Span)| Expression::Float(f, span))
+       | map!(call!(spanned, number_parser), |(i, span): (i64, Span)| Expression::Int(i, span))
        | parenthesis
 )));
 
@@ -21,21 +22,27 @@ mod tests {
 
     #[test]
     fn test_value_parser() {
-        test_parser_done(Value, b"-1", Expression::Int(-1), b"");
-        test_parser_done(Value, b"15", Expression::Int(15), b"");
+        test_parser_done(Value, b"-1", Expression::Int(-1, Span::default()), b"");
+        test_parser_done(Value, b"15", Expression::Int(15, Span::default()), b"");
         test_parser_done(
             Value,
             b"locals()",
             Expression::Call(Box::new(Call {
                 func: Identifier::new(b"locals"),
                 params: Vec::new(),
+                span: Span::default(),
             })),
             b"",
         );
 
-        test_parser_done(Value, b"1.5", Expression::Float(1.5), b"");
-        test_parser_done(Value, b"(0)", Expression::Int(0), b"");
-        test_parser_done(Value, b"((((((4))))))", Expression::Int(4), b"");
+        test_parser_done(Value, b"1.5", Expression::Float(1.5, Span::default()), b"");
+        test_parser_done(Value, b"(0)", Expression::Int(0, Span::default()), b"");
+        test_parser_done(
+            Value,
+            b"((((((4))))))",
+            Expression::Int(4, Span::default()),
+            b"",
+        );
     }
 
     #[test]
@@ -45,5 +52,4 @@ mod tests {
 
         test_parser_done(Value, b"a", expected, b"");
     }
-
 }
@@ -1,8 +1,10 @@
 use inner_errors::ParserError;
+use parsers::position::{position, span};
 use parsers::{expression, identifier_parser};
 use types::{Input, VarAccess};
 
 named!(pub var_access<Input, VarAccess, ParserError>, fix_error!(ParserError, gws!(do_parse!(
+    start: position >>
     first: identifier_parser >>
     second: opt!(preceded!(
         char_e!('.'),
@@ -11,14 +13,15 @@ named!(pub var_access<Input, VarAccess, ParserError>, fix_error!(ParserError, gw
         char_e!('['),
         delimited!(multispace0, expression, multispace0),
         char_e!(']'))) >>
+    end: position >>
 
-    (VarAccess::new(first, second, index))
+    (VarAccess::new(first, second, index).with_span(span(start, end)))
 ))));
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use types::{Expression, Identifier};
+    use types::{Expression, Identifier, Span};
 
     #[test]
     fn simple() {
@@ -43,7 +46,11 @@ mod tests {
     #[test]
     fn simple_array_int() {
         let input = Input(b"foo[3]");
-        let expected = VarAccess::new(Identifier::new(b"foo"), None, Some(Expression::Int(3)));
+        let expected = VarAccess::new(
+            Identifier::new(b"foo"),
+            None,
+            Some(Expression::Int(3, Span::default())),
+        );
 
         let actual = var_access(input).unwrap().1;
 
@@ -56,7 +63,7 @@ mod tests {
         let expected = VarAccess::new(
             Identifier::new(b"foo"),
             Some(Identifier::new(b"bar")),
-            Some(Expression::Int(3)),
+            Some(Expression::Int(3, Span::default())),
         );
 
         let actual = var_access(input).unwrap().1;
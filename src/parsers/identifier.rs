@@ -1,13 +1,16 @@
 use inner_errors::ParserError;
+use parsers::position::token_span;
 use types::{Identifier, Input};
 
 fn convert_identifier(input: Input) -> Identifier {
-    Identifier::new(&input.0)
+    Identifier::new(input.0).with_span(token_span(input))
 }
 
-const IDENTIFIER_BEGIN: &'static [u8] =
+/// Bytes an identifier may start with. The CST lexer and error recovery use the same tables.
+pub(crate) const IDENTIFIER_BEGIN: &[u8] =
     b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_1234567890";
-const IDENTIFIER_END: &'static [u8] =
+/// Bytes that may follow the first one of an identifier.
+pub(crate) const IDENTIFIER_END: &[u8] =
     b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ_^@1234567890\xC4\xE4\xD6\xF6\xFC\xDC\xDF";
 
 named!(pub identifier_list<Input, Vec<Identifier>, ParserError>,
@@ -35,11 +38,7 @@ fn is_valid(input: Input) -> bool {
         return true;
     }
 
-    if input.0.iter().all(u8::is_ascii_digit) {
-        return true;
-    }
-
-    return false;
+    input.0.iter().all(u8::is_ascii_digit)
 }
 
 fn is_keyword(input: Input) -> bool {
@@ -7,15 +7,20 @@ mod base;
 mod decl;
 mod exp;
 mod identifier;
+mod position;
+mod recovery;
 mod statement;
 
 pub use self::base::{float_parser, number_parser, string_parser};
 pub use self::decl::{
-    array_size_decl, class, const_array_decl, const_decl, declaration, func, instance, prototype,
-    var_decl, var_decl_list, var_decl_list_0,
+    array_size_decl, class, const_array_decl, const_decl, declaration, external, func, instance,
+    prototype, var_decl, var_decl_list, var_decl_list_0,
 };
 pub use self::exp::{call_parser, expression, var_access, Bit, Boolean, Cmp, Mul, Unary, Value};
 pub use self::identifier::{identifier_list, identifier_parser};
+pub(crate) use self::identifier::{IDENTIFIER_BEGIN, IDENTIFIER_END};
+pub use self::position::{offset, with_source};
+pub use self::recovery::{parse_externals, parse_recovering};
 pub use self::statement::{assignment, if_clause, statement, statement_block};
 
 pub use self::util::whitespace;
@@ -0,0 +1,159 @@
+use std::cell::Cell;
+use types::{FileId, Input, PResult, Span};
+
+#[derive(Clone, Copy, Default)]
+struct Source {
+    start: usize,
+    len: usize,
+    file: FileId,
+}
+
+thread_local! {
+    static SOURCE: Cell<Source> = Cell::new(Source::default());
+}
+
+struct SourceGuard {
+    previous: Source,
+}
+
+impl Drop for SourceGuard {
+    fn drop(&mut self) {
+        SOURCE.with(|source| source.set(self.previous));
+    }
+}
+
+/// Runs `f` with `content` registered as the file that is currently being parsed,
+/// so that every span produced by the parsers is relative to the start of `content`.
+///
+/// Parsers invoked outside of `with_source` still work, but the spans they produce are meaningless.
+pub fn with_source<T, F: FnOnce() -> T>(content: &[u8], file: FileId, f: F) -> T {
+    let source = Source {
+        start: content.as_ptr() as usize,
+        len: content.len(),
+        file,
+    };
+    let _guard = SourceGuard {
+        previous: SOURCE.with(|current| current.replace(source)),
+    };
+
+    f()
+}
+
+/// Byte offset of `input` within the file registered by `with_source`.
+pub fn offset(input: Input) -> usize {
+    let ptr = input.0.as_ptr() as usize;
+    SOURCE.with(|source| {
+        let source = source.get();
+        if ptr >= source.start && ptr <= source.start + source.len {
+            ptr - source.start
+        } else {
+            0
+        }
+    })
+}
+
+fn current_file() -> FileId {
+    SOURCE.with(|source| source.get().file)
+}
+
+/// Span of a single token, e.g. an identifier recognized by the parser.
+pub fn token_span(token: Input) -> Span {
+    let start = offset(token);
+    Span::new(current_file(), start, start + token.0.len())
+}
+
+/// Returns its input unchanged, so it can be used in `do_parse!` to remember a position.
+pub fn position(input: Input) -> PResult<Input> {
+    Ok((input, input))
+}
+
+/// Span of everything consumed between `start` and `end`, excluding
+/// surrounding whitespace and comments.
+pub fn span(start: Input, end: Input) -> Span {
+    let consumed = &start.0[..start.0.len() - end.0.len()];
+    let (first, last) = token_bounds(consumed);
+    let base = offset(start);
+
+    Span::new(current_file(), base + first, base + last)
+}
+
+/// Runs `parser` and additionally returns the span of what it consumed.
+pub fn spanned<'a, O, F>(input: Input<'a>, parser: F) -> PResult<'a, (O, Span)>
+where
+    F: Fn(Input<'a>) -> PResult<'a, O>,
+{
+    let (rest, output) = parser(input)?;
+    Ok((rest, (output, span(input, rest))))
+}
+
+/// Finds the first and one-past-the-last byte of `bytes` that are neither whitespace nor comment.
+fn token_bounds(bytes: &[u8]) -> (usize, usize) {
+    let mut first = None;
+    let mut last = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' => i += 1,
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = bytes[i..]
+                    .iter()
+                    .position(|b| *b == b'\n')
+                    .map(|pos| i + pos + 1)
+                    .unwrap_or_else(|| bytes.len());
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = bytes[i + 2..]
+                    .windows(2)
+                    .position(|w| w == b"*/")
+                    .map(|pos| i + 2 + pos + 2)
+                    .unwrap_or_else(|| bytes.len());
+            }
+            b'"' => {
+                first.get_or_insert(i);
+                i = bytes[i + 1..]
+                    .iter()
+                    .position(|b| *b == b'"')
+                    .map(|pos| i + 1 + pos + 1)
+                    .unwrap_or_else(|| bytes.len());
+                last = i;
+            }
+            _ => {
+                first.get_or_insert(i);
+                i += 1;
+                last = i;
+            }
+        }
+    }
+
+    match first {
+        Some(first) => (first, last),
+        None => (0, 0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_skip_trivia() {
+        assert_eq!(token_bounds(b"  foo  "), (2, 5));
+        assert_eq!(token_bounds(b"foo // bar\n"), (0, 3));
+        assert_eq!(token_bounds(b"/* a */ foo(1) /* b */"), (8, 14));
+        assert_eq!(token_bounds(b"\"a // b\"  "), (0, 8));
+        assert_eq!(token_bounds(b" \n "), (0, 0));
+    }
+
+    #[test]
+    fn offsets_relative_to_source() {
+        let content = b"  foo bar";
+        let input = Input(content);
+
+        let span = with_source(content, FileId(3), || {
+            span(Input(&input.0[2..]), Input(&input.0[6..]))
+        });
+
+        assert!(span.same_location(&Span::new(FileId(3), 2, 5)));
+    }
+}
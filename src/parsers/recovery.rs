@@ -0,0 +1,340 @@
+use error_handler;
+use errors::Error;
+use inner_errors::ParserError;
+use nom::IResult;
+use parsers::position::with_source;
+use parsers::replacements::multispace0;
+use parsers::{declaration, external, IDENTIFIER_END};
+use types::{FileId, Function, Input, Span, AST};
+
+const TOP_LEVEL_KEYWORDS: &[&[u8]] = &[
+    b"func",
+    b"instance",
+    b"prototype",
+    b"class",
+    b"var",
+    b"const",
+];
+
+/// Parses a whole file like `start`, but does not give up at the first syntax error.
+///
+/// Whenever a declaration fails to parse, the error is recorded and parsing resumes at the next
+/// point where a new declaration can reasonably begin (see `resync`). The returned AST contains
+/// every declaration that parsed successfully.
+pub fn parse_recovering(content: &[u8], file: FileId) -> (AST, Vec<Error>) {
+    let (declarations, errors) = parse_all(content, file, declaration);
+    (AST { declarations }, errors)
+}
+
+/// Parses an externals file, which only declares functions, recovering from errors like
+/// `parse_recovering`.
+pub fn parse_externals(content: &[u8], file: FileId) -> (Vec<Function>, Vec<Error>) {
+    parse_all(content, file, external)
+}
+
+fn parse_all<T, P>(content: &[u8], file: FileId, parser: P) -> (Vec<T>, Vec<Error>)
+where
+    P: Fn(Input) -> IResult<Input, T, ParserError>,
+{
+    with_source(content, file, || {
+        let mut items = Vec::new();
+        let mut errors = Vec::new();
+        let mut input = Input(content);
+
+        loop {
+            if let Ok((rest, _)) = multispace0(input) {
+                input = rest;
+            }
+            if input.0.is_empty() {
+                break;
+            }
+
+            match parser(input) {
+                Ok((rest, item)) => {
+                    items.push(item);
+                    input = rest;
+                }
+                Err(err) => {
+                    let decl_start = content.len() - input.0.len();
+                    let mut err = error_handler::map_err(content, file, err);
+                    let error_pos = match err {
+                        Error::ParsingError { span, .. } => span.start.max(decl_start),
+                        _ => decl_start,
+                    };
+                    let resume = resync(content, decl_start, error_pos);
+                    if let Error::ParsingError { ref mut block, .. } = err {
+                        *block = open_block(content, decl_start, resume)
+                            .map(|pos| Span::new(file, pos, pos + 1));
+                    }
+                    errors.push(err);
+
+                    input = Input(&content[resume..]);
+                }
+            }
+        }
+
+        (items, errors)
+    })
+}
+
+/// Finds the offset at which parsing should resume after the declaration starting at
+/// `decl_start` failed with an error at `error_pos`.
+///
+/// Candidates, in the order they are encountered after the error:
+/// - a top-level keyword (`func`, `var`, ...) outside of any braces,
+/// - the position after a `;` outside of any braces,
+/// - the position after a `}` (and an optional `;`) that closes the outermost brace,
+/// - `func`, `instance`, `prototype` or `class` at the start of a line, even inside braces,
+///   in case a brace was never closed. Since the parser may only notice the missing brace after
+///   consuming such a keyword, these are accepted from the start of the error's line on.
+///
+/// The result is always past `decl_start`, so parsing is guaranteed to make progress.
+fn resync(content: &[u8], decl_start: usize, error_pos: usize) -> usize {
+    let mut depth = 0usize;
+    let mut tokens = Tokens::new(content, decl_start).peekable();
+    let error_line_start = content[..error_pos]
+        .iter()
+        .rposition(|b| *b == b'\n')
+        .map(|pos| pos + 1)
+        .unwrap_or(0);
+
+    while let Some(token) = tokens.next() {
+        let after_error = token.start >= error_pos && token.start > decl_start;
+        let on_error_line = token.start >= error_line_start && token.start > decl_start;
+
+        match token.kind {
+            TokenKind::Word if on_error_line => {
+                let word = &content[token.start..token.end];
+                let is_keyword = TOP_LEVEL_KEYWORDS
+                    .iter()
+                    .any(|keyword| word.eq_ignore_ascii_case(keyword));
+                let is_block_keyword = is_keyword
+                    && !word.eq_ignore_ascii_case(b"var")
+                    && !word.eq_ignore_ascii_case(b"const");
+
+                if (is_keyword && depth == 0 && after_error)
+                    || (is_block_keyword && token.at_line_start)
+                {
+                    return token.start;
+                }
+            }
+            TokenKind::Punct(b'{') => depth += 1,
+            TokenKind::Punct(b'}') => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 && token.end > error_pos {
+                    return match tokens.peek() {
+                        Some(next) if next.kind == TokenKind::Punct(b';') => next.end,
+                        _ => token.end,
+                    };
+                }
+            }
+            TokenKind::Punct(b';') if depth == 0 && token.end > error_pos => return token.end,
+            _ => {}
+        }
+    }
+
+    content.len()
+}
+
+/// The offset of the innermost `{` of the declaration starting at `decl_start` that is still
+/// open at `end`.
+fn open_block(content: &[u8], decl_start: usize, end: usize) -> Option<usize> {
+    let mut open = Vec::new();
+    for token in Tokens::new(content, decl_start).take_while(|token| token.start < end) {
+        match token.kind {
+            TokenKind::Punct(b'{') => open.push(token.start),
+            TokenKind::Punct(b'}') => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+
+    open.pop()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum TokenKind {
+    Word,
+    Punct(u8),
+    String,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+    at_line_start: bool,
+}
+
+/// A minimal tokenizer that skips whitespace, comments and string contents,
+/// which is all that is needed to find synchronization points.
+struct Tokens<'a> {
+    content: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(content: &'a [u8], pos: usize) -> Self {
+        Tokens { content, pos }
+    }
+
+    fn skip_until(&mut self, pattern: &[u8]) {
+        self.pos = self.content[self.pos..]
+            .windows(pattern.len())
+            .position(|window| window == pattern)
+            .map(|found| self.pos + found + pattern.len())
+            .unwrap_or_else(|| self.content.len());
+    }
+}
+
+fn is_line_start(content: &[u8], pos: usize) -> bool {
+    content[..pos]
+        .iter()
+        .rev()
+        .take_while(|b| **b != b'\n')
+        .all(|b| *b == b' ' || *b == b'\t' || *b == b'\r')
+}
+
+/// Identifiers and keywords, and the bytes that may continue them.
+fn is_word_byte(b: u8) -> bool {
+    IDENTIFIER_END.contains(&b)
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        let content = self.content;
+        while self.pos < content.len() {
+            let start = self.pos;
+            match content[start] {
+                b' ' | b'\t' | b'\r' | b'\n' | b'\x0c' => self.pos += 1,
+                b'/' if content.get(start + 1) == Some(&b'/') => self.skip_until(b"\n"),
+                b'/' if content.get(start + 1) == Some(&b'*') => {
+                    self.pos += 2;
+                    self.skip_until(b"*/");
+                }
+                b'"' => {
+                    self.pos += 1;
+                    self.skip_until(b"\"");
+                    return Some(Token {
+                        kind: TokenKind::String,
+                        start,
+                        end: self.pos,
+                        at_line_start: is_line_start(content, start),
+                    });
+                }
+                b if is_word_byte(b) => {
+                    self.pos += content[start..]
+                        .iter()
+                        .take_while(|b| is_word_byte(**b))
+                        .count();
+                    return Some(Token {
+                        kind: TokenKind::Word,
+                        start,
+                        end: self.pos,
+                        at_line_start: is_line_start(content, start),
+                    });
+                }
+                b => {
+                    self.pos += 1;
+                    return Some(Token {
+                        kind: TokenKind::Punct(b),
+                        start,
+                        end: self.pos,
+                        at_line_start: is_line_start(content, start),
+                    });
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use inner_errors::ParserError;
+    use types::Declaration;
+
+    fn names(ast: &AST) -> Vec<String> {
+        ast.declarations
+            .iter()
+            .map(|decl| match *decl {
+                Declaration::Func(ref func) => func.name.to_string(),
+                Declaration::Var(ref vars) => vars[0].name.to_string(),
+                Declaration::Inst(ref insts) => insts[0].name.to_string(),
+                _ => String::new(),
+            })
+            .collect()
+    }
+
+    fn parser_errors(errors: &[Error]) -> Vec<(ParserError, usize)> {
+        errors
+            .iter()
+            .map(|err| match *err {
+                Error::ParsingError { err, line, .. } => (err, line),
+                _ => panic!("unexpected error"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_errors() {
+        let content = b"var int a;\nfunc void foo() {};";
+
+        let (ast, errors) = parse_recovering(content, FileId(0));
+
+        assert!(errors.is_empty());
+        assert_eq!(names(&ast), vec!["a", "foo"]);
+    }
+
+    #[test]
+    fn broken_function_body() {
+        let content = b"func void foo() {\n    x = ;\n};\nfunc void bar() {};\nfunc void baz() { y = 3 };\nvar int z;";
+
+        let (ast, errors) = parse_recovering(content, FileId(0));
+
+        assert_eq!(names(&ast), vec!["bar", "z"]);
+        assert_eq!(errors.len(), 2);
+        let lines: Vec<usize> = parser_errors(&errors).iter().map(|e| e.1).collect();
+        assert_eq!(lines, vec![2, 5]);
+    }
+
+    #[test]
+    fn missing_semicolon_between_declarations() {
+        let content = b"var int a\nvar int b;\nvar int c;";
+
+        let (ast, errors) = parse_recovering(content, FileId(0));
+
+        assert_eq!(names(&ast), vec!["b", "c"]);
+        assert_eq!(parser_errors(&errors), vec![(ParserError::MissingSemi, 2)]);
+    }
+
+    #[test]
+    fn unclosed_brace() {
+        let content = b"func void foo() {\n    if (1) {\n};\nfunc void bar() {};\n";
+
+        let (ast, errors) = parse_recovering(content, FileId(0));
+
+        assert_eq!(names(&ast), vec!["bar"]);
+        assert_eq!(errors.len(), 1);
+        match errors[0] {
+            Error::ParsingError { block, .. } => assert_eq!(block.map(|b| b.start), Some(16)),
+            _ => panic!("unexpected error"),
+        }
+    }
+
+    #[test]
+    fn garbage() {
+        let content = b"@@@ ;\n// func in a comment\n\"func\" }\ninstance foo(bar);";
+
+        let (ast, errors) = parse_recovering(content, FileId(0));
+
+        assert_eq!(names(&ast), vec!["foo"]);
+        assert!(!errors.is_empty());
+    }
+}
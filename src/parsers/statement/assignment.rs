@@ -1,8 +1,10 @@
 use inner_errors::ParserError;
+use parsers::position::{position, span};
 use parsers::{expression, var_access};
 use types::{Assignment, AssignmentOperator, Input};
 
 named!(pub assignment<Input, Assignment, ParserError>, fix_error!(ParserError, gws!(do_parse!(
+    start: position >>
     lhs: var_access >> multispace0 >>
     op: alt!(
             tag_e!("+=")
@@ -11,18 +13,20 @@ named!(pub assignment<Input, Assignment, ParserError>, fix_error!(ParserError, g
           | tag_e!("/=")
           | tag_e!("=")
          ) >> multispace0 >>
-    rhs: expression >> multispace0 >>
+    rhs: expression >>
+    end: position >> multispace0 >>
     (Assignment {
         var: lhs,
-        op: AssignmentOperator::from(&op.0),
-        exp: rhs
+        op: AssignmentOperator::from(op.0),
+        exp: rhs,
+        span: span(start, end),
     })
 ))));
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use types::{Expression, Identifier, VarAccess};
+    use types::{Expression, Identifier, Span, VarAccess};
 
     #[test]
     fn simple_eq_int() {
@@ -30,7 +34,8 @@ mod tests {
         let expected = Assignment {
             var: VarAccess::new(Identifier::new(b"foo"), None, None),
             op: AssignmentOperator::Eq,
-            exp: Expression::Int(3),
+            exp: Expression::Int(3, Span::default()),
+            span: Span::default(),
         };
 
         let actual = assignment(input).unwrap().1;
@@ -44,7 +49,8 @@ mod tests {
         let expected = Assignment {
             var: VarAccess::new(Identifier::new(b"foo"), Some(Identifier::new(b"bar")), None),
             op: AssignmentOperator::Eq,
-            exp: Expression::Int(3),
+            exp: Expression::Int(3, Span::default()),
+            span: Span::default(),
         };
 
         let actual = assignment(input).unwrap().1;
@@ -58,7 +64,8 @@ mod tests {
         let expected = Assignment {
             var: VarAccess::new(Identifier::new(b"foo"), Some(Identifier::new(b"bar")), None),
             op: AssignmentOperator::DivideEq,
-            exp: Expression::Int(3),
+            exp: Expression::Int(3, Span::default()),
+            span: Span::default(),
         };
 
         let actual = assignment(input).unwrap().1;
@@ -70,9 +77,14 @@ mod tests {
     fn array_assign() {
         let input = Input(b"foo[0]/=3");
         let expected = Assignment {
-            var: VarAccess::new(Identifier::new(b"foo"), None, Some(Expression::Int(0))),
+            var: VarAccess::new(
+                Identifier::new(b"foo"),
+                None,
+                Some(Expression::Int(0, Span::default())),
+            ),
             op: AssignmentOperator::DivideEq,
-            exp: Expression::Int(3),
+            exp: Expression::Int(3, Span::default()),
+            span: Span::default(),
         };
 
         let actual = assignment(input).unwrap().1;
@@ -1,18 +1,21 @@
 use inner_errors::ParserError;
 use nom::ErrorKind;
+use parsers::position::{position, span};
 use parsers::{expression, replacements::*, statement_block};
-use types::{IfBranch, IfStatement, Input, Statement};
+use types::{IfBranch, IfStatement, Input, Span, Statement};
 
 named!(pub if_branch<Input, IfBranch, ParserError>, do_parse!(
+    start: position >>
     tag_no_case_e!("if") >> multispace0 >>
-    branch: return_error!(ErrorKind::Custom(ParserError::IfClause), if_branch_real) >> multispace0 >>
-    (branch)
+    branch: return_error!(ErrorKind::Custom(ParserError::IfClause), if_branch_real) >>
+    end: position >> multispace0 >>
+    (IfBranch { span: span(start, end), ..branch })
 ));
 
 named!(if_branch_real<Input, IfBranch, ParserError>, fix_error!(ParserError, do_parse!(
     cond: return_error!(ErrorKind::Custom(ParserError::IllegalExpression), expression) >> multispace0 >>
     body: statement_block >> multispace0 >>
-    (IfBranch {cond, body})
+    (IfBranch {cond, body, span: Span::default()})
 )));
 
 named!(pub else_branch<Input, Vec<Statement>, ParserError>, do_parse!(
@@ -27,13 +30,15 @@ named!(else_branch_real<Input, Vec<Statement>, ParserError>, fix_error!(ParserEr
 )));
 
 named!(pub if_clause<Input, IfStatement, ParserError>, fix_error!(ParserError, do_parse!(
+    start: position >>
     branches: separated_nonempty_list!(
         gws!(tag_no_case_e!("else")),
         if_branch) >> multispace0 >>
 
-    else_branch: opt!(else_branch) >> multispace0 >>
+    else_branch: opt!(else_branch) >>
+    end: position >> multispace0 >>
 
-    (IfStatement { branches, else_branch})
+    (IfStatement { branches, else_branch, span: span(start, end) })
 )));
 
 #[cfg(test)]
@@ -45,8 +50,9 @@ mod tests {
     fn simple_empty() {
         let input = Input(b"if (3) {}");
         let expected = IfBranch {
-            cond: Expression::Int(3),
+            cond: Expression::Int(3, Span::default()),
             body: Vec::new(),
+            span: Span::default(),
         };
 
         let actual = if_branch(input).unwrap().1;
@@ -58,8 +64,9 @@ mod tests {
     fn single_statement() {
         let input = Input(b"if (3) {5;}");
         let expected = IfBranch {
-            cond: Expression::Int(3),
-            body: vec![Statement::Exp(Expression::Int(5))],
+            cond: Expression::Int(3, Span::default()),
+            body: vec![Statement::Exp(Expression::Int(5, Span::default()))],
+            span: Span::default(),
         };
 
         let actual = if_branch(input).unwrap().1;
@@ -75,16 +82,19 @@ mod tests {
             Expression::Call(Box::new(Call {
                 func: Identifier::new(b"foo"),
                 params: Vec::new(),
+                span: Span::default(),
             })),
             Expression::Call(Box::new(Call {
                 func: Identifier::new(b"bar"),
                 params: Vec::new(),
+                span: Span::default(),
             })),
         )));
 
         let expected = IfBranch {
             cond,
-            body: vec![Statement::Exp(Expression::Int(5))],
+            body: vec![Statement::Exp(Expression::Int(5, Span::default()))],
+            span: Span::default(),
         };
 
         let actual = if_branch(input).unwrap().1;
@@ -96,12 +106,13 @@ mod tests {
     fn many_statements() {
         let input = Input(b"if (3) {5;3;6;}");
         let expected = IfBranch {
-            cond: Expression::Int(3),
+            cond: Expression::Int(3, Span::default()),
             body: vec![
-                Statement::Exp(Expression::Int(5)),
-                Statement::Exp(Expression::Int(3)),
-                Statement::Exp(Expression::Int(6)),
+                Statement::Exp(Expression::Int(5, Span::default())),
+                Statement::Exp(Expression::Int(3, Span::default())),
+                Statement::Exp(Expression::Int(6, Span::default())),
             ],
+            span: Span::default(),
         };
 
         let actual = if_branch(input).unwrap().1;
@@ -122,7 +133,7 @@ mod tests {
     #[test]
     fn else_branch_single_statement() {
         let input = Input(b"else{5;}");
-        let expected: Vec<Statement> = vec![Statement::Exp(Expression::Int(5))];
+        let expected: Vec<Statement> = vec![Statement::Exp(Expression::Int(5, Span::default()))];
 
         let actual = else_branch(input).unwrap().1;
 
@@ -134,10 +145,12 @@ mod tests {
         let input = Input(b"if3{}else{}");
         let expected = IfStatement {
             branches: vec![IfBranch {
-                cond: Expression::Int(3),
+                cond: Expression::Int(3, Span::default()),
                 body: Vec::new(),
+                span: Span::default(),
             }],
             else_branch: Some(Vec::new()),
+            span: Span::default(),
         };
 
         let actual = if_clause(input).unwrap().1;
@@ -151,15 +164,18 @@ mod tests {
         let expected = IfStatement {
             branches: vec![
                 IfBranch {
-                    cond: Expression::Int(3),
+                    cond: Expression::Int(3, Span::default()),
                     body: Vec::new(),
+                    span: Span::default(),
                 },
                 IfBranch {
-                    cond: Expression::Int(2),
+                    cond: Expression::Int(2, Span::default()),
                     body: Vec::new(),
+                    span: Span::default(),
                 },
             ],
             else_branch: None,
+            span: Span::default(),
         };
 
         let actual = if_clause(input).unwrap().1;
@@ -173,15 +189,18 @@ mod tests {
         let expected = IfStatement {
             branches: vec![
                 IfBranch {
-                    cond: Expression::Int(3),
+                    cond: Expression::Int(3, Span::default()),
                     body: Vec::new(),
+                    span: Span::default(),
                 },
                 IfBranch {
-                    cond: Expression::Int(2),
+                    cond: Expression::Int(2, Span::default()),
                     body: Vec::new(),
+                    span: Span::default(),
                 },
             ],
             else_branch: Some(Vec::new()),
+            span: Span::default(),
         };
 
         let actual = if_clause(input).unwrap().1;
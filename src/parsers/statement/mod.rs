@@ -1,7 +1,8 @@
 mod assignment;
 mod if_clause;
+#[allow(clippy::module_inception)]
 mod statement;
 
 pub use self::assignment::assignment;
-pub use self::if_clause::{if_branch, if_clause};
+pub use self::if_clause::if_clause;
 pub use self::statement::{statement, statement_block};
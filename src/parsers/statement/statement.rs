@@ -1,5 +1,6 @@
 use inner_errors::ParserError;
 use nom::ErrorKind;
+use parsers::position::spanned;
 use parsers::{
     assignment, const_array_decl, const_decl, expression, if_clause, replacements::*, var_decl_list,
 };
@@ -12,7 +13,7 @@ named!(semi_statement<Input, Statement, ParserError>, do_parse!(
         |map!(const_decl, Statement::ConstDeclaration)
         |map!(assignment, Statement::Ass)
         |map!(expression, Statement::Exp)
-        |map!(return_parser, Statement::ReturnStatement)
+        |map!(call!(spanned, return_parser), |(exp, span)| Statement::ReturnStatement(exp, span))
     )) >> multispace0 >>
     return_error!(ErrorKind::Custom(ParserError::MissingSemi), char_e!(';')) >> multispace0 >>
     (state)
@@ -45,7 +46,7 @@ named!(return_parser<Input, Option<Expression>, ParserError>, fix_error!(ParserE
 mod tests {
     use super::*;
     use types::{
-        Assignment, AssignmentOperator, Expression, Identifier, IfBranch, IfStatement,
+        Assignment, AssignmentOperator, Expression, Identifier, IfBranch, IfStatement, Span,
         UnaryExpression, VarAccess,
     };
 
@@ -55,7 +56,8 @@ mod tests {
         let expected = Statement::Ass(Assignment {
             var: VarAccess::new(Identifier::new(b"foo"), None, None),
             op: AssignmentOperator::Eq,
-            exp: Expression::Int(3),
+            exp: Expression::Int(3, Span::default()),
+            span: Span::default(),
         });
 
         let actual = statement(input).unwrap().1;
@@ -68,7 +70,7 @@ mod tests {
         let input = Input(b"!3 ;");
         let expected = Statement::Exp(Expression::Unary(Box::new(UnaryExpression::new(
             b'!',
-            Expression::Int(3),
+            Expression::Int(3, Span::default()),
         ))));
 
         let actual = statement(input).unwrap().1;
@@ -81,10 +83,12 @@ mod tests {
         let input = Input(b"if(3){4;};");
         let expected = Statement::If(Box::new(IfStatement {
             branches: vec![IfBranch {
-                cond: Expression::Int(3),
-                body: vec![Statement::Exp(Expression::Int(4))],
+                cond: Expression::Int(3, Span::default()),
+                body: vec![Statement::Exp(Expression::Int(4, Span::default()))],
+                span: Span::default(),
             }],
             else_branch: None,
+            span: Span::default(),
         }));
 
         let actual = statement(input).unwrap().1;
@@ -95,7 +99,7 @@ mod tests {
     #[test]
     fn void_return() {
         let input = Input(b"return;");
-        let expected = Statement::ReturnStatement(None);
+        let expected = Statement::ReturnStatement(None, Span::default());
 
         let actual = statement(input).unwrap().1;
 
@@ -105,7 +109,8 @@ mod tests {
     #[test]
     fn int_return() {
         let input = Input(b"return 3;");
-        let expected = Statement::ReturnStatement(Some(Expression::Int(3)));
+        let expected =
+            Statement::ReturnStatement(Some(Expression::Int(3, Span::default())), Span::default());
 
         let actual = statement(input).unwrap().1;
 
@@ -125,5 +130,4 @@ mod tests {
 
         assert_eq!(expected, actual);
     }
-
 }
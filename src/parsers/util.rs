@@ -62,7 +62,7 @@ macro_rules! gws (
 mod tests {
     use super::*;
     use parsers::expression;
-    use types::{BinaryExpression, BinaryOperator, Expression};
+    use types::{BinaryExpression, BinaryOperator, Expression, Span};
 
     #[test]
     fn simple_whitespace() {
@@ -129,13 +129,12 @@ mod tests {
         let input = Input(b"4//\n+3");
         let expected = Expression::Binary(Box::new(BinaryExpression::new(
             BinaryOperator::Plus,
-            Expression::Int(4),
-            Expression::Int(3),
+            Expression::Int(4, Span::default()),
+            Expression::Int(3, Span::default()),
         )));
 
         let actual = expression(input).unwrap().1;
 
         assert_eq!(expected, actual);
     }
-
 }
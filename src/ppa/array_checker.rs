@@ -0,0 +1,194 @@
+use errors::Error;
+use inner_errors::{ConstError, TypeError};
+use ppa::constant::Evaluator;
+use ppa::name_resolver::Resolution;
+use ppa::visitor::{visit_ast, VisitorMut};
+use types::{ArraySizeDeclaration, ConstArrayDeclaration, Identifier, VarAccess, AST};
+
+/// Checks array sizes, the number of elements of constant arrays and constant indices in all
+/// `asts`. The `evaluator` must know the constants of all files, not only of `asts`.
+pub fn check<'a, I: IntoIterator<Item = &'a AST>>(
+    asts: I,
+    resolution: &Resolution,
+    evaluator: &Evaluator,
+) -> Vec<Error> {
+    let mut checker = ArrayChecker {
+        resolution,
+        evaluator,
+        errors: Vec::new(),
+    };
+    for ast in asts {
+        visit_ast(ast, &mut checker);
+    }
+
+    checker.errors
+}
+
+/// Array sizes must be positive constants. Indices need not be constant, but if they are,
+/// they must be within the array.
+struct ArrayChecker<'a, 'b: 'a> {
+    resolution: &'a Resolution,
+    evaluator: &'a Evaluator<'b>,
+    errors: Vec<Error>,
+}
+
+impl<'a, 'b> ArrayChecker<'a, 'b> {
+    fn error(&mut self, err: TypeError) {
+        self.errors.push(Error::TypeCheckError(err));
+    }
+
+    /// The evaluated `size`, if it is valid. Sizes naming something undefined are left to the
+    /// name resolver.
+    fn array_size(&mut self, size: &ArraySizeDeclaration) -> Option<usize> {
+        if let ArraySizeDeclaration::Identifier(ref name) = *size {
+            self.resolution.symbol_at(name.span)?;
+        }
+
+        match self.evaluator.array_size(size) {
+            Ok(count) if count > 0 => Some(count as usize),
+            Ok(count) => {
+                self.error(TypeError::InvalidArraySize {
+                    size: count,
+                    span: size.span(),
+                });
+                None
+            }
+            Err(err) => {
+                self.error(TypeError::Constant(err));
+                None
+            }
+        }
+    }
+}
+
+impl<'a, 'b> VisitorMut for ArrayChecker<'a, 'b> {
+    fn visit_var_decl(&mut self, decl: &::types::VarDeclaration, _scope: Option<&Identifier>) {
+        if let Some(ref size) = decl.array_size {
+            self.array_size(size);
+        }
+    }
+
+    fn visit_func_decl(&mut self, decl: &::types::Function) {
+        for param in &decl.params {
+            self.visit_var_decl(param, Some(&decl.name));
+        }
+    }
+
+    fn visit_const_arr_decl(&mut self, decl: &ConstArrayDeclaration, _scope: Option<&Identifier>) {
+        let found = decl.initializer.expressions.len();
+        match self.array_size(&decl.array_size) {
+            Some(expected) if expected != found => self.error(TypeError::WrongElementCount {
+                array: decl.name.clone(),
+                expected,
+                found,
+            }),
+            _ => {}
+        }
+    }
+
+    fn visit_var_access(&mut self, access: &VarAccess, _scope: Option<&Identifier>) {
+        let index = match access.index {
+            Some(ref index) => index,
+            None => return,
+        };
+        let symbol = match self.resolution.symbol_at(access.name.span) {
+            Some(id) => self.resolution.symbols.get(id),
+            None => return,
+        };
+        // Only constant indices can be checked, and only against valid sizes, which were
+        // reported where the array is declared otherwise.
+        let size = match symbol.array_size {
+            Some(ref size) => match self.evaluator.array_size(size) {
+                Ok(size) if size > 0 => size as usize,
+                _ => return,
+            },
+            None => 1,
+        };
+
+        if let Ok(value) = self.evaluator.int(index) {
+            if value < 0 || value as usize >= size {
+                self.error(TypeError::Constant(ConstError::IndexOutOfBounds {
+                    index: value,
+                    size,
+                    span: index.span(),
+                }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use ppa::name_resolver::resolve;
+    use types::FileId;
+
+    fn check_errors(content: &str) -> Vec<String> {
+        let (ast, errors) = parse_recovering(content.as_bytes(), FileId(0));
+        assert!(errors.is_empty(), "{:?}", errors);
+        let resolution = resolve(Some(&ast));
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        let evaluator = Evaluator::new(Some(&ast), &resolution);
+
+        check(Some(&ast), &resolution, &evaluator)
+            .iter()
+            .map(|err| match *err {
+                Error::TypeCheckError(ref err) => err.to_string(),
+                ref other => panic!("unexpected error {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_arrays() {
+        let errors = check_errors(
+            "const int MAX = 2;
+const int SIZE = MAX + 1;
+const int ARR[SIZE] = { 1, 2, 3 };
+class C { var int values[MAX]; };
+var int v[3];
+func void f(var int p[MAX]) { var C c; c.values[1] = ARR[2]; v[MAX] = v[0]; p[SIZE - 2] = 1; };",
+        );
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn invalid_sizes() {
+        let errors = check_errors(
+            "const int ZERO = 0;
+var int v;
+var int a[ZERO];
+var int b[-2];
+var int c[v];
+const int D[2] = { 1, 2, 3 };",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Array size must be positive, found 0",
+                "Array size must be positive, found -2",
+                "Expression is not constant",
+                "Wrong number of array elements for 'D': expected 2, found 3",
+            ]
+        );
+    }
+
+    #[test]
+    fn constant_indices() {
+        let errors = check_errors(
+            "const int MAX = 2;
+var int v[MAX];
+var int x;
+func void f() { var int i; v[MAX] = 1; v[i] = x[0]; x[1] = v[-1]; };",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Index out of bounds: 2 for an array of size 2",
+                "Index out of bounds: 1 for an array of size 1",
+                "Index out of bounds: -1 for an array of size 2",
+            ]
+        );
+    }
+}
@@ -0,0 +1,361 @@
+//! Which functions the functions, instances and prototypes of the scripts call.
+
+use ppa::name_resolver::Resolution;
+use ppa::visitor::{visit_ast, VisitorMut};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use types::{Call, Identifier, Span, SymbolCollection, SymbolId, SymbolKind, AST};
+
+/// A call of `callee` in the body of `caller`.
+#[derive(Debug, Clone, Copy)]
+pub struct Edge {
+    pub caller: SymbolId,
+    pub callee: SymbolId,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CallGraph {
+    /// All functions, instances and prototypes of the scripts and the externals they call, in
+    /// declaration order.
+    nodes: Vec<SymbolId>,
+    /// Every call, in the order of the scripts.
+    edges: Vec<Edge>,
+    /// Each pair of caller and callee once, with the number of calls, in the order of the first.
+    calls: Vec<(SymbolId, SymbolId, usize)>,
+    callees: HashMap<SymbolId, Vec<SymbolId>>,
+    callers: HashMap<SymbolId, Vec<SymbolId>>,
+}
+
+impl CallGraph {
+    /// Builds the call graph of `asts`, which must all have been resolved. Calls in the
+    /// initializers of global constants have no caller and are left out.
+    pub fn build<'a, I: IntoIterator<Item = &'a AST>>(asts: I, resolution: &Resolution) -> Self {
+        let mut builder = CallGraphBuilder {
+            resolution,
+            edges: Vec::new(),
+        };
+        for ast in asts {
+            visit_ast(ast, &mut builder);
+        }
+
+        let called: HashSet<SymbolId> = builder.edges.iter().map(|edge| edge.callee).collect();
+        let nodes = resolution
+            .symbols
+            .iter()
+            .filter(|(id, symbol)| {
+                let callable = matches!(
+                    symbol.kind,
+                    SymbolKind::Func | SymbolKind::Instance | SymbolKind::Prototype
+                );
+                callable && symbol.is_global() && (!symbol.external || called.contains(id))
+            })
+            .map(|(id, _)| id)
+            .collect();
+
+        CallGraph::new(nodes, builder.edges)
+    }
+
+    /// Indexes `edges` by caller and callee.
+    fn new(nodes: Vec<SymbolId>, edges: Vec<Edge>) -> Self {
+        let mut graph = CallGraph {
+            nodes,
+            ..CallGraph::default()
+        };
+        let mut pairs: HashMap<(SymbolId, SymbolId), usize> = HashMap::new();
+        for edge in &edges {
+            match pairs.get(&(edge.caller, edge.callee)) {
+                Some(&index) => graph.calls[index].2 += 1,
+                None => {
+                    pairs.insert((edge.caller, edge.callee), graph.calls.len());
+                    graph.calls.push((edge.caller, edge.callee, 1));
+                    graph
+                        .callees
+                        .entry(edge.caller)
+                        .or_default()
+                        .push(edge.callee);
+                    graph
+                        .callers
+                        .entry(edge.callee)
+                        .or_default()
+                        .push(edge.caller);
+                }
+            }
+        }
+        graph.edges = edges;
+        graph
+    }
+
+    pub fn nodes(&self) -> &[SymbolId] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    /// Each pair of caller and callee once, with the number of calls, in the order of the first.
+    pub fn calls(&self) -> &[(SymbolId, SymbolId, usize)] {
+        &self.calls
+    }
+
+    /// The functions `caller` calls, in the order of their first call.
+    pub fn callees(&self, caller: SymbolId) -> &[SymbolId] {
+        self.callees
+            .get(&caller)
+            .map_or(&[], |callees| &callees[..])
+    }
+
+    /// The functions, instances and prototypes that call `callee`, in the order of their first
+    /// call.
+    pub fn callers(&self, callee: SymbolId) -> &[SymbolId] {
+        self.callers
+            .get(&callee)
+            .map_or(&[], |callers| &callers[..])
+    }
+
+    /// `id` and everything that calls it directly or indirectly, i.e. what changing it affects,
+    /// in declaration order.
+    pub fn affected_by(&self, id: SymbolId) -> Vec<SymbolId> {
+        let mut affected = HashSet::new();
+        let mut pending = vec![id];
+        while let Some(next) = pending.pop() {
+            if affected.insert(next) {
+                pending.extend_from_slice(self.callers(next));
+            }
+        }
+
+        let mut affected: Vec<SymbolId> = affected.into_iter().collect();
+        affected.sort_by_key(|id| id.0);
+        affected
+    }
+
+    /// The graph of only `nodes` and the calls between them.
+    pub fn restrict(&self, nodes: &[SymbolId]) -> CallGraph {
+        let nodes: HashSet<SymbolId> = nodes.iter().cloned().collect();
+        CallGraph::new(
+            self.nodes
+                .iter()
+                .filter(|id| nodes.contains(id))
+                .cloned()
+                .collect(),
+            self.edges
+                .iter()
+                .filter(|edge| nodes.contains(&edge.caller) && nodes.contains(&edge.callee))
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// The graph in Graphviz DOT, with each pair of caller and callee as one edge. Externals are
+    /// drawn as boxes, instances and prototypes as diamonds.
+    pub fn dot(&self, symbols: &SymbolCollection) -> String {
+        let name = |id: SymbolId| format!("{:?}", symbols.get(id).name.to_string());
+        let mut dot = String::from("digraph calls {\n");
+        for id in &self.nodes {
+            let shape = match node_kind(symbols, *id) {
+                "external" => " [shape=box]",
+                "instance" | "prototype" => " [shape=diamond]",
+                _ => "",
+            };
+            dot.push_str(&format!("    {}{};\n", name(*id), shape));
+        }
+        for &(caller, callee, _) in &self.calls {
+            dot.push_str(&format!("    {} -> {};\n", name(caller), name(callee)));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// The graph as JSON: a list of `nodes` with their name and kind, and a list of `edges`
+    /// with the names of caller and callee and the number of calls.
+    pub fn json(&self, symbols: &SymbolCollection) -> Value {
+        let nodes: Vec<Value> = self
+            .nodes
+            .iter()
+            .map(|id| {
+                json!({
+                    "name": symbols.get(*id).name.to_string(),
+                    "kind": node_kind(symbols, *id),
+                })
+            })
+            .collect();
+        let edges: Vec<Value> = self
+            .calls
+            .iter()
+            .map(|&(caller, callee, calls)| {
+                json!({
+                    "caller": symbols.get(caller).name.to_string(),
+                    "callee": symbols.get(callee).name.to_string(),
+                    "calls": calls,
+                })
+            })
+            .collect();
+        json!({"nodes": nodes, "edges": edges})
+    }
+}
+
+fn node_kind(symbols: &SymbolCollection, id: SymbolId) -> &'static str {
+    let symbol = symbols.get(id);
+    match symbol.kind {
+        SymbolKind::Instance => "instance",
+        SymbolKind::Prototype => "prototype",
+        _ if symbol.external => "external",
+        _ => "func",
+    }
+}
+
+/// How the command line tool writes call graphs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GraphFormat {
+    #[default]
+    Dot,
+    Json,
+}
+
+impl GraphFormat {
+    pub const VALUES: &'static [&'static str] = &["dot", "json"];
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            other => Err(format!("unknown graph format {}", other)),
+        }
+    }
+}
+
+struct CallGraphBuilder<'a> {
+    resolution: &'a Resolution,
+    edges: Vec<Edge>,
+}
+
+impl<'a> VisitorMut for CallGraphBuilder<'a> {
+    fn visit_call(&mut self, call: &Call, scope: Option<&Identifier>) {
+        let symbols = &self.resolution.symbols;
+        let caller = match scope.and_then(|scope| symbols.declaration(scope)) {
+            Some(caller) => caller,
+            None => return,
+        };
+        // Calls of undefined names or of anything but functions are reported elsewhere.
+        let callee = match self.resolution.symbol_at(call.func.span) {
+            Some(callee) if symbols.get(callee).kind == SymbolKind::Func => callee,
+            _ => return,
+        };
+
+        self.edges.push(Edge {
+            caller,
+            callee,
+            span: call.span,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::{parse_externals, parse_recovering};
+    use ppa::name_resolver::resolve_files;
+    use types::FileId;
+
+    const SCRIPTS: &str = "class C_NPC { var int id; };
+const int START = Hlp_Random(3);
+func void B_GiveInvItems(var int amount) { Hlp_Random(amount); };
+func void B_GiveGold() { B_GiveInvItems(100); B_GiveInvItems(START); };
+func int Unused() { return Hlp_Random(1); };
+prototype Npc_Default(C_NPC) { B_GiveGold(); };
+instance Hero(Npc_Default) { B_GiveInvItems(1); id = 2; };";
+
+    fn graph(test: impl FnOnce(&CallGraph, &SymbolCollection)) {
+        let (externals, _) = parse_externals(
+            b"func int Hlp_Random(var int bound); func void Print(var string text);",
+            FileId(0),
+        );
+        let (ast, errors) = parse_recovering(SCRIPTS.as_bytes(), FileId(1));
+        assert!(errors.is_empty(), "{:?}", errors);
+        let resolution = resolve_files(&externals, Some(&ast), |_| true);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+        test(
+            &CallGraph::build(Some(&ast), &resolution),
+            &resolution.symbols,
+        );
+    }
+
+    fn names(symbols: &SymbolCollection, ids: &[SymbolId]) -> Vec<String> {
+        ids.iter()
+            .map(|id| symbols.get(*id).name.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn callers_and_callees() {
+        graph(|graph, symbols| {
+            let id = |name: &[u8]| symbols.lookup_global(name).unwrap();
+
+            assert_eq!(
+                names(symbols, graph.nodes()),
+                vec![
+                    "Hlp_Random",
+                    "B_GiveInvItems",
+                    "B_GiveGold",
+                    "Unused",
+                    "Npc_Default",
+                    "Hero"
+                ]
+            );
+            assert_eq!(graph.edges().len(), 6);
+            assert_eq!(
+                names(symbols, graph.callees(id(b"B_GIVEGOLD"))),
+                vec!["B_GiveInvItems"]
+            );
+            assert_eq!(
+                names(symbols, graph.callers(id(b"B_GiveInvItems"))),
+                vec!["B_GiveGold", "Hero"]
+            );
+            assert_eq!(
+                names(symbols, &graph.affected_by(id(b"B_GiveInvItems"))),
+                vec!["B_GiveInvItems", "B_GiveGold", "Npc_Default", "Hero"]
+            );
+        });
+    }
+
+    #[test]
+    fn exports() {
+        graph(|graph, symbols| {
+            let affected = graph.affected_by(symbols.lookup_global(b"B_GiveGold").unwrap());
+            assert_eq!(
+                graph.restrict(&affected).dot(symbols),
+                "digraph calls {
+    \"B_GiveGold\";
+    \"Npc_Default\" [shape=diamond];
+    \"Npc_Default\" -> \"B_GiveGold\";
+}
+"
+            );
+
+            let json = graph.json(symbols);
+            assert_eq!(
+                json["nodes"][0],
+                json!({"name": "Hlp_Random", "kind": "external"})
+            );
+            assert_eq!(
+                json["edges"][1],
+                json!({"caller": "B_GiveGold", "callee": "B_GiveInvItems", "calls": 2})
+            );
+        });
+    }
+
+    #[test]
+    fn formats() {
+        for name in GraphFormat::VALUES {
+            assert!(name.parse::<GraphFormat>().is_ok());
+        }
+        assert!("svg".parse::<GraphFormat>().is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! Evaluation of constant expressions at compile time. Like in the engine, integers have 32 bits
+//! and wrap around on overflow.
+
+use inner_errors::ConstError;
+use ppa::name_resolver::Resolution;
+use ppa::visitor::{visit_ast, VisitorMut};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use types::{
+    ArraySizeDeclaration, ConstArrayDeclaration, ConstDeclaration, Expression, FileId, Identifier,
+    SymbolId, SymbolKind, Type, UnaryOperator, VarAccess, AST,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i32),
+    Float(f32),
+    String(Vec<u8>),
+    /// A function, instance, prototype or class, whose value is the symbol itself.
+    Symbol(SymbolId),
+}
+
+/// Initializers of all constants, keyed by the location of their name.
+#[derive(Default)]
+struct Constants {
+    values: HashMap<(FileId, usize), Expression>,
+    arrays: HashMap<(FileId, usize), Vec<Expression>>,
+}
+
+impl VisitorMut for Constants {
+    fn visit_const_decl(&mut self, decl: &ConstDeclaration, _scope: Option<&Identifier>) {
+        let key = (decl.name.span.file, decl.name.span.start);
+        self.values.insert(key, decl.initializer.clone());
+    }
+
+    fn visit_const_arr_decl(&mut self, decl: &ConstArrayDeclaration, _scope: Option<&Identifier>) {
+        let key = (decl.name.span.file, decl.name.span.start);
+        self.arrays
+            .insert(key, decl.initializer.expressions.clone());
+    }
+}
+
+/// Evaluates expressions, looking up the constants they refer to. Without a resolution,
+/// only literals and operators are evaluated.
+#[derive(Default)]
+pub struct Evaluator<'a> {
+    resolution: Option<&'a Resolution>,
+    constants: Constants,
+    /// Elements of constants that are being evaluated, to detect cycles.
+    active: RefCell<Vec<(SymbolId, usize)>>,
+    /// Values of the elements of constants evaluated so far.
+    values: RefCell<HashMap<(SymbolId, usize), Value>>,
+}
+
+impl<'a> Evaluator<'a> {
+    /// An evaluator for the constants declared in `asts`, whose names were resolved in
+    /// `resolution`.
+    pub fn new<'b, I: IntoIterator<Item = &'b AST>>(asts: I, resolution: &'a Resolution) -> Self {
+        let mut evaluator = Evaluator {
+            resolution: Some(resolution),
+            ..Evaluator::default()
+        };
+        for ast in asts {
+            visit_ast(ast, &mut evaluator.constants);
+        }
+        evaluator
+    }
+
+    /// The initializers of the constant `id`, one per element, or `None` if it is no constant.
+    pub fn initializers(&self, id: SymbolId) -> Option<&[Expression]> {
+        let symbol = self.resolution?.symbols.get(id);
+        let key = (symbol.name.span.file, symbol.name.span.start);
+        match symbol.kind {
+            SymbolKind::Const => self.constants.values.get(&key).map(::std::slice::from_ref),
+            SymbolKind::ConstArray => self.constants.arrays.get(&key).map(Vec::as_slice),
+            _ => None,
+        }
+    }
+
+    pub fn evaluate(&self, exp: &Expression) -> Result<Value, ConstError> {
+        match *exp {
+            Expression::Int(value, _) => Ok(Value::Int(value as i32)),
+            Expression::Float(value, _) => Ok(Value::Float(value)),
+            Expression::String(ref string) => Ok(Value::String(string.as_bytes().to_vec())),
+            Expression::Binary(ref bin) => {
+                let left = self.evaluate(&bin.left)?;
+                let right = self.evaluate(&bin.right)?;
+                match (left, right) {
+                    (Value::Int(left), Value::Int(right)) => bin
+                        .op
+                        .apply(left, right)
+                        .map(Value::Int)
+                        .ok_or(ConstError::DivisionByZero(bin.span)),
+                    _ => Err(ConstError::InvalidOperand(bin.span)),
+                }
+            }
+            Expression::Unary(ref un) => match (self.evaluate(&un.right)?, &un.op) {
+                (Value::Int(right), op) => Ok(Value::Int(op.apply(right))),
+                (Value::Float(right), &UnaryOperator::Plus) => Ok(Value::Float(right)),
+                (Value::Float(right), &UnaryOperator::Minus) => Ok(Value::Float(-right)),
+                _ => Err(ConstError::InvalidOperand(un.span)),
+            },
+            Expression::Identifier(ref var) => self.reference(var),
+            Expression::Call(ref call) => Err(ConstError::Call(call.span)),
+        }
+    }
+
+    pub fn int(&self, exp: &Expression) -> Result<i32, ConstError> {
+        match self.evaluate(exp)? {
+            Value::Int(value) => Ok(value),
+            _ => Err(mismatch(Type::Int, exp)),
+        }
+    }
+
+    /// Integers are converted, as the original compiler accepts them for floats.
+    pub fn float(&self, exp: &Expression) -> Result<f32, ConstError> {
+        match self.evaluate(exp)? {
+            Value::Float(value) => Ok(value),
+            Value::Int(value) => Ok(value as f32),
+            _ => Err(mismatch(Type::Float, exp)),
+        }
+    }
+
+    pub fn string(&self, exp: &Expression) -> Result<Vec<u8>, ConstError> {
+        match self.evaluate(exp)? {
+            Value::String(value) => Ok(value),
+            _ => Err(mismatch(Type::String, exp)),
+        }
+    }
+
+    /// The number of elements of an array, which is not checked to be positive.
+    pub fn array_size(&self, size: &ArraySizeDeclaration) -> Result<i32, ConstError> {
+        match *size {
+            ArraySizeDeclaration::Size(size, _) => Ok(size as i32),
+            ArraySizeDeclaration::Identifier(ref name) => match self.element(name, None)? {
+                Value::Int(size) => Ok(size),
+                _ => Err(ConstError::MismatchedType {
+                    expected: Type::Int,
+                    span: name.span,
+                }),
+            },
+        }
+    }
+
+    /// The symbol `name` refers to. Names in files that were not resolved in this run, because
+    /// they did not change, are looked up as globals.
+    fn symbol(&self, name: &Identifier) -> Option<SymbolId> {
+        let resolution = self.resolution?;
+        resolution
+            .symbol_at(name.span)
+            .or_else(|| resolution.symbols.lookup_global(name.as_bytes()))
+    }
+
+    fn reference(&self, var: &VarAccess) -> Result<Value, ConstError> {
+        if var.instance.is_some() {
+            return Err(ConstError::NotConstant(var.span));
+        }
+        self.element(&var.name, var.index.as_ref())
+    }
+
+    /// The value of the element `index` of what `name` refers to.
+    fn element(&self, name: &Identifier, index: Option<&Expression>) -> Result<Value, ConstError> {
+        let id = self
+            .symbol(name)
+            .ok_or(ConstError::NotConstant(name.span))?;
+        let initializers = match self.resolution.unwrap().symbols.get(id).kind {
+            SymbolKind::Func | SymbolKind::Instance | SymbolKind::Prototype | SymbolKind::Class => {
+                return Ok(Value::Symbol(id))
+            }
+            _ => self
+                .initializers(id)
+                .ok_or(ConstError::NotConstant(name.span))?,
+        };
+        let element = match index {
+            Some(index) => self.int(index)?,
+            None => 0,
+        };
+        let exp = match initializers.get(element as usize) {
+            Some(exp) if element >= 0 => exp,
+            _ => {
+                return Err(ConstError::IndexOutOfBounds {
+                    index: element,
+                    size: initializers.len(),
+                    span: index.map_or(name.span, Expression::span),
+                })
+            }
+        };
+
+        let key = (id, element as usize);
+        if let Some(value) = self.values.borrow().get(&key) {
+            return Ok(value.clone());
+        }
+        if self.active.borrow().contains(&key) {
+            return Err(ConstError::Cycle(name.clone()));
+        }
+        self.active.borrow_mut().push(key);
+        let value = self.evaluate(exp);
+        self.active.borrow_mut().pop();
+
+        let value = value?;
+        self.values.borrow_mut().insert(key, value.clone());
+        Ok(value)
+    }
+}
+
+fn mismatch(expected: Type, exp: &Expression) -> ConstError {
+    ConstError::MismatchedType {
+        expected,
+        span: exp.span(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use ppa::name_resolver::resolve;
+    use types::{Declaration, FileId, Span};
+
+    /// Evaluates the initializer of the last constant in `content`.
+    fn evaluate(content: &str) -> Result<Value, ConstError> {
+        let (ast, errors) = parse_recovering(content.as_bytes(), FileId(0));
+        assert!(errors.is_empty(), "{}: {:?}", content, errors);
+        let resolution = resolve(Some(&ast));
+        let evaluator = Evaluator::new(Some(&ast), &resolution);
+        match *ast.declarations.last().unwrap() {
+            Declaration::Const(ref decl) => evaluator.evaluate(&decl.initializer),
+            ref other => panic!("expected a constant, got {:?}", other),
+        }
+    }
+
+    fn int(value: i32) -> Result<Value, ConstError> {
+        Ok(Value::Int(value))
+    }
+
+    #[test]
+    fn references() {
+        assert_eq!(
+            evaluate("const int A = 2; const int ARR[2] = { A, A * 3 }; const int B = ARR[1] + A;"),
+            int(8)
+        );
+        assert_eq!(
+            evaluate("const float F = -1.5; const float G = -F;"),
+            Ok(Value::Float(1.5))
+        );
+        assert_eq!(
+            evaluate("const string S = \"x\"; const string T = S;"),
+            Ok(Value::String(b"x".to_vec()))
+        );
+        assert_eq!(
+            evaluate("func void foo() {}; const func F = foo;"),
+            Ok(Value::Symbol(SymbolId(0)))
+        );
+    }
+
+    #[test]
+    fn wraps_around() {
+        assert_eq!(evaluate("const int A = 2147483647 + 1;"), int(i32::MIN));
+        assert_eq!(evaluate("const int A = 65536 * 65536;"), int(0));
+        assert_eq!(evaluate("const int A = 1 << 33;"), int(2));
+        assert_eq!(evaluate("const int A = -2147483648 / -1;"), int(i32::MIN));
+        assert_eq!(evaluate("const int A = 4294967297;"), int(1));
+    }
+
+    #[test]
+    fn errors() {
+        let code = |content: &str| evaluate(content).unwrap_err().code();
+        assert_eq!(
+            code("const int Z = 2 - 2; const int A = 1 / Z;"),
+            "division-by-zero"
+        );
+        assert_eq!(code("const int A = 5 % 0;"), "division-by-zero");
+        assert_eq!(
+            code("func int f() { return 1; }; const int A = f();"),
+            "non-constant-call"
+        );
+        assert_eq!(code("var int v; const int A = v;"), "not-constant");
+        assert_eq!(
+            code("const string S = \"x\"; const int A = S + 1;"),
+            "invalid-operand"
+        );
+        assert_eq!(
+            code("const int ARR[2] = { 1, 2 }; const int A = ARR[2];"),
+            "index-out-of-bounds"
+        );
+
+        let cycle = evaluate("const int A = B + 1; const int B = A * 2; const int C = A;");
+        match cycle {
+            Err(ConstError::Cycle(ref name)) => {
+                assert_eq!(name.as_bytes(), b"A");
+                assert!(name.span.same_location(&Span::new(FileId(0), 35, 36)));
+            }
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn without_symbols() {
+        let exp = Expression::Int(7, Span::default());
+        assert_eq!(Evaluator::default().int(&exp), Ok(7));
+        let string = Expression::String(::types::StringLiteral::new(b"x"));
+        assert_eq!(
+            Evaluator::default().int(&string).unwrap_err().code(),
+            "constant-type-mismatch"
+        );
+    }
+}
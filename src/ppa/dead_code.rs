@@ -0,0 +1,156 @@
+//! Functions, constants and instances that nothing uses.
+
+use diagnostics::{Diagnostic, Severity};
+use ppa::inheritance::chain;
+use ppa::name_resolver::Resolution;
+use std::collections::HashSet;
+use types::{charset, Symbol, SymbolCollection, SymbolId, SymbolKind};
+
+/// Prefixes of the functions the engine calls by name: `Startup_*` and `Init_*` when a world is
+/// loaded, and the AI states `ZS_*` along with their `_Loop` and `_End` functions.
+const ENTRY_POINTS: [&[u8]; 3] = [b"STARTUP_", b"INIT_", b"ZS_"];
+
+/// Whether the engine uses the symbol `id` without any script referring to it: entry points,
+/// and dialogs, i.e. instances of `C_Info`.
+pub fn is_entry_point(symbols: &SymbolCollection, id: SymbolId) -> bool {
+    let symbol = symbols.get(id);
+    let name = symbol.name.as_bytes();
+    match symbol.kind {
+        SymbolKind::Func => ENTRY_POINTS.iter().any(|prefix| {
+            name.len() >= prefix.len() && charset().eq_ignore_case(&name[..prefix.len()], prefix)
+        }),
+        SymbolKind::Instance => chain(symbols, id).is_some_and(|chain| {
+            charset().eq_ignore_case(symbols.get(chain.class).name.as_bytes(), b"C_INFO")
+        }),
+        _ => false,
+    }
+}
+
+/// The global functions, constants and instances of the scripts that are not entry points and
+/// are never referenced, except from their own body, in declaration order. Functions count as
+/// used when they are called or assigned, like the `condition` of a dialog.
+pub fn unused(resolution: &Resolution) -> Vec<SymbolId> {
+    let symbols = &resolution.symbols;
+    let used: HashSet<SymbolId> = resolution
+        .references
+        .iter()
+        .filter(|reference| {
+            let span = symbols.get(reference.symbol).span;
+            span.file != reference.span.file || !span.contains(reference.span.start)
+        })
+        .map(|reference| reference.symbol)
+        .collect();
+
+    symbols
+        .iter()
+        .filter(|(id, symbol)| {
+            let checked = match symbol.kind {
+                SymbolKind::Func => !symbol.external,
+                SymbolKind::Const | SymbolKind::ConstArray | SymbolKind::Instance => true,
+                _ => false,
+            };
+            checked && symbol.is_global() && !used.contains(id) && !is_entry_point(symbols, *id)
+        })
+        .map(|(id, _)| id)
+        .collect()
+}
+
+/// The code of warnings about an unused symbol of `kind`.
+pub fn code(kind: SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Func => "unused-function",
+        SymbolKind::Instance => "unused-instance",
+        _ => "unused-constant",
+    }
+}
+
+pub fn message(symbol: &Symbol) -> String {
+    let what = match symbol.kind {
+        SymbolKind::Func => "Function",
+        SymbolKind::Instance => "Instance",
+        _ => "Constant",
+    };
+    format!("{} is never used: '{}'", what, symbol.name)
+}
+
+/// Warnings for all `unused` symbols.
+pub fn check(resolution: &Resolution) -> Vec<Diagnostic> {
+    unused(resolution)
+        .into_iter()
+        .map(|id| {
+            let symbol = resolution.symbols.get(id);
+            Diagnostic {
+                severity: Severity::Warning,
+                code: code(symbol.kind),
+                message: message(symbol),
+                span: Some(symbol.name.span),
+                related: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::{parse_externals, parse_recovering};
+    use ppa::name_resolver::resolve_files;
+    use types::FileId;
+
+    const SCRIPTS: &str = "const int ATR_INDEX_MAX = 8;
+class C_NPC { var int id; var int attribute[ATR_INDEX_MAX]; };
+class C_Info { var int npc; var func condition; var func information; };
+const int MAX = 2;
+const int UNUSED_CONST = 3;
+const int USED_IN_CONST = 4;
+const int DERIVED = USED_IN_CONST + 1;
+var int counter[MAX];
+func int Recursive(var int n) { return Recursive(n - 1); };
+func void Helper() { Hlp_Random(DERIVED); };
+func void Unused() { Helper(); };
+func void Startup_World() {};
+func void INIT_World() {};
+func void ZS_Talk() {};
+func void ZS_Talk_Loop() {};
+func int DIA_Hero_Condition() { return 1; };
+func void DIA_Hero_Info() {};
+prototype Npc_Default(C_NPC) {};
+instance Hero(Npc_Default) {};
+instance Mud(Npc_Default) {};
+instance DIA_Hero(C_Info) { npc = Hero; condition = DIA_Hero_Condition; information = DIA_Hero_Info; };";
+
+    #[test]
+    fn unused_symbols() {
+        let (externals, _) = parse_externals(b"func int Hlp_Random(var int bound);", FileId(0));
+        let (ast, errors) = parse_recovering(SCRIPTS.as_bytes(), FileId(1));
+        assert!(errors.is_empty(), "{:?}", errors);
+        let resolution = resolve_files(&externals, Some(&ast), |_| true);
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+        let messages: Vec<(&str, String)> = check(&resolution)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.code, diagnostic.message))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "unused-constant",
+                    "Constant is never used: 'UNUSED_CONST'".to_owned()
+                ),
+                (
+                    "unused-function",
+                    "Function is never used: 'Recursive'".to_owned()
+                ),
+                (
+                    "unused-function",
+                    "Function is never used: 'Unused'".to_owned()
+                ),
+                (
+                    "unused-instance",
+                    "Instance is never used: 'Mud'".to_owned()
+                ),
+            ]
+        );
+    }
+}
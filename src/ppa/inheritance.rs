@@ -0,0 +1,221 @@
+use errors::Error;
+use inner_errors::LinkerError;
+use ppa::name_resolver::Resolution;
+use ppa::visitor::{visit_ast, VisitorMut};
+use types::{Identifier, Statement, SymbolCollection, SymbolId, SymbolKind, AST};
+
+/// What an instance or prototype derives from. Instances derive from a class, either directly
+/// or through a prototype, prototypes only directly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Chain {
+    pub prototype: Option<SymbolId>,
+    pub class: SymbolId,
+}
+
+/// The inheritance chain of the instance or prototype `id`, if it is valid.
+pub fn chain(symbols: &SymbolCollection, id: SymbolId) -> Option<Chain> {
+    let symbol = symbols.get(id);
+    let parent = symbols.lookup_global(symbol.typ.as_ref()?.as_bytes())?;
+    match (symbol.kind, symbols.get(parent).kind) {
+        (SymbolKind::Instance, SymbolKind::Class) | (SymbolKind::Prototype, SymbolKind::Class) => {
+            Some(Chain {
+                prototype: None,
+                class: parent,
+            })
+        }
+        (SymbolKind::Instance, SymbolKind::Prototype) => {
+            chain(symbols, parent).map(|chain| Chain {
+                prototype: Some(parent),
+                class: chain.class,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Checks the parents of all instances and prototypes in `asts`, and that their bodies only
+/// assign to members of their class. Undefined parents are reported by the name resolver.
+pub fn check<'a, I: IntoIterator<Item = &'a AST>>(asts: I, resolution: &Resolution) -> Vec<Error> {
+    let mut checker = InheritanceChecker {
+        resolution,
+        current: None,
+        errors: Vec::new(),
+    };
+    for ast in asts {
+        visit_ast(ast, &mut checker);
+    }
+
+    checker.errors
+}
+
+struct InheritanceChecker<'a> {
+    resolution: &'a Resolution,
+    /// The instance or prototype being visited and its class, if its chain is valid.
+    current: Option<(SymbolId, SymbolId)>,
+    errors: Vec<Error>,
+}
+
+impl<'a> InheritanceChecker<'a> {
+    fn enter(&mut self, name: &Identifier, parent: &Identifier) {
+        let symbols = &self.resolution.symbols;
+        self.current = None;
+        let id = match symbols.declaration(name) {
+            Some(id) => id,
+            None => return,
+        };
+        let parent_kind = match self.resolution.symbol_at(parent.span) {
+            Some(parent) => symbols.get(parent).kind,
+            None => return,
+        };
+
+        match (symbols.get(id).kind, parent_kind) {
+            (_, SymbolKind::Class) | (SymbolKind::Instance, SymbolKind::Prototype) => {}
+            (SymbolKind::Prototype, SymbolKind::Prototype) => {
+                self.errors
+                    .push(Error::LinkingError(LinkerError::PrototypeParent(
+                        parent.clone(),
+                    )))
+            }
+            _ => self
+                .errors
+                .push(Error::LinkingError(LinkerError::InvalidParent(
+                    parent.clone(),
+                ))),
+        }
+        self.current = chain(symbols, id).map(|chain| (id, chain.class));
+    }
+}
+
+impl<'a> VisitorMut for InheritanceChecker<'a> {
+    fn visit_func_decl(&mut self, _decl: &::types::Function) {
+        self.current = None;
+    }
+
+    fn visit_class_decl(&mut self, _decl: &::types::Class) {
+        self.current = None;
+    }
+
+    fn visit_inst_decl(&mut self, decl: &::types::Instance) {
+        self.enter(&decl.name, &decl.class);
+    }
+
+    fn visit_proto_decl(&mut self, decl: &::types::Prototype) {
+        self.enter(&decl.name, &decl.class);
+    }
+
+    fn visit_statement(&mut self, statement: &Statement, _scope: &Identifier) {
+        let (current, class) = match self.current {
+            Some(current) => current,
+            None => return,
+        };
+        let var = match *statement {
+            Statement::Ass(ref ass) if ass.var.instance.is_none() => &ass.var,
+            _ => return,
+        };
+        // Undefined names were reported by the name resolver already.
+        let symbol = match self.resolution.symbol_at(var.name.span) {
+            Some(id) => self.resolution.symbols.get(id),
+            None => return,
+        };
+
+        if symbol.scope != Some(class) && symbol.scope != Some(current) {
+            self.errors
+                .push(Error::LinkingError(LinkerError::UndefinedMember {
+                    instance: self.resolution.symbols.get(class).name.clone(),
+                    member: var.name.clone(),
+                }));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use ppa::name_resolver::resolve;
+    use types::FileId;
+
+    const CLASSES: &str = "class C_NPC { var int id; var string name[5]; };
+class C_ITEM { var int value; };
+var int global;
+func void f() {};
+";
+
+    fn check_errors(content: &str) -> Vec<String> {
+        let content = format!("{}{}", CLASSES, content);
+        let (ast, errors) = parse_recovering(content.as_bytes(), FileId(0));
+        assert!(errors.is_empty(), "{:?}", errors);
+        let resolution = resolve(Some(&ast));
+
+        check(Some(&ast), &resolution)
+            .iter()
+            .map(|err| match *err {
+                Error::LinkingError(ref err) => err.to_string(),
+                ref other => panic!("unexpected error {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chains() {
+        let content = format!(
+            "{}prototype Npc_Default(C_NPC) {{}}; instance Hero(Npc_Default) {{}}; instance Sword(C_ITEM) {{}};",
+            CLASSES
+        );
+        let (ast, _) = parse_recovering(content.as_bytes(), FileId(0));
+        let resolution = resolve(Some(&ast));
+        let symbols = &resolution.symbols;
+        let id = |name: &[u8]| symbols.lookup_global(name).unwrap();
+
+        assert_eq!(
+            chain(symbols, id(b"Hero")),
+            Some(Chain {
+                prototype: Some(id(b"Npc_Default")),
+                class: id(b"C_NPC"),
+            })
+        );
+        assert_eq!(
+            chain(symbols, id(b"SWORD")),
+            Some(Chain {
+                prototype: None,
+                class: id(b"C_ITEM"),
+            })
+        );
+        assert_eq!(chain(symbols, id(b"f")), None);
+    }
+
+    #[test]
+    fn members() {
+        let errors = check_errors(
+            "prototype Npc_Default(C_NPC) { id = 1; };
+instance Hero(Npc_Default) { var int local; local = 2; NAME[1] = \"Hero\"; if (id) { global = 3; }; };
+instance Sword(C_ITEM) { value = 5; global = 1; Hero.id = 2; };",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "Undefined member 'C_NPC.global'",
+                "Undefined member 'C_ITEM.global'",
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_parents() {
+        let errors = check_errors(
+            "prototype Npc_Default(C_NPC) {};
+prototype Npc_Nested(Npc_Default) { id = 1; };
+instance Hero(Npc_Nested) { id = 2; };
+instance Bad(f) { id = 3; };
+instance Worse(Hero) {};",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "A prototype can only derive from a class: 'Npc_Default'",
+                "Not a class or prototype: 'f'",
+                "Not a class or prototype: 'Hero'",
+            ]
+        );
+    }
+}
@@ -0,0 +1,296 @@
+//! Memory layout of classes: where each member is stored in the engine object an instance of
+//! the class belongs to.
+
+use errors::Error;
+use inner_errors::TypeError;
+use ppa::constant::Evaluator;
+use std::str::FromStr;
+use types::{charset, SymbolCollection, SymbolId, SymbolKind, Type};
+
+/// Size of a `zSTRING` in engine objects.
+pub const STRING_SIZE: u32 = 20;
+
+/// The engine version scripts are compiled for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Target {
+    Gothic1,
+    #[default]
+    Gothic2,
+}
+
+impl Target {
+    const ALL: [Target; 2] = [Target::Gothic1, Target::Gothic2];
+    pub const VALUES: &'static [&'static str] = &["g1", "g2"];
+
+    pub fn name(self) -> &'static str {
+        Target::VALUES[Target::ALL.iter().position(|t| *t == self).unwrap()]
+    }
+
+    /// Where the members of the class `name` start in its engine objects. The classes of
+    /// characters and items describe vobs, whose own data comes first and is bigger in Gothic 2.
+    pub fn class_offset(self, name: &[u8]) -> u32 {
+        let vob_size = match self {
+            Target::Gothic1 => 0x100,
+            Target::Gothic2 => 0x120,
+        };
+        let vobs: [&[u8]; 2] = [b"C_NPC", b"C_ITEM"];
+        if vobs
+            .iter()
+            .any(|class| charset().eq_ignore_case(class, name))
+        {
+            vob_size
+        } else {
+            0
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Target::VALUES
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(s))
+        {
+            Some(index) => Ok(Target::ALL[index]),
+            None => Err(format!("unknown target {}", s)),
+        }
+    }
+}
+
+/// A member of a class.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Field {
+    pub symbol: SymbolId,
+    /// Offset from the start of the members, not of the engine object.
+    pub offset: u32,
+    /// Size of one element.
+    pub size: u32,
+    pub count: u32,
+}
+
+impl Field {
+    /// Size of all elements, if it fits in a `u32`.
+    pub fn total_size(&self) -> Option<u32> {
+        self.size.checked_mul(self.count)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClassLayout {
+    pub class: SymbolId,
+    /// Offset of the first member in engine objects.
+    pub offset: u32,
+    /// Size of all members.
+    pub size: u32,
+    pub fields: Vec<Field>,
+}
+
+impl ClassLayout {
+    pub fn field(&self, member: SymbolId) -> Option<&Field> {
+        self.fields.iter().find(|field| field.symbol == member)
+    }
+}
+
+/// Size of one element of a variable of type `typ`. Instances and functions are stored as
+/// pointers or symbol indices.
+pub fn type_size(typ: &Type) -> u32 {
+    match *typ {
+        Type::String => STRING_SIZE,
+        _ => 4,
+    }
+}
+
+/// The layouts of all classes, in declaration order, and an error for each class whose
+/// members do not fit in engine objects. Members whose array size is invalid, which the array
+/// checker reports, count as one element.
+pub fn layout(
+    symbols: &SymbolCollection,
+    evaluator: &Evaluator,
+    target: Target,
+) -> (Vec<ClassLayout>, Vec<Error>) {
+    let mut errors = Vec::new();
+    let layouts = symbols
+        .iter()
+        .filter(|(_, symbol)| symbol.kind == SymbolKind::Class)
+        .map(|(id, class)| {
+            let mut size = Some(0u32);
+            let fields = symbols
+                .children(id)
+                .iter()
+                .map(|member| {
+                    let symbol = symbols.get(*member);
+                    let count = match symbol.array_size {
+                        Some(ref array_size) => match evaluator.array_size(array_size) {
+                            Ok(count) if count > 0 => count as u32,
+                            _ => 1,
+                        },
+                        None => 1,
+                    };
+                    // Members after an overflow get the last offset, they are never used.
+                    let field = Field {
+                        symbol: *member,
+                        offset: size.unwrap_or(u32::MAX),
+                        size: type_size(&symbols.type_of(symbol)),
+                        count,
+                    };
+                    size = size.and_then(|size| {
+                        field.total_size().and_then(|total| size.checked_add(total))
+                    });
+                    field
+                })
+                .collect();
+
+            if size.is_none() {
+                errors.push(Error::TypeCheckError(TypeError::ClassTooLarge(
+                    class.name.span,
+                )));
+            }
+            ClassLayout {
+                class: id,
+                offset: target.class_offset(class.name.as_bytes()),
+                size: size.unwrap_or(u32::MAX),
+                fields,
+            }
+        })
+        .collect();
+    (layouts, errors)
+}
+
+/// The `layouts` as a text table, with one line per class followed by one per member.
+pub fn table(layouts: &[ClassLayout], symbols: &SymbolCollection) -> String {
+    let mut rows = vec![[
+        "NAME".to_owned(),
+        "OFFSET".to_owned(),
+        "SIZE".to_owned(),
+        "COUNT".to_owned(),
+    ]];
+    for layout in layouts {
+        let class = &symbols.get(layout.class).name;
+        rows.push([
+            class.to_string(),
+            layout.offset.to_string(),
+            layout.size.to_string(),
+            String::new(),
+        ]);
+        for field in &layout.fields {
+            rows.push([
+                format!("{}.{}", class, symbols.get(field.symbol).name),
+                field.offset.to_string(),
+                field.total_size().unwrap_or(u32::MAX).to_string(),
+                field.count.to_string(),
+            ]);
+        }
+    }
+
+    let width = rows
+        .iter()
+        .map(|row| row[0].chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut table = String::new();
+    for row in rows {
+        let line = format!(
+            "{:width$}  {:>6}  {:>6}  {:>5}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            width = width
+        );
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use ppa::name_resolver::resolve;
+    use types::FileId;
+
+    const CLASSES: &str = "const int MAX = 3;
+class C_NPC { var int id; var string name[2]; var func daily_routine; var float f[MAX]; };
+class C_Info { var int npc; var string description; };";
+
+    #[test]
+    fn offsets() {
+        let (ast, _) = parse_recovering(CLASSES.as_bytes(), FileId(0));
+        let resolution = resolve(Some(&ast));
+        let evaluator = Evaluator::new(Some(&ast), &resolution);
+        let symbols = &resolution.symbols;
+
+        let (layouts, errors) = layout(symbols, &evaluator, Target::Gothic2);
+        assert!(errors.is_empty());
+        let npc = &layouts[0];
+        assert_eq!((npc.offset, npc.size), (0x120, 60));
+        let fields: Vec<(u32, u32, u32)> = npc
+            .fields
+            .iter()
+            .map(|field| (field.offset, field.size, field.count))
+            .collect();
+        assert_eq!(fields, vec![(0, 4, 1), (4, 20, 2), (44, 4, 1), (48, 4, 3)]);
+        let routine = symbols.lookup_scoped(npc.class, b"DAILY_ROUTINE").unwrap();
+        assert_eq!(npc.field(routine).map(|field| field.offset), Some(44));
+
+        assert_eq!((layouts[1].offset, layouts[1].size), (0, 24));
+        assert_eq!(
+            layout(symbols, &evaluator, Target::Gothic1).0[0].offset,
+            0x100
+        );
+    }
+
+    #[test]
+    fn table_rows() {
+        let (ast, _) = parse_recovering(CLASSES.as_bytes(), FileId(0));
+        let resolution = resolve(Some(&ast));
+        let evaluator = Evaluator::new(Some(&ast), &resolution);
+        let (layouts, _) = layout(&resolution.symbols, &evaluator, Target::Gothic2);
+
+        let table = table(&layouts[1..], &resolution.symbols);
+        assert_eq!(
+            table,
+            "NAME                OFFSET    SIZE  COUNT
+C_Info                   0      24
+C_Info.npc               0       4      1
+C_Info.description       4      20      1
+"
+        );
+    }
+
+    #[test]
+    fn oversized_classes() {
+        let (ast, _) = parse_recovering(
+            b"class C { var string s[300000000]; };
+class D { var int a[536870912]; var int b[536870912]; var int c; };
+class E { var int id; };",
+            FileId(0),
+        );
+        let resolution = resolve(Some(&ast));
+        let evaluator = Evaluator::new(Some(&ast), &resolution);
+
+        let (layouts, errors) = layout(&resolution.symbols, &evaluator, Target::Gothic2);
+        let codes: Vec<&str> = errors
+            .iter()
+            .map(|err| match *err {
+                Error::TypeCheckError(ref err) => err.code(),
+                _ => "",
+            })
+            .collect();
+        assert_eq!(codes, vec!["class-too-large", "class-too-large"]);
+        assert_eq!(layouts[1].fields[2].offset, u32::MAX);
+        assert_eq!(layouts[2].size, 4);
+    }
+
+    #[test]
+    fn targets() {
+        for name in Target::VALUES {
+            assert_eq!(name.parse::<Target>().unwrap().name(), *name);
+        }
+        assert_eq!("G1".parse(), Ok(Target::Gothic1));
+        assert!("g3".parse::<Target>().is_err());
+    }
+}
@@ -1,2 +1,10 @@
+pub mod array_checker;
+pub mod call_graph;
+pub mod constant;
+pub mod dead_code;
+pub mod inheritance;
+pub mod layout;
+pub mod name_resolver;
 pub mod symbol_collector;
+pub mod type_checker;
 pub mod visitor;
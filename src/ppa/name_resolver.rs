@@ -0,0 +1,396 @@
+use errors::Error;
+use inner_errors::LinkerError;
+use ppa::symbol_collector::SymbolCollector;
+use ppa::visitor::{visit_ast, VisitorMut};
+use std::collections::HashMap;
+use types;
+use types::{FileId, Span, SymbolCollection, SymbolId, SymbolKind, AST};
+
+/// A use of a symbol, located at `span`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Reference {
+    pub span: Span,
+    pub symbol: SymbolId,
+}
+
+#[derive(Debug)]
+pub struct Resolution {
+    pub symbols: SymbolCollection,
+    pub references: Vec<Reference>,
+    pub errors: Vec<Error>,
+    by_location: HashMap<(FileId, usize), SymbolId>,
+}
+
+impl Resolution {
+    /// The symbol that the name starting at `span` refers to.
+    pub fn symbol_at(&self, span: Span) -> Option<SymbolId> {
+        self.by_location.get(&(span.file, span.start)).cloned()
+    }
+}
+
+/// Builds the symbol table of all `asts`, which must be given in `.src` order,
+/// and resolves every name used in them.
+pub fn resolve<'a, I>(asts: I) -> Resolution
+where
+    I: IntoIterator<Item = &'a AST>,
+    I::IntoIter: Clone,
+{
+    resolve_files(&[], asts, |_| true)
+}
+
+/// Like `resolve`, but declares the engine functions in `externals` first, and only resolves
+/// the names used in the ASTs whose index satisfies `dirty`, e.g. because the others were
+/// resolved in an earlier run. Symbols are still collected from all `asts`.
+pub fn resolve_files<'a, I, F>(externals: &[types::Function], asts: I, dirty: F) -> Resolution
+where
+    I: IntoIterator<Item = &'a AST>,
+    I::IntoIter: Clone,
+    F: Fn(usize) -> bool,
+{
+    let asts = asts.into_iter();
+
+    let mut collector = SymbolCollector::new();
+    collector.declare_externals(externals);
+    for ast in asts.clone() {
+        visit_ast(ast, &mut collector);
+    }
+    let (symbols, mut errors) = collector.finish();
+
+    let references = {
+        let mut resolver = NameResolver::new(&symbols);
+        for (_, ast) in asts.enumerate().filter(|(i, _)| dirty(*i)) {
+            visit_ast(ast, &mut resolver);
+        }
+        errors.extend(resolver.errors);
+        resolver.references
+    };
+
+    let by_location = references
+        .iter()
+        .map(|reference| {
+            (
+                (reference.span.file, reference.span.start),
+                reference.symbol,
+            )
+        })
+        .collect();
+
+    Resolution {
+        symbols,
+        references,
+        errors,
+        by_location,
+    }
+}
+
+/// Resolves variable accesses, calls and the parents of instances and prototypes.
+///
+/// Inside a function, its parameters and locals shadow globals. Inside an instance or prototype,
+/// the members of its class do as well.
+pub struct NameResolver<'a> {
+    symbols: &'a SymbolCollection,
+    scope: Option<SymbolId>,
+    references: Vec<Reference>,
+    errors: Vec<Error>,
+}
+
+impl<'a> NameResolver<'a> {
+    pub fn new(symbols: &'a SymbolCollection) -> Self {
+        NameResolver {
+            symbols,
+            scope: None,
+            references: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    fn lookup(&self, name: &types::Identifier) -> Option<SymbolId> {
+        let name = name.as_bytes();
+
+        if let Some(scope) = self.scope {
+            if let Some(id) = self.symbols.lookup_scoped(scope, name) {
+                return Some(id);
+            }
+
+            let scope = self.symbols.get(scope);
+            if scope.kind == SymbolKind::Instance || scope.kind == SymbolKind::Prototype {
+                let member = scope
+                    .typ
+                    .as_ref()
+                    .and_then(|typ| self.symbols.class_of(typ.as_bytes()))
+                    .and_then(|class| self.symbols.lookup_scoped(class, name));
+                if member.is_some() {
+                    return member;
+                }
+            }
+        }
+
+        self.symbols.lookup_global(name)
+    }
+
+    fn reference(
+        &mut self,
+        name: &types::Identifier,
+        symbol: Option<SymbolId>,
+    ) -> Option<SymbolId> {
+        match symbol {
+            Some(symbol) => self.references.push(Reference {
+                span: name.span,
+                symbol,
+            }),
+            None => self
+                .errors
+                .push(Error::LinkingError(LinkerError::UndefinedSymbol(
+                    name.clone(),
+                ))),
+        }
+
+        symbol
+    }
+
+    fn enter(&mut self, name: &types::Identifier) {
+        self.scope = self.symbols.declaration(name);
+    }
+
+    /// Bodies of duplicate declarations are skipped, their names would resolve to the wrong scope.
+    fn skipped(&self, scope: Option<&types::Identifier>) -> bool {
+        scope.is_some() && self.scope.is_none()
+    }
+
+    fn array_size(
+        &mut self,
+        size: Option<&types::ArraySizeDeclaration>,
+        scope: Option<&types::Identifier>,
+    ) {
+        if let Some(types::ArraySizeDeclaration::Identifier(ref name)) = size {
+            if !self.skipped(scope) {
+                let symbol = self.lookup(name);
+                self.reference(name, symbol);
+            }
+        }
+    }
+}
+
+impl<'a> VisitorMut for NameResolver<'a> {
+    fn visit_var_access(&mut self, access: &types::VarAccess, scope: Option<&types::Identifier>) {
+        if self.skipped(scope) {
+            return;
+        }
+
+        let instance = match access.instance {
+            None => {
+                let symbol = self.lookup(&access.name);
+                self.reference(&access.name, symbol);
+                return;
+            }
+            Some(ref instance) => instance,
+        };
+
+        let symbol = self.lookup(instance);
+        let instance_symbol = match self.reference(instance, symbol) {
+            Some(symbol) => self.symbols.get(symbol),
+            None => return,
+        };
+
+        let class = match instance_symbol.kind {
+            SymbolKind::Class => symbol,
+            _ => instance_symbol
+                .typ
+                .as_ref()
+                .and_then(|typ| self.symbols.class_of(typ.as_bytes())),
+        };
+
+        match class.and_then(|class| self.symbols.lookup_scoped(class, access.name.as_bytes())) {
+            Some(member) => self.references.push(Reference {
+                span: access.name.span,
+                symbol: member,
+            }),
+            None => self
+                .errors
+                .push(Error::LinkingError(LinkerError::UndefinedMember {
+                    instance: instance.clone(),
+                    member: access.name.clone(),
+                })),
+        }
+    }
+
+    fn visit_call(&mut self, call: &types::Call, scope: Option<&types::Identifier>) {
+        if self.skipped(scope) {
+            return;
+        }
+
+        let symbol = self.lookup(&call.func);
+        self.reference(&call.func, symbol);
+    }
+
+    fn visit_var_decl(&mut self, decl: &types::VarDeclaration, scope: Option<&types::Identifier>) {
+        self.array_size(decl.array_size.as_ref(), scope);
+    }
+
+    fn visit_const_arr_decl(
+        &mut self,
+        decl: &types::ConstArrayDeclaration,
+        scope: Option<&types::Identifier>,
+    ) {
+        self.array_size(Some(&decl.array_size), scope);
+    }
+
+    fn visit_func_decl(&mut self, decl: &types::Function) {
+        self.enter(&decl.name);
+        for param in &decl.params {
+            self.array_size(param.array_size.as_ref(), Some(&decl.name));
+        }
+    }
+
+    fn visit_class_decl(&mut self, decl: &types::Class) {
+        self.enter(&decl.name);
+    }
+
+    fn visit_inst_decl(&mut self, decl: &types::Instance) {
+        let class = self.symbols.lookup_global(decl.class.as_bytes());
+        self.reference(&decl.class, class);
+        self.enter(&decl.name);
+    }
+
+    fn visit_proto_decl(&mut self, decl: &types::Prototype) {
+        let class = self.symbols.lookup_global(decl.class.as_bytes());
+        self.reference(&decl.class, class);
+        self.enter(&decl.name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use types::FileId;
+
+    fn parse(files: &[&str]) -> Vec<AST> {
+        files
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let (ast, errors) = parse_recovering(content.as_bytes(), FileId(i));
+                assert!(errors.is_empty());
+                ast
+            })
+            .collect()
+    }
+
+    fn referenced(resolution: &Resolution, file: usize, text: &str, needle: &str) -> String {
+        let start = text.find(needle).unwrap();
+        let reference = resolution
+            .references
+            .iter()
+            .find(|r| r.span.file == FileId(file) && r.span.start == start)
+            .expect("no reference at this position");
+        let symbol = resolution.symbols.get(reference.symbol);
+
+        match symbol.scope {
+            Some(scope) => format!("{}.{}", resolution.symbols.get(scope).name, symbol.name),
+            None => symbol.name.to_string(),
+        }
+    }
+
+    fn linker_errors(resolution: &Resolution) -> Vec<LinkerError> {
+        resolution
+            .errors
+            .iter()
+            .map(|err| match *err {
+                Error::LinkingError(ref err) => err.clone(),
+                _ => panic!("unexpected error"),
+            })
+            .collect()
+    }
+
+    const CLASSES: &str = "class C_NPC { var int id; var string name[5]; };
+prototype Npc_Default(C_NPC) { id = 1; };
+var C_NPC self;
+const int MAX = 3;";
+
+    #[test]
+    fn resolves_across_files() {
+        let story = "instance Hero(NPC_DEFAULT) { name[0] = \"Hero\"; b_init(self); };
+func void B_Init(var c_npc slf) { var int id; id = slf.ID + Max; self.name = \"x\"; };";
+        let asts = parse(&[CLASSES, story]);
+
+        let resolution = resolve(&asts);
+
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+        assert_eq!(referenced(&resolution, 0, CLASSES, "C_NPC)"), "C_NPC");
+        assert_eq!(
+            referenced(&resolution, 1, story, "NPC_DEFAULT"),
+            "Npc_Default"
+        );
+        assert_eq!(referenced(&resolution, 1, story, "name[0]"), "C_NPC.name");
+        assert_eq!(referenced(&resolution, 1, story, "b_init"), "B_Init");
+        assert_eq!(referenced(&resolution, 1, story, "self)"), "self");
+        assert_eq!(referenced(&resolution, 1, story, "id ="), "B_Init.id");
+        assert_eq!(referenced(&resolution, 1, story, "slf.ID"), "B_Init.slf");
+        assert_eq!(referenced(&resolution, 1, story, "ID +"), "C_NPC.id");
+        assert_eq!(referenced(&resolution, 1, story, "Max"), "MAX");
+        assert_eq!(referenced(&resolution, 1, story, "name ="), "C_NPC.name");
+    }
+
+    #[test]
+    fn reports_undefined_names() {
+        let story = "instance Hero(C_Monster) { foo = 1; };
+func void bar() { baz(); self.level = MAX; };";
+        let asts = parse(&[CLASSES, story]);
+
+        let resolution = resolve(&asts);
+
+        let errors = linker_errors(&resolution);
+        let names: Vec<String> = errors.iter().map(|err| format!("{}", err)).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Undefined symbol 'C_Monster'",
+                "Undefined symbol 'foo'",
+                "Undefined symbol 'baz'",
+                "Undefined member 'self.level'",
+            ]
+        );
+        assert!(errors[2].span().same_location(&Span::new(
+            FileId(1),
+            story.find("baz").unwrap(),
+            story.find("baz").unwrap() + 3
+        )));
+    }
+
+    #[test]
+    fn resolves_member_array_sizes() {
+        let items = "class C_ITEM { var int count[MAX]; var string text[TEXT_MAX]; };";
+        let asts = parse(&[CLASSES, items]);
+
+        let resolution = resolve(&asts);
+
+        assert_eq!(referenced(&resolution, 1, items, "MAX]"), "MAX");
+        let names: Vec<String> = linker_errors(&resolution)
+            .iter()
+            .map(|err| format!("{}", err))
+            .collect();
+        assert_eq!(names, vec!["Undefined symbol 'TEXT_MAX'"]);
+    }
+
+    #[test]
+    fn reports_duplicates() {
+        let other =
+            "func void Foo() { var int x; };\nvar int max;\nfunc void foo() { var int x; y = 1; };";
+        let asts = parse(&[CLASSES, other]);
+
+        let resolution = resolve(&asts);
+
+        let errors = linker_errors(&resolution);
+        assert_eq!(errors.len(), 2);
+        match errors[0] {
+            LinkerError::DuplicateSymbol { ref name, previous } => {
+                assert_eq!(name.to_string(), "max");
+                assert_eq!(previous.file, FileId(0));
+                assert_eq!(previous.start, CLASSES.find("MAX").unwrap());
+            }
+            ref err => panic!("unexpected error {:?}", err),
+        }
+        assert_eq!(errors[1].span().start, other.rfind("foo").unwrap());
+    }
+}
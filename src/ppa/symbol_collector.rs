@@ -1,26 +1,141 @@
+use errors::Error;
+use inner_errors::LinkerError;
 use ppa::visitor::*;
-use std::collections::HashMap;
 use types;
-use types::PrintableByteVec;
+use types::{Symbol, SymbolCollection, SymbolId, SymbolKind};
 
-#[derive(Debug)]
-pub struct ClassCollector {
-    class_defs: HashMap<PrintableByteVec, /*&'a*/ types::Class>,
+/// Registers every declaration it visits in a `SymbolCollection`.
+///
+/// ASTs have to be visited in `.src` order, so that for duplicate names the first
+/// declaration wins and every later one is reported.
+#[derive(Debug, Default)]
+pub struct SymbolCollector {
+    symbols: SymbolCollection,
+    errors: Vec<Error>,
+    current: Option<SymbolId>,
 }
 
-impl ClassCollector {
+impl SymbolCollector {
     pub fn new() -> Self {
-        ClassCollector {
-            class_defs: HashMap::new(),
+        SymbolCollector::default()
+    }
+
+    pub fn finish(self) -> (SymbolCollection, Vec<Error>) {
+        (self.symbols, self.errors)
+    }
+
+    /// Registers the functions of an externals file. They have to come before the scripts, like
+    /// in the engine, which defines them before parsing anything.
+    pub fn declare_externals(&mut self, externals: &[types::Function]) {
+        for func in externals {
+            self.func(func, true);
+        }
+        self.current = None;
+    }
+
+    fn func(&mut self, decl: &types::Function, external: bool) {
+        let symbol = Symbol::new(decl.name.clone(), SymbolKind::Func)
+            .with_type(decl.typ.clone())
+            .with_span(decl.span)
+            .with_external(external);
+        self.current = self.declare(symbol);
+
+        if self.current.is_some() {
+            for param in &decl.params {
+                let symbol = Symbol::new(param.name.clone(), SymbolKind::Param)
+                    .with_type(param.typ.clone())
+                    .with_array_size(param.array_size.clone())
+                    .with_scope(self.current)
+                    .with_span(param.span)
+                    .with_external(external);
+                self.declare(symbol);
+            }
+        }
+    }
+
+    fn declare(&mut self, symbol: Symbol) -> Option<SymbolId> {
+        let name = symbol.name.clone();
+        match self.symbols.insert(symbol) {
+            Ok(id) => Some(id),
+            Err(previous) => {
+                let previous = self.symbols.get(previous).name.span;
+                self.errors
+                    .push(Error::LinkingError(LinkerError::DuplicateSymbol {
+                        name,
+                        previous,
+                    }));
+                None
+            }
+        }
+    }
+
+    /// Declares a symbol in the global scope, or in the current one if `scope` is given.
+    /// Locals of a declaration that was itself a duplicate are dropped.
+    fn declare_in(&mut self, symbol: Symbol, scope: Option<&types::Identifier>) {
+        match (scope, self.current) {
+            (None, _) => {
+                self.declare(symbol);
+            }
+            (Some(_), Some(current)) => {
+                self.declare(symbol.with_scope(Some(current)));
+            }
+            (Some(_), None) => {}
         }
     }
 }
 
-impl VisitorMut for ClassCollector {
+impl VisitorMut for SymbolCollector {
+    fn visit_var_decl(&mut self, decl: &types::VarDeclaration, scope: Option<&types::Identifier>) {
+        let symbol = Symbol::new(decl.name.clone(), SymbolKind::Var)
+            .with_type(decl.typ.clone())
+            .with_array_size(decl.array_size.clone())
+            .with_span(decl.span);
+        self.declare_in(symbol, scope);
+    }
+
+    fn visit_func_decl(&mut self, decl: &types::Function) {
+        self.func(decl, false);
+    }
+
     fn visit_class_decl(&mut self, decl: &types::Class) {
-        self.class_defs.insert(
-            PrintableByteVec(decl.name.as_bytes().to_vec()),
-            decl.clone(),
-        );
+        let symbol = Symbol::new(decl.name.clone(), SymbolKind::Class).with_span(decl.span);
+        self.current = self.declare(symbol);
+    }
+
+    fn visit_inst_decl(&mut self, decl: &types::Instance) {
+        let symbol = Symbol::new(decl.name.clone(), SymbolKind::Instance)
+            .with_type(decl.class.clone())
+            .with_span(decl.span);
+        self.current = self.declare(symbol);
+    }
+
+    fn visit_proto_decl(&mut self, decl: &types::Prototype) {
+        let symbol = Symbol::new(decl.name.clone(), SymbolKind::Prototype)
+            .with_type(decl.class.clone())
+            .with_span(decl.span);
+        self.current = self.declare(symbol);
+    }
+
+    fn visit_const_decl(
+        &mut self,
+        decl: &types::ConstDeclaration,
+        scope: Option<&types::Identifier>,
+    ) {
+        let symbol = Symbol::new(decl.name.clone(), SymbolKind::Const)
+            .with_type(decl.typ.clone())
+            .with_span(decl.span);
+        self.declare_in(symbol, scope);
+    }
+
+    fn visit_const_arr_decl(
+        &mut self,
+        decl: &types::ConstArrayDeclaration,
+        scope: Option<&types::Identifier>,
+    ) {
+        let symbol = Symbol::new(decl.name.clone(), SymbolKind::ConstArray)
+            .with_type(decl.typ.clone())
+            .with_array_size(Some(decl.array_size.clone()))
+            .with_span(decl.span);
+        self.declare_in(symbol, scope);
     }
 }
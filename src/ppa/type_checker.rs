@@ -0,0 +1,407 @@
+use errors::Error;
+use inner_errors::TypeError;
+use ppa::name_resolver::Resolution;
+use ppa::visitor::{visit_ast, VisitorMut};
+use types;
+use types::{
+    AssignmentOperator, BinaryExpression, Call, Expression, Statement, SymbolId, SymbolKind, Type,
+    UnaryExpression, UnaryOperator, VarAccess, AST,
+};
+
+/// Type checks all `asts`, whose names must have been resolved into `resolution`.
+pub fn check<'a, I: IntoIterator<Item = &'a AST>>(asts: I, resolution: &Resolution) -> Vec<Error> {
+    let mut checker = TypeChecker::new(resolution);
+    for ast in asts {
+        visit_ast(ast, &mut checker);
+    }
+
+    checker.errors
+}
+
+/// Checks assignments, calls, returns, conditions and constant initializers.
+///
+/// Every expression is typed exactly once, from the statement or declaration it belongs to.
+/// Anything that failed to resolve has the type `Unknown`, which is compatible with every type.
+pub struct TypeChecker<'a> {
+    resolution: &'a Resolution,
+    func: Option<SymbolId>,
+    errors: Vec<Error>,
+}
+
+impl<'a> TypeChecker<'a> {
+    pub fn new(resolution: &'a Resolution) -> Self {
+        TypeChecker {
+            resolution,
+            func: None,
+            errors: Vec::new(),
+        }
+    }
+
+    fn error(&mut self, err: TypeError) {
+        self.errors.push(Error::TypeCheckError(err));
+    }
+
+    /// Type of the value a symbol stands for when it is used in an expression.
+    fn symbol_type(&self, id: SymbolId) -> Type {
+        let symbol = self.resolution.symbols.get(id);
+        match symbol.kind {
+            SymbolKind::Func => Type::Func,
+            SymbolKind::Class => Type::Unknown,
+            _ => self.resolution.symbols.type_of(symbol),
+        }
+    }
+
+    fn expression_type(&mut self, exp: &Expression) -> Type {
+        match *exp {
+            Expression::Int(..) => Type::Int,
+            Expression::Float(..) => Type::Float,
+            Expression::String(_) => Type::String,
+            Expression::Identifier(ref var) => self.var_type(var),
+            Expression::Binary(ref bin) => self.binary_type(bin),
+            Expression::Unary(ref un) => self.unary_type(un),
+            Expression::Call(ref call) => self.call_type(call),
+        }
+    }
+
+    fn var_type(&mut self, var: &VarAccess) -> Type {
+        if let Some(ref index) = var.index {
+            let typ = self.expression_type(index);
+            self.check_assignable(&Type::Int, &typ, index);
+        }
+
+        match self.resolution.symbol_at(var.name.span) {
+            Some(id) => self.symbol_type(id),
+            None => Type::Unknown,
+        }
+    }
+
+    fn binary_type(&mut self, bin: &BinaryExpression) -> Type {
+        let left = self.expression_type(&bin.left);
+        let right = self.expression_type(&bin.right);
+
+        if left == Type::Float || right == Type::Float {
+            self.error(TypeError::FloatArithmetic(bin.span));
+            return Type::Unknown;
+        }
+
+        self.check_assignable(&Type::Int, &left, &bin.left);
+        self.check_assignable(&Type::Int, &right, &bin.right);
+        Type::Int
+    }
+
+    fn unary_type(&mut self, un: &UnaryExpression) -> Type {
+        // Negative float literals are fine, they are folded by the compiler.
+        if let Expression::Float(..) = un.right {
+            if un.op == UnaryOperator::Minus || un.op == UnaryOperator::Plus {
+                return Type::Float;
+            }
+        }
+
+        let typ = self.expression_type(&un.right);
+        if typ == Type::Float {
+            self.error(TypeError::FloatArithmetic(un.span));
+            return Type::Unknown;
+        }
+
+        self.check_assignable(&Type::Int, &typ, &un.right);
+        Type::Int
+    }
+
+    fn call_type(&mut self, call: &Call) -> Type {
+        let symbols = &self.resolution.symbols;
+        let func = match self.resolution.symbol_at(call.func.span) {
+            Some(func) if symbols.get(func).kind == SymbolKind::Func => Some(func),
+            Some(_) => {
+                self.error(TypeError::NotCallable(call.func.clone()));
+                None
+            }
+            None => None,
+        };
+
+        let params: Vec<Type> = match func {
+            Some(func) => symbols
+                .children(func)
+                .iter()
+                .map(|id| symbols.get(*id))
+                .filter(|symbol| symbol.kind == SymbolKind::Param)
+                .map(|param| symbols.type_of(param))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if func.is_some() && params.len() != call.params.len() {
+            self.error(TypeError::WrongArgumentCount {
+                func: call.func.clone(),
+                expected: params.len(),
+                found: call.params.len(),
+            });
+        }
+
+        for (i, arg) in call.params.iter().enumerate() {
+            let typ = self.expression_type(arg);
+            if let Some(param) = params.get(i) {
+                self.check_assignable(param, &typ, arg);
+            }
+        }
+
+        match func {
+            Some(func) => symbols.type_of(symbols.get(func)),
+            None => Type::Unknown,
+        }
+    }
+
+    /// Integer literals may be stored in floats, everything else has to match.
+    fn check_assignable(&mut self, expected: &Type, found: &Type, exp: &Expression) {
+        let is_int_literal = match *exp {
+            Expression::Int(..) => true,
+            Expression::Unary(ref un) => match un.right {
+                Expression::Int(..) => un.op == UnaryOperator::Minus,
+                _ => false,
+            },
+            _ => false,
+        };
+
+        let accepted = expected.accepts(found) || (*expected == Type::Float && is_int_literal);
+        if !accepted {
+            self.error(TypeError::Mismatch {
+                expected: expected.clone(),
+                found: found.clone(),
+                span: exp.span(),
+            });
+        }
+    }
+
+    fn check_assignment(&mut self, ass: &types::Assignment) {
+        let target = self.var_type(&ass.var);
+        let value = self.expression_type(&ass.exp);
+
+        if ass.op == AssignmentOperator::Eq {
+            self.check_assignable(&target, &value, &ass.exp);
+        } else if target == Type::Float || value == Type::Float {
+            self.error(TypeError::FloatArithmetic(ass.span));
+        } else {
+            if !target.is_integral() {
+                self.error(TypeError::Mismatch {
+                    expected: Type::Int,
+                    found: target,
+                    span: ass.var.span,
+                });
+            }
+            self.check_assignable(&Type::Int, &value, &ass.exp);
+        }
+    }
+
+    fn check_condition(&mut self, cond: &Expression) {
+        let typ = self.expression_type(cond);
+        if typ == Type::String {
+            self.error(TypeError::StringCondition(cond.span()));
+        } else {
+            self.check_assignable(&Type::Int, &typ, cond);
+        }
+    }
+
+    fn check_return(&mut self, exp: Option<&Expression>, span: types::Span) {
+        let exp = match exp {
+            Some(exp) => exp,
+            None => return,
+        };
+        let typ = self.expression_type(exp);
+
+        let returns = match self.func {
+            Some(func) => self
+                .resolution
+                .symbols
+                .type_of(self.resolution.symbols.get(func)),
+            None => return,
+        };
+
+        if returns == Type::Void {
+            self.error(TypeError::ReturnFromVoid(span));
+        } else {
+            self.check_assignable(&returns, &typ, exp);
+        }
+    }
+}
+
+impl<'a> VisitorMut for TypeChecker<'a> {
+    fn visit_statement(&mut self, statement: &Statement, _scope: &types::Identifier) {
+        match *statement {
+            Statement::Exp(ref exp) => {
+                self.expression_type(exp);
+            }
+            Statement::Ass(ref ass) => self.check_assignment(ass),
+            Statement::If(ref if_statement) => {
+                for branch in &if_statement.branches {
+                    self.check_condition(&branch.cond);
+                }
+            }
+            Statement::ReturnStatement(ref exp, span) => self.check_return(exp.as_ref(), span),
+            Statement::VarDeclarations(_)
+            | Statement::ConstDeclaration(_)
+            | Statement::ConstArrayDeclaration(_) => {}
+        }
+    }
+
+    fn visit_func_decl(&mut self, decl: &types::Function) {
+        self.func = self.resolution.symbols.declaration(&decl.name);
+    }
+
+    fn visit_class_decl(&mut self, _decl: &types::Class) {
+        self.func = None;
+    }
+
+    fn visit_inst_decl(&mut self, _decl: &types::Instance) {
+        self.func = None;
+    }
+
+    fn visit_proto_decl(&mut self, _decl: &types::Prototype) {
+        self.func = None;
+    }
+
+    fn visit_const_decl(
+        &mut self,
+        decl: &types::ConstDeclaration,
+        _scope: Option<&types::Identifier>,
+    ) {
+        let expected = self.resolution.symbols.declared_type(&decl.typ);
+        let typ = self.expression_type(&decl.initializer);
+        self.check_assignable(&expected, &typ, &decl.initializer);
+    }
+
+    fn visit_const_arr_decl(
+        &mut self,
+        decl: &types::ConstArrayDeclaration,
+        _scope: Option<&types::Identifier>,
+    ) {
+        let expected = self.resolution.symbols.declared_type(&decl.typ);
+        for exp in &decl.initializer.expressions {
+            let typ = self.expression_type(exp);
+            self.check_assignable(&expected, &typ, exp);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parsers::parse_recovering;
+    use ppa::name_resolver::resolve;
+    use types::FileId;
+
+    const PRELUDE: &str = "class C_NPC { var int id; var string name; };
+class C_ITEM { var int value; };
+prototype Npc_Default(C_NPC) { id = 1; };
+instance Hero(Npc_Default) { name = \"Hero\"; };
+var C_NPC self;
+var int x;
+var float fl;
+var string s;
+func int Add(var int a, var int b) { return a + b; };
+func void Give(var C_NPC npc, var int item) {};";
+
+    fn check_errors(content: &str) -> Vec<String> {
+        let source = format!("{}\n{}", PRELUDE, content);
+        let (ast, errors) = parse_recovering(source.as_bytes(), FileId(0));
+        assert!(errors.is_empty());
+
+        let resolution = resolve(Some(&ast));
+        assert!(resolution.errors.is_empty(), "{:?}", resolution.errors);
+
+        check(Some(&ast), &resolution)
+            .into_iter()
+            .map(|err| match err {
+                Error::TypeCheckError(err) => err.to_string(),
+                err => panic!("unexpected error {:?}", err),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn valid_code() {
+        let errors = check_errors(
+            "const float PI = 3.14;
+const int ARR[2] = { 1, Add(1, 2) };
+instance Item(C_ITEM) { value = 5; };
+func int f() {
+    self = Hero;
+    self.name = \"x\";
+    x = Add(x, Hero);
+    x += 1;
+    fl = fl;
+    fl = 1;
+    fl = -2.5;
+    Give(self, Item);
+    if (x > 1 && self) { return x; };
+    return Add(1, 2);
+};",
+        );
+
+        assert!(errors.is_empty(), "{:?}", errors);
+    }
+
+    #[test]
+    fn mismatched_assignments() {
+        let errors = check_errors(
+            "const int C = \"c\";
+func void f() { x = \"a\"; s = x; self = 5; s += 1; };",
+        );
+
+        assert_eq!(
+            errors,
+            vec![
+                "Type mismatch: expected int, found string",
+                "Type mismatch: expected int, found string",
+                "Type mismatch: expected string, found int",
+                "Type mismatch: expected C_NPC, found int",
+                "Type mismatch: expected int, found string",
+            ]
+        );
+    }
+
+    #[test]
+    fn calls() {
+        let errors = check_errors("func void f() { Add(1); Add(1, \"s\"); x(); Give(Hero, fl); };");
+
+        assert_eq!(
+            errors,
+            vec![
+                "Wrong number of arguments for 'Add': expected 2, found 1",
+                "Type mismatch: expected int, found string",
+                "Not a function: 'x'",
+                "Type mismatch: expected int, found float",
+            ]
+        );
+    }
+
+    #[test]
+    fn returns_and_conditions() {
+        let errors = check_errors(
+            "func void f() { if (s) { return 1; } else if (fl) {}; };
+func string g() { return 1; };",
+        );
+
+        assert_eq!(
+            errors,
+            vec![
+                "A string cannot be used as a condition",
+                "Type mismatch: expected int, found float",
+                "Returning a value from a void function",
+                "Type mismatch: expected string, found int",
+            ]
+        );
+    }
+
+    #[test]
+    fn float_arithmetic() {
+        let errors = check_errors("func void f() { fl = fl * 2.0; fl += 1; x = -fl; };");
+
+        assert_eq!(
+            errors,
+            vec![
+                "Float arithmetic is not supported by the Gothic VM",
+                "Float arithmetic is not supported by the Gothic VM",
+                "Float arithmetic is not supported by the Gothic VM",
+            ]
+        );
+    }
+}
@@ -1,6 +1,9 @@
 use types;
 use types::*;
 
+// Both traits are called for every expression, including nested ones: for `a + f(b)` the visitor
+// sees the binary expression, then `a`, then the call and then `b`.
+
 #[allow(unused_variables)]
 pub trait VisitorMut {
     fn visit_expression(&mut self, _exp: &Expression, scope: Option<&types::Identifier>) {}
@@ -17,6 +20,8 @@ pub trait VisitorMut {
         scope: Option<&types::Identifier>,
     ) {
     }
+    fn visit_var_access(&mut self, access: &VarAccess, scope: Option<&types::Identifier>) {}
+    fn visit_call(&mut self, call: &Call, scope: Option<&types::Identifier>) {}
 }
 
 #[allow(unused_variables)]
@@ -35,6 +40,8 @@ pub trait Visitor {
         scope: Option<&types::Identifier>,
     ) {
     }
+    fn visit_var_access(&mut self, access: &VarAccess, scope: Option<&types::Identifier>) {}
+    fn visit_call(&mut self, call: &Call, scope: Option<&types::Identifier>) {}
 }
 /*
 impl<'a, T> VisitorMut for &'a mut T
@@ -86,15 +93,45 @@ struct VisitorEngine<'a, V: VisitorMut + 'a> {
 impl<'a, V: VisitorMut + 'a> VisitorMut for VisitorEngine<'a, V> {
     fn visit_expression(&mut self, exp: &Expression, scope: Option<&types::Identifier>) {
         self.visitor.visit_expression(exp, scope);
+
+        match *exp {
+            Expression::Identifier(ref var) => self.visit_var_access(var, scope),
+            Expression::Binary(ref bin) => {
+                self.visit_expression(&bin.left, scope);
+                self.visit_expression(&bin.right, scope);
+            }
+            Expression::Unary(ref un) => self.visit_expression(&un.right, scope),
+            Expression::Call(ref call) => self.visit_call(call, scope),
+            Expression::Int(..) | Expression::Float(..) | Expression::String(_) => {}
+        }
+    }
+
+    fn visit_var_access(&mut self, access: &VarAccess, scope: Option<&types::Identifier>) {
+        self.visitor.visit_var_access(access, scope);
+
+        if let Some(ref index) = access.index {
+            self.visit_expression(index, scope);
+        }
+    }
+
+    fn visit_call(&mut self, call: &Call, scope: Option<&types::Identifier>) {
+        self.visitor.visit_call(call, scope);
+
+        for param in &call.params {
+            self.visit_expression(param, scope);
+        }
     }
 
     fn visit_statement(&mut self, statement: &Statement, scope: &types::Identifier) {
         self.visitor.visit_statement(statement, scope);
 
-        match statement {
-            &Statement::Exp(ref exp) => self.visit_expression(exp, Some(scope)),
-            &Statement::Ass(ref ass) => self.visit_expression(&ass.exp, Some(scope)),
-            &Statement::If(ref if_statement) => {
+        match *statement {
+            Statement::Exp(ref exp) => self.visit_expression(exp, Some(scope)),
+            Statement::Ass(ref ass) => {
+                self.visit_var_access(&ass.var, Some(scope));
+                self.visit_expression(&ass.exp, Some(scope));
+            }
+            Statement::If(ref if_statement) => {
                 for branch in &if_statement.branches {
                     self.visit_expression(&branch.cond, Some(scope));
                     for statement in &branch.body {
@@ -102,25 +139,25 @@ impl<'a, V: VisitorMut + 'a> VisitorMut for VisitorEngine<'a, V> {
                     }
                 }
 
-                if let Some(ref else_branch) = &if_statement.else_branch {
+                if let Some(ref else_branch) = if_statement.else_branch {
                     for statement in else_branch {
                         self.visit_statement(statement, scope);
                     }
                 }
             }
-            &Statement::VarDeclarations(ref var_decls) => {
+            Statement::VarDeclarations(ref var_decls) => {
                 for decl in var_decls {
                     self.visit_var_decl(decl, Some(scope));
                 }
             }
-            &Statement::ConstDeclaration(ref const_decl) => {
+            Statement::ConstDeclaration(ref const_decl) => {
                 self.visit_const_decl(const_decl, Some(scope));
             }
-            &Statement::ConstArrayDeclaration(ref const_arr_decl) => {
+            Statement::ConstArrayDeclaration(ref const_arr_decl) => {
                 self.visit_const_arr_decl(const_arr_decl, Some(scope));
             }
-            &Statement::ReturnStatement(ref opt_exp) => {
-                if let Some(ref exp) = opt_exp {
+            Statement::ReturnStatement(ref opt_exp, _) => {
+                if let Some(ref exp) = *opt_exp {
                     self.visit_expression(exp, Some(scope))
                 }
             }
@@ -156,10 +193,14 @@ impl<'a, V: VisitorMut + 'a> VisitorMut for VisitorEngine<'a, V> {
 
     fn visit_proto_decl(&mut self, decl: &Prototype) {
         self.visitor.visit_proto_decl(decl);
+        for statement in &decl.body {
+            self.visit_statement(statement, &decl.name);
+        }
     }
 
     fn visit_const_decl(&mut self, decl: &ConstDeclaration, scope: Option<&types::Identifier>) {
         self.visitor.visit_const_decl(decl, scope);
+        self.visit_expression(&decl.initializer, scope);
     }
 
     fn visit_const_arr_decl(
@@ -168,6 +209,9 @@ impl<'a, V: VisitorMut + 'a> VisitorMut for VisitorEngine<'a, V> {
         scope: Option<&types::Identifier>,
     ) {
         self.visitor.visit_const_arr_decl(decl, scope);
+        for exp in &decl.initializer.expressions {
+            self.visit_expression(exp, scope);
+        }
     }
 }
 
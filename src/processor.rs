@@ -1,12 +1,21 @@
-use parsiphae::ppa::symbol_collector::ClassCollector;
+use parsiphae::cache::{self, Cache, Entry, FileAnalysis};
+use parsiphae::diagnostics::{self, Diagnostic, MessageFormat, Severity};
+use parsiphae::inner_errors::LinkerError;
+use parsiphae::ppa::call_graph::{CallGraph, GraphFormat};
+use parsiphae::ppa::layout::Target;
+use parsiphae::source_map::{SourceFile, SourceMap};
 use parsiphae::types::Expression;
-use parsiphae::{error_handler, errors::*, inner_errors::ParserError, ppa, src_parser, types};
+use parsiphae::{
+    codegen, dat, errors::*, externals, formatter, lint, parallel, ppa, src_parser, types,
+};
+use std::collections::HashSet;
 use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 
 struct TestVisitor;
 impl ppa::visitor::Visitor for TestVisitor {
-    fn visit_expression(&mut self, exp: &Expression, scope: Option<&types::Identifier>) {
+    fn visit_expression(&mut self, exp: &Expression, _scope: Option<&types::Identifier>) {
         let res = exp.evaluate_int();
 
         if let Ok(val) = res {
@@ -26,94 +35,559 @@ impl ppa::visitor::Visitor for TestVisitor {
     }
 }
 
-#[derive(Debug)]
-pub struct ParsingResult {
-    file: PathBuf,
-    result: Result<types::AST>,
+/// Prints `errors` and then `warnings` as diagnostics in `format` and fails with the last
+/// error, if any.
+fn report(
+    sources: &SourceMap,
+    errors: Vec<Error>,
+    warnings: Vec<Diagnostic>,
+    format: MessageFormat,
+) -> Result<()> {
+    let mut diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+    diagnostics.extend(warnings);
+    diagnostics::emit(&diagnostics, sources, format);
+
+    match errors.into_iter().last() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
 }
 
-impl ParsingResult {
-    pub fn new<P: AsRef<Path>>(path: P, result: Result<types::AST>) -> Self {
-        ParsingResult {
-            file: path.as_ref().to_owned(),
-            result,
-        }
+/// Prints progress, unless stdout is reserved for machine-readable diagnostics.
+fn status(format: MessageFormat, message: &str) {
+    if format == MessageFormat::Human {
+        println!("{}", message);
     }
+}
 
-    pub fn print(&self) {
-        match self.result {
-            Ok(_) => {}
-            Err(ref e) => match e {
-                Error::ParsingError { err, line } => {
-                    let msg = err.description();
-                    eprintln!("Error in file {:?} in line {}: {}", self.file, line, msg);
-                }
-                _ => unreachable!(),
+/// Parses a single file, returning everything that could be parsed
+/// plus every syntax error that was encountered along the way.
+fn process_file<P: AsRef<Path>>(
+    path: P,
+    sources: &mut SourceMap,
+) -> Result<(types::AST, Vec<Error>)> {
+    let content = read_file(&path)?;
+
+    let file_id = sources.add(&path, content);
+    let content = &sources.get(file_id).unwrap().content;
+    Ok(parsiphae::parsers::parse_recovering(content, file_id))
+}
+
+fn read_file<P: AsRef<Path>>(path: P) -> Result<Vec<u8>> {
+    let mut content = Vec::new();
+    ::std::fs::File::open(&path)?.read_to_end(&mut content)?;
+    Ok(content)
+}
+
+/// A script of a src after parsing.
+struct ParsedFile {
+    ast: types::AST,
+    errors: Vec<Error>,
+    /// The entry of the file in the cache from an earlier run. If the file did not change since,
+    /// its AST was moved to `ast`.
+    entry: Option<Entry>,
+    /// Whether the file is new or changed since it was last analysed.
+    changed: bool,
+}
+
+/// Reads and parses `paths` on `jobs` threads and adds them to `sources` in the given order,
+/// so file ids and errors are the same as when parsing one after another. Files that did not
+/// change since they were cached are not parsed again.
+fn process_files<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    jobs: usize,
+    sources: &mut SourceMap,
+    cache: Option<&Cache>,
+) -> Result<Vec<ParsedFile>> {
+    let first_id = sources.files().len();
+    let files: Vec<(types::FileId, &P)> = paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| (types::FileId(first_id + i), path))
+        .collect();
+    let parsed = parallel::map_ordered(&files, jobs, |(file_id, path)| -> Result<_> {
+        let content = read_file(path)?;
+        let mut entry = cache.and_then(|cache| cache.load(path.as_ref(), *file_id));
+        let (ast, errors, changed) = match entry {
+            Some(ref mut entry) if entry.hash == cache::hash(&content) => {
+                let ast = ::std::mem::take(&mut entry.ast);
+                (ast, Vec::new(), entry.analysis.is_none())
+            }
+            _ => {
+                let (ast, errors) = parsiphae::parsers::parse_recovering(&content, *file_id);
+                (ast, errors, true)
+            }
+        };
+        Ok((
+            content,
+            ParsedFile {
+                ast,
+                errors,
+                entry,
+                changed,
             },
+        ))
+    });
+
+    let mut results = Vec::with_capacity(paths.len());
+    for ((file_id, path), parsed) in files.into_iter().zip(parsed) {
+        let (content, parsed) = parsed?;
+        assert_eq!(sources.add(path, content), file_id);
+        results.push(parsed);
+    }
+    Ok(results)
+}
+
+pub fn process_single_file<P: AsRef<Path>>(path: P, format: MessageFormat) -> Result<types::AST> {
+    let mut sources = SourceMap::new();
+    let (ast, errors) = process_file(path, &mut sources)?;
+
+    report(&sources, errors, Vec::new(), format).map(|_| ast)
+}
+
+/// Rewrites `paths` in canonical formatting, or with `check` only reports the files that are
+/// not formatted. Files whose formatting is not stable are left as they are and reported.
+/// Returns whether all files were formatted already.
+pub fn format_files<P: AsRef<Path>>(
+    paths: &[P],
+    check: bool,
+    format: MessageFormat,
+) -> Result<bool> {
+    let mut sources = SourceMap::new();
+    let mut formatted_already = true;
+    let mut syntax_errors = Vec::new();
+    let mut warnings = Vec::new();
+
+    for path in paths {
+        let path = path.as_ref();
+        let mut content = Vec::new();
+        ::std::fs::File::open(path)?.read_to_end(&mut content)?;
+
+        let file_id = sources.add(path, content.clone());
+        let (ast, errors) = parsiphae::parsers::parse_recovering(&content, file_id);
+        if !errors.is_empty() {
+            syntax_errors.extend(errors);
+            continue;
+        }
+
+        let mut formatted = formatter::format(&ast, &content);
+        let (reparsed, errors) = parsiphae::parsers::parse_recovering(&formatted, file_id);
+        if !errors.is_empty() || formatter::format(&reparsed, &formatted) != formatted {
+            formatted_already = false;
+            warnings.push(Diagnostic {
+                severity: Severity::Warning,
+                code: "unstable-formatting",
+                message: "The file is left as it is, formatting it is not stable".to_owned(),
+                span: Some(types::Span::new(file_id, 0, 0)),
+                related: Vec::new(),
+            });
+            continue;
+        }
+        if content.windows(2).any(|pair| pair == b"\r\n") {
+            formatted = crlf(&formatted);
+        }
+
+        if formatted != content {
+            formatted_already = false;
+            if check {
+                status(format, &format!("{:?} is not formatted", path));
+            } else {
+                ::std::fs::write(path, &formatted)?;
+                status(format, &format!("Formatted {:?}", path));
+            }
         }
     }
 
-    pub fn is_ok(&self) -> bool {
-        return match self.result {
-            Ok(_) => true,
-            Err(_) => false,
-        };
+    report(&sources, syntax_errors, warnings, format).map(|_| formatted_already)
+}
+
+fn crlf(content: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(content.len());
+    for byte in content {
+        if *byte == b'\n' {
+            out.push(b'\r');
+        }
+        out.push(*byte);
     }
+    out
 }
 
-fn process_file<P: AsRef<Path>>(path: P) -> Result<ParsingResult> {
-    let mut file = ::std::fs::File::open(&path).unwrap();
+/// How to check a src, and what to write for it if it has no errors.
+#[derive(Debug, Default)]
+pub struct Options<'a> {
+    /// The compiled scripts.
+    pub dat: Option<&'a str>,
+    /// The memory layout of all classes, as a table.
+    pub layout: Option<&'a str>,
+    pub target: Target,
+    /// The externals the scripts are checked against, bundled or from a file.
+    pub externals: &'a str,
+    /// The call graph of the scripts.
+    pub call_graph: Option<&'a str>,
+    pub call_graph_format: GraphFormat,
+    /// Only puts this function and what calls it into the call graph.
+    pub callers_of: Option<&'a str>,
+    /// Warns about functions, constants and instances that are never used.
+    pub dead_code: bool,
+    /// Runs the lints this config enables.
+    pub lint: Option<&'a lint::Config>,
+}
 
-    let mut content = Vec::new();
-    file.read_to_end(&mut content)?;
+/// Checks a src and prints its errors and warnings. Returns whether there were no lints with
+/// the severity error, which unlike other errors do not fail the run.
+pub fn process_src<P: AsRef<Path>>(
+    path: P,
+    options: &Options,
+    format: MessageFormat,
+    jobs: usize,
+    cache: Option<&Cache>,
+) -> Result<bool> {
+    let mut sources = SourceMap::new();
+    let mut warnings = Vec::new();
+    let errors = check_src(
+        path,
+        options,
+        format,
+        jobs,
+        cache,
+        &mut sources,
+        &mut warnings,
+    )?;
+    let passed = !warnings
+        .iter()
+        .any(|warning| warning.severity == Severity::Error);
+    report(&sources, errors, warnings, format).map(|_| passed)
+}
 
-    use parsiphae::parsers::*;
-    let result = start(types::Input(&content))
-        .map_err(|err| error_handler::map_err(&content, err))
-        .map(|tuple| tuple.1);
+/// How often `watch_src` looks for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
 
-    Ok(ParsingResult::new(path, result))
-}
+/// Checks the src like `process_src`, and again whenever the src or one of its scripts changes,
+/// until the process is killed. The `cache` makes sure only what changed is checked again.
+pub fn watch_src<P: AsRef<Path>>(
+    path: P,
+    options: &Options,
+    format: MessageFormat,
+    jobs: usize,
+    cache: &Cache,
+) -> ! {
+    let path = path.as_ref();
+    loop {
+        let snapshot = snapshot(path);
 
-pub fn process_single_file<P: AsRef<Path>>(path: P) -> Result<types::AST> {
-    let res = process_file(path)?;
+        let mut sources = SourceMap::new();
+        let mut warnings = Vec::new();
+        let checked = check_src(
+            path,
+            options,
+            format,
+            jobs,
+            Some(cache),
+            &mut sources,
+            &mut warnings,
+        );
+        let errors = match checked {
+            Ok(errors) => errors,
+            Err(e) => vec![e],
+        };
+        let summary = match errors.len() {
+            0 => "No errors found".to_owned(),
+            1 => "Found 1 error".to_owned(),
+            count => format!("Found {} errors", count),
+        };
+        let _ = report(&sources, errors, warnings, format);
+        status(format, &summary);
+        status(format, "Watching for changes, press Ctrl-C to stop");
 
-    res.print();
+        while snapshot == self::snapshot(path) {
+            ::std::thread::sleep(POLL_INTERVAL);
+        }
+        status(format, "");
+    }
+}
 
-    return res.result;
+/// The scripts of the src at `path` with their modification times and sizes, plus the src
+/// itself and the srcs it includes. Any change to this means the src has to be checked again.
+fn snapshot(path: &Path) -> Vec<(PathBuf, Option<(SystemTime, u64)>)> {
+    let paths = match src_parser::load_src(path, &mut SourceMap::new()) {
+        Ok((src, _)) => src.srcs().into_iter().chain(src.scripts()).collect(),
+        Err(_) => vec![path.to_owned()],
+    };
+    paths
+        .into_iter()
+        .map(|path| {
+            let metadata = ::std::fs::metadata(&path)
+                .and_then(|metadata| Ok((metadata.modified()?, metadata.len())))
+                .ok();
+            (path, metadata)
+        })
+        .collect()
 }
 
-pub fn process_src<P: AsRef<Path>>(path: P) -> Result<()> {
-    let d_paths = src_parser::parse_src(&path)?;
+/// Parses, checks and optionally compiles the scripts of a src, parsing on `jobs` threads.
+/// With a `cache`, only the files that changed since the last run are parsed, and only they
+/// and the files using their symbols are analysed. Returns the errors of the first stage
+/// that failed; warnings are only added to `warnings` if there are none.
+fn check_src<P: AsRef<Path>>(
+    path: P,
+    options: &Options,
+    format: MessageFormat,
+    jobs: usize,
+    cache: Option<&Cache>,
+    sources: &mut SourceMap,
+    warnings: &mut Vec<Diagnostic>,
+) -> Result<Vec<Error>> {
+    let (src, mut src_errors) = src_parser::load_src(&path, sources)?;
+    let (externals, externals_errors) = externals::load(options.externals, sources)?;
+    src_errors.extend(externals_errors);
+    // Different externals change what names resolve to in every file, like reordering them does,
+    // so they are remembered along with the files of the src.
+    let externals_content = &sources.files().last().unwrap().content;
+    let run_files: Vec<PathBuf> = Some(PathBuf::from(format!(
+        "{}#{:016x}",
+        options.externals,
+        cache::hash(externals_content)
+    )))
+    .into_iter()
+    .chain(src.scripts())
+    .collect();
+    let d_paths = src.scripts();
+    let first_id = sources.files().len();
+    let mut files = process_files(&d_paths, jobs, sources, cache)?;
+    let index = |file: types::FileId| file.0 - first_id;
 
-    let results: Vec<ParsingResult> = d_paths.iter().map(process_file).collect::<Result<_>>()?;
+    status(format, &format!("Parsed {} files", files.len()));
+    if cache.is_some() {
+        let changed = files.iter().filter(|file| file.changed).count();
+        status(
+            format,
+            &format!("{} of them changed since the last run", changed),
+        );
+    }
 
-    let mut visitor = ClassCollector::new();
+    // Missing files would only lead to a flood of undefined symbols, so like syntax errors,
+    // problems with the src stop the run before the analysis.
+    if !src_errors.is_empty() || files.iter().any(|file| !file.errors.is_empty()) {
+        if let Some(cache) = cache {
+            let contents = &sources.files()[first_id..];
+            store_unanalysed(cache, &d_paths, &files, contents, jobs)?;
+        }
+        let syntax_errors = files.into_iter().flat_map(|file| file.errors);
+        return Ok(src_errors.into_iter().chain(syntax_errors).collect());
+    }
+    status(format, "No syntax errors detected!");
 
-    {
-        let okay_results = results
-            .iter()
-            .filter_map(|res| res.result.as_ref().ok());
+    // Only files that changed, or that use a name one of them declares or used to declare, are
+    // analysed again. If files were added, removed or reordered, when compiling, linting, looking
+    // for dead code or writing the call graph, all are.
+    let analyse_all = options.dat.is_some()
+        || options.dead_code
+        || options.lint.is_some()
+        || options.call_graph.is_some()
+        || cache.is_none_or(|cache| cache.load_files(path.as_ref()) != run_files);
+    let mut changed_exports = Vec::new();
+    for file in files.iter().filter(|file| file.changed) {
+        changed_exports.extend(cache::exports(&file.ast));
+        if let Some(ref entry) = file.entry {
+            changed_exports.extend(entry.exports.iter().cloned());
+        }
+    }
+    let changed_exports: HashSet<&[u8]> = changed_exports.iter().map(|name| &name[..]).collect();
+    let dirty: Vec<bool> = files
+        .iter()
+        .map(|file| match file.entry {
+            Some(ref entry) if !analyse_all && !file.changed => entry.imports_any(&changed_exports),
+            _ => true,
+        })
+        .collect();
 
-        for ast in okay_results {
-            ::parsiphae::ppa::visitor::visit_ast(&ast, &mut visitor);
+    let asts: Vec<types::AST> = files
+        .iter_mut()
+        .map(|file| ::std::mem::take(&mut file.ast))
+        .collect();
+    let mut resolution = ppa::name_resolver::resolve_files(&externals, &asts, |i| dirty[i]);
+    status(
+        format,
+        &format!("Found {} symbols", resolution.symbols.len()),
+    );
+    let dirty_asts = || {
+        asts.iter()
+            .enumerate()
+            .filter(|(i, _)| dirty[*i])
+            .map(|(_, ast)| ast)
+    };
+    let mut type_errors = ppa::type_checker::check(dirty_asts(), &resolution);
+    let evaluator = ppa::constant::Evaluator::new(&asts, &resolution);
+    type_errors.extend(ppa::array_checker::check(
+        dirty_asts(),
+        &resolution,
+        &evaluator,
+    ));
+
+    // Symbols are always collected from all files, so duplicates are always reported afresh.
+    // Everything else is reported per file, and taken from the cache for the clean ones.
+    let (mut errors, mut link_errors): (Vec<Error>, Vec<Error>) =
+        resolution.errors.drain(..).partition(|err| {
+            matches!(
+                err,
+                Error::LinkingError(LinkerError::DuplicateSymbol { .. })
+            )
+        });
+    link_errors.extend(ppa::inheritance::check(dirty_asts(), &resolution));
+    let mut analyses: Vec<FileAnalysis> = files
+        .iter_mut()
+        .zip(&dirty)
+        .map(|(file, dirty)| match file.entry {
+            Some(ref mut entry) if !dirty => entry.analysis.take().unwrap_or_default(),
+            _ => FileAnalysis::default(),
+        })
+        .collect();
+    for err in link_errors.into_iter().chain(type_errors) {
+        match err {
+            Error::LinkingError(err) => analyses[index(err.span().file)].link_errors.push(err),
+            Error::TypeCheckError(err) => analyses[index(err.span().file)].type_errors.push(err),
+            err => errors.push(err),
         }
+    }
+    for analysis in &analyses {
+        errors.extend(
+            analysis
+                .link_errors
+                .iter()
+                .cloned()
+                .map(Error::LinkingError),
+        );
+    }
+    for analysis in &analyses {
+        errors.extend(
+            analysis
+                .type_errors
+                .iter()
+                .cloned()
+                .map(Error::TypeCheckError),
+        );
+    }
 
-        // println!("{:#?}", visitor);
+    if let Some(cache) = cache {
+        let analysed: Vec<(usize, FileAnalysis)> = analyses
+            .into_iter()
+            .enumerate()
+            .filter(|(i, _)| dirty[*i])
+            .collect();
+        let stored = parallel::map_ordered(&analysed, jobs, |(i, analysis)| {
+            let content = &sources.files()[first_id + i].content;
+            let mut entry = Entry::new(content, asts[*i].clone());
+            entry.analysis = Some(analysis.clone());
+            cache.store(d_paths[*i].as_ref(), &entry)
+        });
+        stored.into_iter().collect::<Result<Vec<()>>>()?;
+        cache.store_files(path.as_ref(), &run_files)?;
     }
 
-    println!("Parsed {} files", results.len());
-    if results.iter().all(ParsingResult::is_ok) {
-        println!("No syntax errors detected!");
-        return Ok(());
-    } else {
-        let mut err = Ok(());
-        for result in results {
-            result.print();
-            if let Err(e) = result.result {
-                err = Err(e);
-            }
+    if !errors.is_empty() {
+        return Ok(errors);
+    }
+
+    if options.dead_code {
+        warnings.extend(ppa::dead_code::check(&resolution));
+    }
+
+    if let Some(config) = options.lint {
+        let cx = lint::Context {
+            asts: &asts,
+            resolution: &resolution,
+        };
+        warnings.extend(lint::run(&cx, &lint::Registry::builtin(), config, sources));
+    }
+
+    if let Some(layout) = options.layout {
+        let evaluator = ppa::constant::Evaluator::new(&asts, &resolution);
+        let (layouts, errors) =
+            ppa::layout::layout(&resolution.symbols, &evaluator, options.target);
+        if !errors.is_empty() {
+            return Ok(errors);
         }
-        return err;
+        ::std::fs::write(layout, ppa::layout::table(&layouts, &resolution.symbols))?;
+        status(
+            format,
+            &format!(
+                "Wrote the layout of {} classes to {}",
+                layouts.len(),
+                layout
+            ),
+        );
     }
+
+    if let Some(path) = options.call_graph {
+        let symbols = &resolution.symbols;
+        let mut graph = CallGraph::build(&asts, &resolution);
+        if let Some(name) = options.callers_of {
+            let id = match symbols.lookup_global(name.as_bytes()) {
+                Some(id) => id,
+                None => {
+                    return Err(Error::IOError(::std::io::Error::new(
+                        ::std::io::ErrorKind::InvalidInput,
+                        format!("no function {} to show the callers of", name),
+                    )))
+                }
+            };
+            graph = graph.restrict(&graph.affected_by(id));
+        }
+        let content = match options.call_graph_format {
+            GraphFormat::Dot => graph.dot(symbols),
+            GraphFormat::Json => graph.json(symbols).to_string(),
+        };
+        ::std::fs::write(path, content)?;
+        status(
+            format,
+            &format!(
+                "Wrote the call graph of {} functions, instances and prototypes to {}",
+                graph.nodes().len(),
+                path
+            ),
+        );
+    }
+
+    if let Some(dat_path) = options.dat {
+        let dat = match codegen::compile(&asts, &resolution, sources, options.target) {
+            Ok(dat) => dat,
+            Err(codegen_errors) => {
+                errors.extend(codegen_errors);
+                return Ok(errors);
+            }
+        };
+
+        let mut file = ::std::io::BufWriter::new(::std::fs::File::create(dat_path)?);
+        dat::write(&dat, &mut file)?;
+        status(
+            format,
+            &format!("Wrote {} symbols to {}", dat.symbols.len(), dat_path),
+        );
+    }
+
+    Ok(errors)
+}
+
+/// Caches the files that changed and parsed without errors, even though they could not be
+/// analysed, so they need not be parsed again.
+fn store_unanalysed<P: AsRef<Path> + Sync>(
+    cache: &Cache,
+    paths: &[P],
+    files: &[ParsedFile],
+    contents: &[SourceFile],
+    jobs: usize,
+) -> Result<()> {
+    let unanalysed: Vec<usize> = (0..files.len())
+        .filter(|i| files[*i].changed && files[*i].errors.is_empty())
+        .collect();
+
+    let stored = parallel::map_ordered(&unanalysed, jobs, |i| {
+        let file = &files[*i];
+        let mut entry = Entry::new(&contents[*i].content, file.ast.clone());
+        if let Some(ref previous) = file.entry {
+            entry.keep_exports(previous);
+        }
+        cache.store(paths[*i].as_ref(), &entry)
+    });
+    stored.into_iter().collect()
 }
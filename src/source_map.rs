@@ -0,0 +1,254 @@
+use std::path::{Path, PathBuf};
+use types::{charset, FileId, Span};
+
+/// 1-based line and column of a byte offset. Columns count bytes, which for
+/// the single-byte encodings Daedalus scripts are written in equals characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Maps byte offsets of a single file to lines and columns.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    line_starts: Vec<usize>,
+    len: usize,
+}
+
+impl LineIndex {
+    pub fn new(content: &[u8]) -> Self {
+        let mut line_starts = vec![0];
+        line_starts.extend(
+            content
+                .iter()
+                .enumerate()
+                .filter(|(_, b)| **b == b'\n')
+                .map(|(i, _)| i + 1),
+        );
+
+        LineIndex {
+            line_starts,
+            len: content.len(),
+        }
+    }
+
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        let offset = offset.min(self.len);
+        let line = match self.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+
+        LineCol {
+            line: line + 1,
+            column: offset - self.line_starts[line] + 1,
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Byte range of the 1-based `line`, excluding its line break.
+    pub fn line_range(&self, line: usize) -> (usize, usize) {
+        let start = self.line_starts[line - 1];
+        let end = self
+            .line_starts
+            .get(line)
+            .map(|next| next - 1)
+            .unwrap_or(self.len);
+
+        (start, end)
+    }
+
+    /// Inverse of `line_col`; positions past the end of a line are clamped to it.
+    pub fn offset(&self, position: LineCol) -> usize {
+        if position.line == 0 || position.line > self.line_starts.len() {
+            return self.len;
+        }
+        let (start, end) = self.line_range(position.line);
+        (start + position.column.saturating_sub(1)).min(end)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub id: FileId,
+    pub path: PathBuf,
+    pub content: Vec<u8>,
+    pub lines: LineIndex,
+}
+
+impl SourceFile {
+    pub fn line_col(&self, offset: usize) -> LineCol {
+        self.lines.line_col(offset)
+    }
+
+    pub fn slice(&self, span: Span) -> &[u8] {
+        &self.content[span.start.min(self.content.len())..span.end.min(self.content.len())]
+    }
+}
+
+/// Owns the contents of every file of a run and resolves spans to locations.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        SourceMap { files: Vec::new() }
+    }
+
+    pub fn add<P: AsRef<Path>>(&mut self, path: P, content: Vec<u8>) -> FileId {
+        let id = FileId(self.files.len());
+        let lines = LineIndex::new(&content);
+        self.files.push(SourceFile {
+            id,
+            path: path.as_ref().to_owned(),
+            content,
+            lines,
+        });
+
+        id
+    }
+
+    pub fn get(&self, id: FileId) -> Option<&SourceFile> {
+        self.files.get(id.0)
+    }
+
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    pub fn resolve(&self, span: Span) -> Option<(&Path, LineCol, LineCol)> {
+        self.get(span.file).map(|file| {
+            (
+                file.path.as_path(),
+                file.line_col(span.start),
+                file.line_col(span.end),
+            )
+        })
+    }
+}
+
+/// Converts script bytes, which are in the charset of the run, to text.
+pub fn decode(bytes: &[u8]) -> String {
+    charset().decode(bytes)
+}
+
+/// Converts text, e.g. from an editor, to the charset of scripts.
+pub fn encode(text: &str) -> Vec<u8> {
+    charset().encode(text)
+}
+
+/// The path of a `file://` URI.
+pub fn path_from_uri(uri: &str) -> Option<PathBuf> {
+    if !uri.get(..7)?.eq_ignore_ascii_case("file://") {
+        return None;
+    }
+    let path = &uri[7..];
+
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = match tail {
+            [high, low, ..] if byte == b'%' => {
+                u8::from_str_radix(::std::str::from_utf8(&[*high, *low]).ok()?, 16).ok()
+            }
+            _ => None,
+        };
+        match escaped {
+            Some(escaped) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            None => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+    let mut path = String::from_utf8(bytes).ok()?;
+
+    // `file:///c:/Gothic` names the path `c:/Gothic`.
+    if path.as_bytes().get(2) == Some(&b':') && path.starts_with('/') {
+        path.remove(0);
+    }
+    Some(PathBuf::from(path))
+}
+
+/// The `file://` URI of an absolute path.
+pub fn file_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::from("file://");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_col() {
+        let index = LineIndex::new(b"ab\ncd\n\nef");
+
+        assert_eq!(index.line_col(0), LineCol { line: 1, column: 1 });
+        assert_eq!(index.line_col(2), LineCol { line: 1, column: 3 });
+        assert_eq!(index.line_col(3), LineCol { line: 2, column: 1 });
+        assert_eq!(index.line_col(6), LineCol { line: 3, column: 1 });
+        assert_eq!(index.line_col(8), LineCol { line: 4, column: 2 });
+    }
+
+    #[test]
+    fn line_range() {
+        let index = LineIndex::new(b"ab\ncd\n\nef");
+
+        assert_eq!(index.line_range(1), (0, 2));
+        assert_eq!(index.line_range(3), (6, 6));
+        assert_eq!(index.line_range(4), (7, 9));
+        assert_eq!(index.offset(LineCol { line: 2, column: 2 }), 4);
+    }
+
+    #[test]
+    fn resolve() {
+        let mut map = SourceMap::new();
+        map.add("a.d", b"func".to_vec());
+        let id = map.add("b.d", b"\n  foo".to_vec());
+
+        let (path, start, end) = map.resolve(Span::new(id, 3, 6)).unwrap();
+
+        assert_eq!(path, Path::new("b.d"));
+        assert_eq!(start, LineCol { line: 2, column: 3 });
+        assert_eq!(end, LineCol { line: 2, column: 6 });
+    }
+
+    #[test]
+    fn uris() {
+        assert_eq!(
+            path_from_uri("file:///home/user/My%20Scripts/a.d"),
+            Some(PathBuf::from("/home/user/My Scripts/a.d"))
+        );
+        assert_eq!(
+            path_from_uri("file:///c%3A/Gothic/a.d"),
+            Some(PathBuf::from("c:/Gothic/a.d"))
+        );
+        assert_eq!(path_from_uri("untitled:Untitled-1"), None);
+        assert_eq!(
+            file_uri(Path::new("/home/user/My Scripts/ä.d")),
+            "file:///home/user/My%20Scripts/%C3%A4.d"
+        );
+    }
+}
@@ -1,104 +1,391 @@
+//! Loading of `.src` files, which list the scripts to compile one path per line. Paths are
+//! relative to the `.src`, may contain `*` and `?` wildcards in any component and may name
+//! further `.src` files. As the scripts are written on Windows, paths match case-insensitively.
+
 use errors::*;
-use glob::glob;
-use std::io::Read;
-use std::path;
+use inner_errors::SrcError;
+use source_map::{decode, SourceMap};
+use std::fs;
 use std::path::{Path, PathBuf};
+use types::{FileId, Span};
+
+/// A loaded `.src` with everything it includes, in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SrcFile {
+    pub path: PathBuf,
+    pub file: FileId,
+    pub includes: Vec<Include>,
+}
+
+/// A file matched by a line of a `.src`. A wildcard line yields one include per match.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Include {
+    /// The line of the `.src` that matched the file.
+    pub span: Span,
+    pub kind: IncludeKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum IncludeKind {
+    Script(PathBuf),
+    Src(SrcFile),
+}
+
+impl SrcFile {
+    /// All scripts in the order they are compiled, including those of nested `.src` files.
+    pub fn scripts(&self) -> Vec<PathBuf> {
+        let mut scripts = Vec::new();
+        self.walk(&mut |include| {
+            if let IncludeKind::Script(ref path) = include.kind {
+                scripts.push(path.clone());
+            }
+        });
+        scripts
+    }
+
+    /// This `.src` and all `.src` files it includes, directly or indirectly.
+    pub fn srcs(&self) -> Vec<PathBuf> {
+        let mut srcs = vec![self.path.clone()];
+        self.walk(&mut |include| {
+            if let IncludeKind::Src(ref src) = include.kind {
+                srcs.push(src.path.clone());
+            }
+        });
+        srcs
+    }
+
+    fn walk<F: FnMut(&Include)>(&self, f: &mut F) {
+        for include in &self.includes {
+            f(include);
+            if let IncludeKind::Src(ref src) = include.kind {
+                src.walk(f);
+            }
+        }
+    }
+}
 
+/// The scripts of the `.src` at `path`, ignoring any problems with its lines.
 pub fn parse_src<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
-    let mut file = ::std::fs::File::open(&path)?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)?;
-
-    let paths = collect_paths(contents, &path)?;
-
-    Ok(paths)
-}
-
-fn collect_paths<P: AsRef<Path>>(content: String, path: P) -> Result<Vec<PathBuf>> {
-    let path = path.as_ref();
-    let parent = parent_dir(path)?;
-    let dir = parent.to_string_lossy();
-
-    let mut vec = Vec::new();
-
-    let lines = content
-        .lines()
-        .filter_map(|line| fix_line(line))
-        .map(|line| format!("{}\\{}", dir, line))
-        .collect::<Vec<String>>();
-
-    for line in lines {
-        let line_normalized = line.replace("\\", &path::MAIN_SEPARATOR.to_string());
-        for entry in glob(&line_normalized).unwrap() {
-            match entry {
-                Ok(path) => {
-                    let extension = {
-                        let extension = match path.extension() {
-                            None => {
-                                println!(
-                                    "invalid extension of path {:?}\nline: {}\ndir: {}",
-                                    &path, &line_normalized, &dir
-                                );
-                                ::std::ffi::OsStr::new("abc")
-                            }
-                            Some(ext) => ext,
-                        };
-                        extension
-                            .to_str()
-                            .expect("found invalid file name")
-                            .to_owned()
-                            .to_uppercase()
-                    };
-
-                    match extension.as_ref() {
-                        "D" => {
-                            vec.push(path);
-                        }
-                        "SRC" => {
-                            let inner_vec = parse_src(path)?;
-                            vec.extend(inner_vec.into_iter());
-                        }
-                        other => {
-                            println!("invalid extension {} in path {:?}", other, path);
-                            return Err(::std::io::Error::new(
-                                ::std::io::ErrorKind::InvalidData,
-                                "Invalid extension",
-                            ).into());
-                        }
-                    }
+    let (src, _) = load_src(path, &mut SourceMap::new())?;
+    Ok(src.scripts())
+}
+
+/// Loads the `.src` at `path` and every `.src` it includes, adding them to `sources` so that
+/// problems with their lines can be reported. Only failing to read `path` itself is an error;
+/// missing files, wildcards without matches and the like are returned as diagnostics.
+pub fn load_src<P: AsRef<Path>>(path: P, sources: &mut SourceMap) -> Result<(SrcFile, Vec<Error>)> {
+    let path = absolute(path.as_ref())?;
+    let content = fs::read(&path)?;
+    let mut errors = Vec::new();
+    let src = load(path, content, sources, &mut Vec::new(), &mut errors);
+    Ok((src, errors))
+}
+
+fn load(
+    path: PathBuf,
+    content: Vec<u8>,
+    sources: &mut SourceMap,
+    stack: &mut Vec<PathBuf>,
+    errors: &mut Vec<Error>,
+) -> SrcFile {
+    let file = sources.add(&path, content);
+    let lines = lines(&sources.get(file).unwrap().content);
+    let dir = path.parent().map(Path::to_owned).unwrap_or_default();
+    stack.push(canonical(&path));
+
+    let mut includes = Vec::new();
+    for (start, line) in lines {
+        let span = Span::new(file, start, start + line.len());
+        let name = decode(&line);
+
+        let matches = resolve(&dir, &name);
+        if matches.is_empty() {
+            errors.push(Error::SrcError(if is_wildcard(&name) {
+                SrcError::NoMatches { name, span }
+            } else {
+                SrcError::MissingFile { name, span }
+            }));
+            continue;
+        }
+
+        for found in matches {
+            let extension = found
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase());
+            let name = found.to_string_lossy().into_owned();
+            match extension.as_deref() {
+                Some("d") => includes.push(Include {
+                    span,
+                    kind: IncludeKind::Script(found),
+                }),
+                Some("src") if stack.contains(&canonical(&found)) => {
+                    errors.push(Error::SrcError(SrcError::RecursiveInclude { name, span }))
                 }
+                Some("src") => match fs::read(&found) {
+                    Ok(content) => {
+                        let src = load(found, content, sources, stack, errors);
+                        includes.push(Include {
+                            span,
+                            kind: IncludeKind::Src(src),
+                        });
+                    }
+                    Err(e) => errors.push(Error::SrcError(SrcError::Unreadable {
+                        name,
+                        reason: e.to_string(),
+                        span,
+                    })),
+                },
+                _ => errors.push(Error::SrcError(SrcError::UnknownExtension { name, span })),
+            }
+        }
+    }
 
-                // if the path matched but was unreadable,
-                // thereby preventing its contents from matching
-                Err(e) => println!("{:?}", e),
+    stack.pop();
+    SrcFile {
+        path,
+        file,
+        includes,
+    }
+}
+
+/// The non-empty lines of `content` with their offsets, without comments and surrounding
+/// whitespace. Block comments may span several lines.
+fn lines(content: &[u8]) -> Vec<(usize, Vec<u8>)> {
+    let mut lines = Vec::new();
+    let mut line: Vec<(usize, u8)> = Vec::new();
+    let mut in_block = false;
+    let mut i = 0;
+    while i < content.len() {
+        let rest = &content[i..];
+        if in_block {
+            if rest.starts_with(b"*/") {
+                in_block = false;
+                i += 2;
+                continue;
             }
+        } else if rest.starts_with(b"/*") {
+            in_block = true;
+            i += 2;
+            continue;
+        } else if rest.starts_with(b"//") {
+            while i < content.len() && content[i] != b'\n' {
+                i += 1;
+            }
+            continue;
         }
+
+        if content[i] == b'\n' {
+            lines.extend(trim(&line));
+            line.clear();
+        } else if !in_block {
+            line.push((i, content[i]));
+        }
+        i += 1;
+    }
+    lines.extend(trim(&line));
+    lines
+}
+
+fn trim(line: &[(usize, u8)]) -> Option<(usize, Vec<u8>)> {
+    let first = line.iter().position(|(_, c)| !c.is_ascii_whitespace())?;
+    let last = line.iter().rposition(|(_, c)| !c.is_ascii_whitespace())?;
+    let line = &line[first..=last];
+    Some((line[0].0, line.iter().map(|(_, c)| *c).collect()))
+}
+
+fn is_wildcard(name: &str) -> bool {
+    name.contains(['*', '?'])
+}
+
+/// The files `name` refers to, relative to `dir`. Components without wildcards match the file
+/// of that name, or if there is none, the one that differs from it only in case. Matches of
+/// wildcards are sorted by their lowercase names, so the order does not depend on the system.
+fn resolve(dir: &Path, name: &str) -> Vec<PathBuf> {
+    let components: Vec<&str> = name
+        .split(['\\', '/'])
+        .filter(|component| !component.is_empty())
+        .collect();
+    let mut found = vec![dir.to_owned()];
+    for (i, component) in components.iter().enumerate() {
+        let last = i + 1 == components.len();
+        let mut next = Vec::new();
+        for path in found {
+            match *component {
+                "." => next.push(path),
+                ".." => next.push(path.parent().map(Path::to_owned).unwrap_or(path)),
+                component if is_wildcard(component) => {
+                    let pattern: Vec<char> = component.to_lowercase().chars().collect();
+                    let mut matches: Vec<(String, PathBuf)> = entries(&path)
+                        .filter(|(name, _)| {
+                            wildcard_match(&pattern, &name.chars().collect::<Vec<_>>())
+                        })
+                        .filter(|(_, path)| if last { path.is_file() } else { path.is_dir() })
+                        .collect();
+                    matches.sort();
+                    next.extend(matches.into_iter().map(|(_, path)| path));
+                }
+                component => {
+                    let exact = path.join(component);
+                    if exact.exists() {
+                        next.push(exact);
+                    } else {
+                        let lowercase = component.to_lowercase();
+                        next.extend(
+                            entries(&path)
+                                .filter(|(name, _)| *name == lowercase)
+                                .map(|(_, path)| path)
+                                .take(1),
+                        );
+                    }
+                }
+            }
+        }
+        found = next;
     }
+    found
+}
 
-    Ok(vec)
+/// The entries of `dir` with their lowercase names.
+fn entries(dir: &Path) -> impl Iterator<Item = (String, PathBuf)> {
+    fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            (
+                entry.file_name().to_string_lossy().to_lowercase(),
+                entry.path(),
+            )
+        })
 }
 
-fn parent_dir(path: &Path) -> Result<PathBuf> {
-    let parent_path = if path.is_relative() {
-        let mut absolute_path = ::std::env::current_dir()?;
-        absolute_path.push(path);
-        absolute_path.parent().map(|p| p.to_owned())
+/// Whether `name` matches `pattern`, where `*` matches any number of characters and `?` exactly
+/// one.
+fn wildcard_match(pattern: &[char], name: &[char]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| wildcard_match(rest, &name[skip..])),
+        Some(('?', rest)) => !name.is_empty() && wildcard_match(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && wildcard_match(rest, &name[1..]),
+    }
+}
+
+fn absolute(path: &Path) -> Result<PathBuf> {
+    if path.is_relative() {
+        Ok(::std::env::current_dir()?.join(path))
     } else {
-        path.parent().map(|p| p.to_owned())
-    };
+        Ok(path.to_owned())
+    }
+}
 
-    parent_path.ok_or(
-        ::std::io::Error::new(
-            ::std::io::ErrorKind::NotFound,
-            "Unable to get parent directory",
-        ).into(),
-    )
+/// `path` without links and `..`, so the same `.src` reached in different ways is recognised.
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_owned())
 }
 
-fn fix_line(line: &str) -> Option<&str> {
-    let fixed = line.split("//").next().unwrap().trim();
-    if fixed.is_empty() {
-        return None;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            ::std::env::temp_dir().join(format!("parsiphae-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write(dir: &Path, name: &str, content: &str) {
+        let path = dir.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    fn names(dir: &Path, paths: &[PathBuf]) -> Vec<String> {
+        paths
+            .iter()
+            .map(|path| {
+                let path = path.strip_prefix(dir).unwrap().to_string_lossy();
+                path.replace('\\', "/")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn comments() {
+        let lines = lines(b"  a.d // b.d\r\n/* c.d\nd.d */ e.d\n\n/**/f.d");
+        let text: Vec<(usize, &[u8])> = lines.iter().map(|(i, line)| (*i, &line[..])).collect();
+        assert_eq!(
+            text,
+            vec![(2, &b"a.d"[..]), (28, &b"e.d"[..]), (37, &b"f.d"[..])]
+        );
+    }
+
+    #[test]
+    fn wildcards() {
+        let pattern: Vec<char> = "b*_?.d".chars().collect();
+        let matches = |name: &str| wildcard_match(&pattern, &name.chars().collect::<Vec<_>>());
+        assert!(matches("b_1.d"));
+        assert!(matches("bar_x.d"));
+        assert!(!matches("bar_.d"));
+        assert!(!matches("bar_x.dd"));
+    }
+
+    #[test]
+    fn load() {
+        let dir = temp_dir("src");
+        write(&dir, "Content/Story/B.d", "");
+        write(&dir, "Content/Story/a.D", "");
+        write(&dir, "Content/Story/notes.txt", "");
+        write(&dir, "Content/Main.d", "");
+        write(&dir, "Content/Inner.src", "STORY\\*.d\r\n..\\Gothic.src\n");
+        write(
+            &dir,
+            "Gothic.src",
+            "/* Gothic */\ncontent\\MAIN.D\nContent/inner.SRC // nested\nMissing.d\nnone\\*.d\nContent\\Story\\*\n",
+        );
+
+        let mut sources = SourceMap::new();
+        let (src, errors) = load_src(dir.join("Gothic.src"), &mut sources).unwrap();
+
+        assert_eq!(
+            names(&dir, &src.scripts()),
+            vec![
+                "Content/Main.d",
+                "Content/Story/a.D",
+                "Content/Story/B.d",
+                "Content/Story/a.D",
+                "Content/Story/B.d",
+            ]
+        );
+        assert_eq!(
+            names(&dir, &src.srcs()),
+            vec!["Gothic.src", "Content/Inner.src"]
+        );
+        match src.includes[1].kind {
+            IncludeKind::Src(ref inner) => assert_eq!(inner.includes.len(), 2),
+            ref other => panic!("expected a src, got {:?}", other),
+        }
+
+        let errors: Vec<(&str, usize)> = errors
+            .iter()
+            .map(|err| match *err {
+                Error::SrcError(ref err) => {
+                    let (_, start, _) = sources.resolve(err.span()).unwrap();
+                    (err.code(), start.line)
+                }
+                ref other => panic!("expected a src error, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("recursive-include", 2),
+                ("missing-file", 4),
+                ("no-matches", 5),
+                ("unknown-extension", 6),
+            ]
+        );
+        fs::remove_dir_all(dir).unwrap();
     }
-    Some(fixed)
 }
@@ -1,6 +1,6 @@
 use parsers::var_decl;
 use tests::utility::*;
-use types::{ArraySizeDeclaration, Identifier, VarDeclaration};
+use types::{ArraySizeDeclaration, Identifier, Span, VarDeclaration};
 
 #[test]
 pub fn test_var_decl() {
@@ -42,7 +42,7 @@ pub fn test_var_decl() {
     let decl = vec![VarDeclaration::new(
         Identifier::new(b"int"),
         Identifier::new(b"foo"),
-        Some(ArraySizeDeclaration::Size(3)),
+        Some(ArraySizeDeclaration::Size(3, Span::default())),
     )];
     test_parser_done(var_decl, b"var int foo[3]", decl, b"");
 
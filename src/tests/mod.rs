@@ -1,3 +1,5 @@
 #[cfg(test)]
 mod decl;
+#[cfg(test)]
+mod spans;
 pub mod utility;
@@ -0,0 +1,69 @@
+use parsers::{start, with_source};
+use types::{Declaration, FileId, Input, Span, Statement, AST};
+
+fn parse(content: &[u8]) -> AST {
+    with_source(content, FileId(7), || start(Input(content)))
+        .unwrap()
+        .1
+}
+
+fn text(content: &[u8], span: Span) -> &[u8] {
+    &content[span.start..span.end]
+}
+
+#[test]
+fn function_and_statements() {
+    let content =
+        b"// header\nfunc void foo(var int a) {\n    a = 3 + b; // trailing\n    return;\n};\n";
+    let ast = parse(content);
+
+    let func = match ast.declarations[0] {
+        Declaration::Func(ref func) => func,
+        _ => panic!("expected a function"),
+    };
+
+    assert_eq!(func.span.file, FileId(7));
+    assert_eq!(
+        text(content, func.span),
+        &b"func void foo(var int a) {\n    a = 3 + b; // trailing\n    return;\n}"[..]
+    );
+    assert_eq!(text(content, func.name.span), b"foo");
+    assert_eq!(text(content, func.params[0].span), b"var int a");
+
+    match func.body[0] {
+        Statement::Ass(ref ass) => {
+            assert_eq!(text(content, ass.span), b"a = 3 + b");
+            assert_eq!(text(content, ass.exp.span()), b"3 + b");
+        }
+        _ => panic!("expected an assignment"),
+    }
+    assert_eq!(text(content, func.body[1].span()), b"return");
+}
+
+#[test]
+fn declarations() {
+    let content = b"const int MAX = 5;\nvar int a, b[MAX];\ninstance x(C_NPC) { name = \"x\"; };";
+    let ast = parse(content);
+
+    let spans: Vec<&[u8]> = ast
+        .declarations
+        .iter()
+        .map(|decl| text(content, decl.span()))
+        .collect();
+
+    assert_eq!(
+        spans,
+        vec![
+            &b"const int MAX = 5"[..],
+            &b"var int a, b[MAX]"[..],
+            &b"instance x(C_NPC) { name = \"x\"; }"[..],
+        ]
+    );
+
+    if let Declaration::Var(ref vars) = ast.declarations[1] {
+        assert_eq!(text(content, vars[0].span), b"var int a");
+        assert_eq!(text(content, vars[1].span), b"b[MAX]");
+    } else {
+        panic!("expected variables");
+    }
+}
@@ -45,7 +45,7 @@ pub fn test_parser_error<'a, O: Debug + PartialEq>(
 pub fn test_expression_value<'a>(
     parser: fn(Input<'a>) -> IResult<Input<'a>, Expression, ParserError>,
     input: &'a [u8],
-    expected: i64,
+    expected: i32,
 ) {
     let expression = parser(Input(input));
 
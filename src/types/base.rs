@@ -1,7 +1,7 @@
 use inner_errors::ParserError;
 use nom::types::CompleteByteSlice;
 use nom::IResult;
-use types::PrintableByteVec;
+use types::{charset, PrintableByteVec, Span};
 
 pub type Input<'a> = CompleteByteSlice<'a>;
 #[allow(non_snake_case)]
@@ -11,38 +11,108 @@ pub fn Input<'a>(input: &'a [u8]) -> Input<'a> {
 
 pub type PResult<'a, O> = IResult<Input<'a>, O, ParserError>;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StringLiteral {
     data: PrintableByteVec,
+    pub span: Span,
 }
 
 impl StringLiteral {
     pub fn new(data: &[u8]) -> Self {
         StringLiteral {
             data: PrintableByteVec(data.to_vec()),
+            span: Span::default(),
         }
     }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data.0
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Identifier {
     name: PrintableByteVec,
+    pub span: Span,
 }
 
 impl Identifier {
     pub fn new(name: &[u8]) -> Self {
         Identifier {
             name: PrintableByteVec(name.to_vec()),
+            span: Span::default(),
         }
     }
 
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
     pub fn as_bytes(&self) -> &[u8] {
         &self.name.0
     }
+
+    /// The name for lookups, under which spellings that only differ in case are the same.
+    /// The identifier itself keeps its original spelling.
+    pub fn key(&self) -> IdentifierKey {
+        IdentifierKey::new(&self.name)
+    }
+
+    /// Whether both name the same symbol, i.e. are equal except for case.
+    pub fn same_name(&self, other: &Identifier) -> bool {
+        charset().eq_ignore_case(&self.name, &other.name)
+    }
+}
+
+/// A name folded to upper case in the charset of the run, like the original compiler does.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct IdentifierKey(Vec<u8>);
+
+impl IdentifierKey {
+    pub fn new(name: &[u8]) -> Self {
+        IdentifierKey(charset().to_uppercase(name))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
 }
 
 impl ::std::fmt::Display for Identifier {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
-        write!(f, "{}", ::std::str::from_utf8(&self.name).unwrap())
+        write!(f, "{}", charset().decode(&self.name))
+    }
+}
+
+impl ::std::fmt::Display for StringLiteral {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> Result<(), ::std::fmt::Error> {
+        write!(f, "{}", charset().decode(&self.data))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_ignore_case() {
+        let upper = Identifier::new(b"MEM_InitAll");
+        let lower = Identifier::new(b"mem_initall");
+        assert_ne!(upper, lower);
+        assert_eq!(upper.key(), lower.key());
+        assert!(upper.same_name(&lower));
+        assert_eq!(upper.key().as_bytes(), b"MEM_INITALL");
+        assert_eq!(upper.to_string(), "MEM_InitAll");
+
+        let umlaut = Identifier::new(b"x\xc4pfel");
+        assert_eq!(umlaut.key(), IdentifierKey::new(b"X\xe4PFEL"));
+        assert_eq!(umlaut.to_string(), "x\u{c4}pfel");
+        assert!(!umlaut.same_name(&Identifier::new(b"xapfel")));
     }
 }
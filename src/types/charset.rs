@@ -0,0 +1,171 @@
+use encoding::all::{UTF_8, WINDOWS_1250, WINDOWS_1251, WINDOWS_1252};
+use encoding::{DecoderTrap, EncoderTrap, EncodingRef};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The encoding scripts are written in. The lexer only looks at bytes, so this decides how names
+/// and strings are shown and which letters are the same except for case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    /// Western European, used by the original game and most mods.
+    Windows1252,
+    /// Central European, e.g. for Polish and Czech mods.
+    Windows1250,
+    /// Cyrillic, for Russian mods.
+    Windows1251,
+    Utf8,
+}
+
+/// Index into `Charset::ALL` of the charset of this run.
+static CHARSET: AtomicUsize = AtomicUsize::new(0);
+
+/// The charset of this run, which is Windows-1252 unless `set_charset` was called.
+pub fn charset() -> Charset {
+    Charset::ALL[CHARSET.load(Ordering::Relaxed)]
+}
+
+/// Sets the charset for the whole process, including worker threads.
+pub fn set_charset(charset: Charset) {
+    let index = Charset::ALL.iter().position(|c| *c == charset).unwrap();
+    CHARSET.store(index, Ordering::Relaxed);
+}
+
+impl Charset {
+    const ALL: [Charset; 4] = [
+        Charset::Windows1252,
+        Charset::Windows1250,
+        Charset::Windows1251,
+        Charset::Utf8,
+    ];
+    pub const VALUES: &'static [&'static str] = &["cp1252", "cp1250", "cp1251", "utf-8"];
+
+    pub fn name(self) -> &'static str {
+        Charset::VALUES[Charset::ALL.iter().position(|c| *c == self).unwrap()]
+    }
+
+    fn encoding(self) -> EncodingRef {
+        match self {
+            Charset::Windows1252 => WINDOWS_1252,
+            Charset::Windows1250 => WINDOWS_1250,
+            Charset::Windows1251 => WINDOWS_1251,
+            Charset::Utf8 => UTF_8,
+        }
+    }
+
+    /// Converts script bytes to text, replacing anything that is invalid in this charset.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        if bytes.is_ascii() {
+            return String::from_utf8_lossy(bytes).into_owned();
+        }
+        self.encoding()
+            .decode(bytes, DecoderTrap::Replace)
+            .unwrap_or_default()
+    }
+
+    /// Converts text, e.g. from an editor, to script bytes.
+    pub fn encode(self, text: &str) -> Vec<u8> {
+        self.encoding()
+            .encode(text, EncoderTrap::Replace)
+            .unwrap_or_default()
+    }
+
+    /// `bytes` in upper case. Letters whose upper case takes several characters or does not
+    /// exist in the charset stay as they are, as does everything if `bytes` is invalid.
+    pub fn to_uppercase(self, bytes: &[u8]) -> Vec<u8> {
+        if bytes.is_ascii() {
+            return bytes.to_ascii_uppercase();
+        }
+        self.map_letters(bytes, char::to_uppercase)
+            .unwrap_or_else(|| bytes.to_ascii_uppercase())
+    }
+
+    /// `bytes` in lower case, like `to_uppercase`.
+    pub fn to_lowercase(self, bytes: &[u8]) -> Vec<u8> {
+        if bytes.is_ascii() {
+            return bytes.to_ascii_lowercase();
+        }
+        self.map_letters(bytes, char::to_lowercase)
+            .unwrap_or_else(|| bytes.to_ascii_lowercase())
+    }
+
+    /// Whether `a` and `b` are the same name, which like in the original compiler ignores case.
+    pub fn eq_ignore_case(self, a: &[u8], b: &[u8]) -> bool {
+        if a.is_ascii() && b.is_ascii() {
+            return a.eq_ignore_ascii_case(b);
+        }
+        self.to_uppercase(a) == self.to_uppercase(b)
+    }
+
+    fn map_letters<F, I>(self, bytes: &[u8], map: F) -> Option<Vec<u8>>
+    where
+        F: Fn(char) -> I,
+        I: Iterator<Item = char>,
+    {
+        let encoding = self.encoding();
+        let text = encoding.decode(bytes, DecoderTrap::Strict).ok()?;
+        let mut mapped = String::with_capacity(text.len());
+        for c in text.chars() {
+            let mut letters = map(c);
+            match (letters.next(), letters.next()) {
+                (Some(letter), None)
+                    if letter.is_ascii()
+                        || encoding
+                            .encode(letter.encode_utf8(&mut [0; 4]), EncoderTrap::Strict)
+                            .is_ok() =>
+                {
+                    mapped.push(letter)
+                }
+                _ => mapped.push(c),
+            }
+        }
+        encoding.encode(&mapped, EncoderTrap::Strict).ok()
+    }
+}
+
+impl FromStr for Charset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Charset::VALUES
+            .iter()
+            .position(|name| name.eq_ignore_ascii_case(s))
+        {
+            Some(index) => Ok(Charset::ALL[index]),
+            None => Err(format!("unknown encoding {}", s)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn case() {
+        let cp1252 = Charset::Windows1252;
+        assert_eq!(cp1252.to_uppercase(b"gr\xfc\xdfe"), b"GR\xdc\xdfE");
+        assert_eq!(cp1252.to_lowercase(b"\xc4PFEL"), b"\xe4pfel");
+        assert!(cp1252.eq_ignore_case(b"\xe4rger", b"\xc4RGER"));
+        assert!(!cp1252.eq_ignore_case(b"\xe4rger", b"ARGER"));
+
+        // The same byte is a different letter in Windows-1251.
+        assert_eq!(Charset::Windows1251.to_lowercase(b"\xc4"), b"\xe4");
+        assert_eq!(Charset::Windows1251.decode(b"\xc4"), "Д");
+        assert_eq!(Charset::Windows1250.decode(b"\xa5"), "Ą");
+        assert_eq!(Charset::Windows1250.to_lowercase(b"\xa5"), b"\xb9");
+
+        let utf8 = Charset::Utf8;
+        assert_eq!(utf8.to_uppercase("äpfel".as_bytes()), "ÄPFEL".as_bytes());
+        assert_eq!(utf8.to_uppercase(b"a\xff"), b"A\xff");
+        assert_eq!(utf8.decode(b"a\xff"), "a\u{fffd}");
+    }
+
+    #[test]
+    fn names() {
+        for name in Charset::VALUES {
+            assert_eq!(name.parse::<Charset>().unwrap().name(), *name);
+        }
+        assert_eq!("UTF-8".parse(), Ok(Charset::Utf8));
+        assert!("latin1".parse::<Charset>().is_err());
+    }
+}
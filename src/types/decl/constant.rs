@@ -1,10 +1,11 @@
-use types::{ArraySizeDeclaration, Expression, Identifier};
+use types::{ArraySizeDeclaration, Expression, Identifier, Span};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConstDeclaration {
-    name: Identifier,
-    typ: Identifier,
-    initializer: Expression,
+    pub name: Identifier,
+    pub typ: Identifier,
+    pub initializer: Expression,
+    pub span: Span,
 }
 
 impl ConstDeclaration {
@@ -13,16 +14,23 @@ impl ConstDeclaration {
             typ,
             name,
             initializer,
+            span: Span::default(),
         }
     }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConstArrayDeclaration {
-    name: Identifier,
-    typ: Identifier,
-    array_size: ArraySizeDeclaration,
-    initializer: ConstArrayInitializer,
+    pub name: Identifier,
+    pub typ: Identifier,
+    pub array_size: ArraySizeDeclaration,
+    pub initializer: ConstArrayInitializer,
+    pub span: Span,
 }
 
 impl ConstArrayDeclaration {
@@ -37,17 +45,32 @@ impl ConstArrayDeclaration {
             name,
             array_size,
             initializer,
+            span: Span::default(),
         }
     }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ConstArrayInitializer {
-    expressions: Vec<Expression>,
+    pub expressions: Vec<Expression>,
+    pub span: Span,
 }
 
 impl ConstArrayInitializer {
     pub fn new(expressions: Vec<Expression>) -> Self {
-        ConstArrayInitializer { expressions }
+        ConstArrayInitializer {
+            expressions,
+            span: Span::default(),
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
     }
 }
@@ -1,8 +1,9 @@
 use types::{
-    Class, ConstArrayDeclaration, ConstDeclaration, Function, Instance, Prototype, VarDeclaration,
+    Class, ConstArrayDeclaration, ConstDeclaration, Function, Instance, Prototype, Span,
+    VarDeclaration,
 };
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Declaration {
     Var(Vec<VarDeclaration>),
     Func(Function),
@@ -12,3 +13,24 @@ pub enum Declaration {
     Const(ConstDeclaration),
     ConstArray(ConstArrayDeclaration),
 }
+
+impl Declaration {
+    pub fn span(&self) -> Span {
+        match *self {
+            Declaration::Var(ref vars) => joined_span(vars.iter().map(|var| var.span)),
+            Declaration::Func(ref func) => func.span,
+            Declaration::Class(ref class) => class.span,
+            Declaration::Inst(ref insts) => joined_span(insts.iter().map(|inst| inst.span)),
+            Declaration::Proto(ref proto) => proto.span,
+            Declaration::Const(ref decl) => decl.span,
+            Declaration::ConstArray(ref decl) => decl.span,
+        }
+    }
+}
+
+pub fn joined_span<I: Iterator<Item = Span>>(mut spans: I) -> Span {
+    match spans.next() {
+        Some(first) => spans.fold(first, |acc, span| acc.to(span)),
+        None => Span::default(),
+    }
+}
@@ -1,9 +1,10 @@
-use types::{Identifier, Statement, VarDeclaration};
+use types::{Identifier, Span, Statement, VarDeclaration};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Function {
     pub name: Identifier,
     pub typ: Identifier,
     pub params: Vec<VarDeclaration>,
     pub body: Vec<Statement>,
+    pub span: Span,
 }
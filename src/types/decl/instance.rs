@@ -1,21 +1,24 @@
-use types::{Identifier, Statement, VarDeclaration};
+use types::{Identifier, Span, Statement, VarDeclaration};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Instance {
     pub name: Identifier,
     pub class: Identifier,
     pub body: Vec<Statement>,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Prototype {
     pub name: Identifier,
     pub class: Identifier,
     pub body: Vec<Statement>,
+    pub span: Span,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Class {
     pub name: Identifier,
     pub members: Vec<VarDeclaration>,
+    pub span: Span,
 }
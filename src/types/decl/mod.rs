@@ -5,7 +5,7 @@ mod instance;
 mod var;
 
 pub use self::constant::{ConstArrayDeclaration, ConstArrayInitializer, ConstDeclaration};
-pub use self::declaration::Declaration;
+pub use self::declaration::{joined_span, Declaration};
 pub use self::func::Function;
 pub use self::instance::{Class, Instance, Prototype};
 pub use self::var::{ArraySizeDeclaration, VarDeclaration};
@@ -1,10 +1,11 @@
-use types::Identifier;
+use types::{Identifier, Span};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct VarDeclaration {
     pub typ: Identifier,
     pub name: Identifier,
     pub array_size: Option<ArraySizeDeclaration>,
+    pub span: Span,
 }
 
 impl VarDeclaration {
@@ -17,12 +18,27 @@ impl VarDeclaration {
             typ,
             name,
             array_size,
+            span: Span::default(),
         }
     }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ArraySizeDeclaration {
     Identifier(Identifier),
-    Size(i64),
+    Size(i64, Span),
+}
+
+impl ArraySizeDeclaration {
+    pub fn span(&self) -> Span {
+        match *self {
+            ArraySizeDeclaration::Identifier(ref ident) => ident.span,
+            ArraySizeDeclaration::Size(_, span) => span,
+        }
+    }
 }
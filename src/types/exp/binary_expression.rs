@@ -1,24 +1,25 @@
-use types::{BinaryOperator, Expression};
+use types::{BinaryOperator, Expression, Span};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct BinaryExpression {
     pub op: BinaryOperator,
-    left: Expression,
-    right: Expression,
+    pub left: Expression,
+    pub right: Expression,
+    pub span: Span,
 }
 
 impl BinaryExpression {
     pub fn new(op: BinaryOperator, left: Expression, right: Expression) -> Self {
-        BinaryExpression { op, left, right }
+        let span = left.span().to(right.span());
+        BinaryExpression {
+            op,
+            left,
+            right,
+            span,
+        }
     }
 
     pub fn is_constant(&self) -> bool {
         self.left.is_constant() && self.right.is_constant()
     }
-
-    pub fn evaluate(&self) -> Result<i64, ()> {
-        let left = self.left.evaluate_int()?;
-        let right = self.right.evaluate_int()?;
-        Ok(self.op.apply(left, right))
-    }
 }
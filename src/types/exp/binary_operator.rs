@@ -1,6 +1,6 @@
 use types::Expression;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BinaryOperator {
     Plus,
     Minus,
@@ -45,7 +45,11 @@ impl BinaryOperator {
     }
 
     pub fn from(v: &[u8]) -> BinaryOperator {
-        match v {
+        BinaryOperator::from_bytes(v).expect("Illegal binary operator")
+    }
+
+    pub fn from_bytes(v: &[u8]) -> Option<BinaryOperator> {
+        Some(match v {
             br"+" => BinaryOperator::Plus,
             br"-" => BinaryOperator::Minus,
             br"*" => BinaryOperator::Multiply,
@@ -63,8 +67,8 @@ impl BinaryOperator {
             br"&" => BinaryOperator::BitAnd,
             br"||" => BinaryOperator::Or,
             br"|" => BinaryOperator::BitOr,
-            _ => panic!("Illegal binary operator"),
-        }
+            _ => return None,
+        })
     }
 
     pub fn get_order(&self) -> usize {
@@ -89,32 +93,39 @@ impl BinaryOperator {
         }
     }
 
-    pub fn apply(&self, left: i64, right: i64) -> i64 {
-        match *self {
-            BinaryOperator::LSL => left << right,
-            BinaryOperator::LSR => left >> right,
+    /// The result of the operator on 32-bit integers, which like in the engine wraps around on
+    /// overflow, or `None` for a division by zero. Shifts only use the lowest five bits of the
+    /// shift amount, like x86 does.
+    pub fn apply(&self, left: i32, right: i32) -> Option<i32> {
+        if right == 0 && matches!(*self, BinaryOperator::Divide | BinaryOperator::Mod) {
+            return None;
+        }
+
+        Some(match *self {
+            BinaryOperator::LSL => left.wrapping_shl(right as u32),
+            BinaryOperator::LSR => left.wrapping_shr(right as u32),
             BinaryOperator::BitAnd => left & right,
             BinaryOperator::BitOr => left | right,
-            BinaryOperator::Multiply => left * right,
-            BinaryOperator::Divide => left / right,
-            BinaryOperator::Mod => left % right,
-            BinaryOperator::Plus => left + right,
-            BinaryOperator::Minus => left - right,
-            BinaryOperator::GT => (left > right) as i64,
-            BinaryOperator::LT => (left < right) as i64,
-            BinaryOperator::GE => (left >= right) as i64,
-            BinaryOperator::LE => (left <= right) as i64,
-            BinaryOperator::Eq => (left == right) as i64,
-            BinaryOperator::NotEq => (left != right) as i64,
-            BinaryOperator::And => (left != 0 && right != 0) as i64,
-            BinaryOperator::Or => (left != 0 || right != 0) as i64,
-        }
+            BinaryOperator::Multiply => left.wrapping_mul(right),
+            BinaryOperator::Divide => left.wrapping_div(right),
+            BinaryOperator::Mod => left.wrapping_rem(right),
+            BinaryOperator::Plus => left.wrapping_add(right),
+            BinaryOperator::Minus => left.wrapping_sub(right),
+            BinaryOperator::GT => (left > right) as i32,
+            BinaryOperator::LT => (left < right) as i32,
+            BinaryOperator::GE => (left >= right) as i32,
+            BinaryOperator::LE => (left <= right) as i32,
+            BinaryOperator::Eq => (left == right) as i32,
+            BinaryOperator::NotEq => (left != right) as i32,
+            BinaryOperator::And => (left != 0 && right != 0) as i32,
+            BinaryOperator::Or => (left != 0 || right != 0) as i32,
+        })
     }
 
     pub fn needs_parentheses(&self, child: &Expression) -> bool {
         match *child {
-            Expression::Float(_) => false,
-            Expression::Int(_) => false,
+            Expression::Float(..) => false,
+            Expression::Int(..) => false,
             Expression::Identifier(_) => false,
             Expression::Call(_) => false,
             Expression::Unary(_) => false,
@@ -1,7 +1,8 @@
-use types::{Expression, Identifier};
+use types::{Expression, Identifier, Span};
 
-#[derive(Clone, PartialEq, Debug)]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Call {
     pub func: Identifier,
     pub params: Vec<Expression>,
+    pub span: Span,
 }
@@ -1,10 +1,12 @@
+use inner_errors::ConstError;
+use ppa::constant::Evaluator;
 use types::base::StringLiteral;
-use types::{BinaryExpression, Call, UnaryExpression, VarAccess};
+use types::{BinaryExpression, Call, Span, UnaryExpression, VarAccess};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Expression {
-    Int(i64),
-    Float(f32),
+    Int(i64, Span),
+    Float(f32, Span),
     Identifier(Box<VarAccess>),
     Binary(Box<BinaryExpression>),
     Unary(Box<UnaryExpression>),
@@ -14,38 +16,31 @@ pub enum Expression {
 
 impl Expression {
     pub fn is_float(&self) -> bool {
-        match *self {
-            Expression::Float(_) => true,
-            _ => false,
-        }
+        matches!(*self, Expression::Float(..))
     }
 
-    pub fn evaluate_int(&self) -> Result<i64, ()> {
+    pub fn span(&self) -> Span {
         match *self {
-            Expression::Int(i) => Ok(i),
-            Expression::Binary(ref b) => (*b).evaluate(),
-            Expression::Unary(ref b) => (*b).evaluate(),
-            _ => Err(()),
+            Expression::Int(_, span) => span,
+            Expression::Float(_, span) => span,
+            Expression::Identifier(ref var) => var.span,
+            Expression::Binary(ref bin) => bin.span,
+            Expression::Unary(ref un) => un.span,
+            Expression::Call(ref call) => call.span,
+            Expression::String(ref string) => string.span,
         }
     }
-    /*
-    pub fn is_constant_int(&self) -> bool {
-        match *self {
-            Expression::Value(_) => true,
-            Expression::Float(_) => false,
-            Expression::Identifier(_) => true,
-            Expression::Binary(_) => true,
-            Expression::Unary(_) => true,
-            Expression::Call(_) => false,
-            Expression::String(_) => false
-        }
+
+    /// The value of an integer expression made of literals and operators only. Use an
+    /// `Evaluator` with a resolution to evaluate references to constants as well.
+    pub fn evaluate_int(&self) -> Result<i32, ConstError> {
+        Evaluator::default().int(self)
     }
-*/
 
     pub fn is_constant(&self) -> bool {
         match *self {
-            Expression::Int(_) => true,
-            Expression::Float(_) => true,
+            Expression::Int(..) => true,
+            Expression::Float(..) => true,
             Expression::Identifier(ref var) => var.is_constant(),
             Expression::Binary(ref bin) => bin.is_constant(),
             Expression::Unary(ref un) => un.is_constant(),
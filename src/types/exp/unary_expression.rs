@@ -1,9 +1,10 @@
-use types::{Expression, UnaryOperator};
+use types::{Expression, Span, UnaryOperator};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct UnaryExpression {
-    op: UnaryOperator,
-    right: Expression,
+    pub op: UnaryOperator,
+    pub right: Expression,
+    pub span: Span,
 }
 
 impl UnaryExpression {
@@ -11,12 +12,13 @@ impl UnaryExpression {
         UnaryExpression {
             op: UnaryOperator::from_ascii(op),
             right,
+            span: Span::default(),
         }
     }
 
-    pub fn evaluate(&self) -> Result<i64, ()> {
-        let right = self.right.evaluate_int()?;
-        Ok(self.op.apply(right))
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
     }
 
     pub fn is_constant(&self) -> bool {
@@ -1,4 +1,4 @@
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum UnaryOperator {
     Plus,
     Minus,
@@ -8,25 +8,32 @@ pub enum UnaryOperator {
 
 impl UnaryOperator {
     pub fn from_ascii(op: u8) -> Self {
-        match op {
-            b'+' => UnaryOperator::Plus,
-            b'-' => UnaryOperator::Minus,
-            b'!' => UnaryOperator::Negate,
-            b'~' => UnaryOperator::Flip,
-            _ => panic!("Constructing UnaryOperator from illegal byte"),
-        }
+        UnaryOperator::from_bytes(&[op]).expect("Constructing UnaryOperator from illegal byte")
+    }
+
+    pub fn from_bytes(op: &[u8]) -> Option<Self> {
+        Some(match op {
+            b"+" => UnaryOperator::Plus,
+            b"-" => UnaryOperator::Minus,
+            b"!" => UnaryOperator::Negate,
+            b"~" => UnaryOperator::Flip,
+            _ => return None,
+        })
     }
 
-    pub fn apply(&self, val: i64) -> i64 {
+    /// The result on 32-bit integers, where negating the smallest value wraps around to itself.
+    pub fn apply(&self, val: i32) -> i32 {
         match *self {
             UnaryOperator::Plus => val,
-            UnaryOperator::Minus => -val,
+            UnaryOperator::Minus => val.wrapping_neg(),
             UnaryOperator::Flip => !val,
-            UnaryOperator::Negate => if val == 0 {
-                1
-            } else {
-                0
-            },
+            UnaryOperator::Negate => {
+                if val == 0 {
+                    1
+                } else {
+                    0
+                }
+            }
         }
     }
 }
@@ -1,11 +1,13 @@
 use types::Expression;
 use types::Identifier;
+use types::Span;
 
-#[derive(Clone, PartialEq)]
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
 pub struct VarAccess {
-    name: Identifier,
-    instance: Option<Identifier>,
-    index: Option<Expression>,
+    pub name: Identifier,
+    pub instance: Option<Identifier>,
+    pub index: Option<Expression>,
+    pub span: Span,
 }
 
 impl VarAccess {
@@ -15,20 +17,27 @@ impl VarAccess {
         index: Option<Expression>,
     ) -> Self {
         // In case there is a second identifier it's an object access (instance.member), so we swap the parameters around.
-        if second_ident.is_some() {
+        if let Some(second_ident) = second_ident {
             VarAccess {
-                name: second_ident.unwrap(),
+                name: second_ident,
                 instance: Some(first_ident),
                 index,
+                span: Span::default(),
             }
         } else {
             VarAccess {
                 name: first_ident,
                 instance: None,
                 index,
+                span: Span::default(),
             }
         }
     }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
 }
 
 impl ::std::fmt::Debug for VarAccess {
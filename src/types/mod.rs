@@ -1,15 +1,21 @@
 mod base;
+mod charset;
 mod decl;
 mod exp;
 mod printable;
+mod span;
 mod statement;
+mod symbol;
+mod typ;
 
 pub use self::printable::{PrintableByteSlice, PrintableByteVec};
 
-pub use self::base::{Identifier, Input, PResult, StringLiteral};
+pub use self::base::{Identifier, IdentifierKey, Input, PResult, StringLiteral};
+pub use self::charset::{charset, set_charset, Charset};
 pub use self::exp::{
     BinaryExpression, BinaryOperator, Call, Expression, UnaryExpression, UnaryOperator, VarAccess,
 };
+pub use self::span::{with_deserialized_file, FileId, Span};
 
 pub use self::decl::{
     ArraySizeDeclaration, Class, ConstArrayDeclaration, ConstArrayInitializer, ConstDeclaration,
@@ -18,7 +24,10 @@ pub use self::decl::{
 
 pub use self::statement::{Assignment, AssignmentOperator, IfBranch, IfStatement, Statement};
 
-#[derive(Debug, Clone, PartialEq)]
+pub use self::symbol::{Symbol, SymbolCollection, SymbolId, SymbolKind};
+pub use self::typ::Type;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct AST {
     pub declarations: Vec<Declaration>,
 }
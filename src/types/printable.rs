@@ -1,4 +1,7 @@
-use encoding::{all::ISO_8859_1, DecoderTrap, Encoding};
+use encoding::{all::ISO_8859_1, DecoderTrap, EncoderTrap, Encoding};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use types::charset;
 
 #[derive(PartialEq, Clone, Hash, Eq)]
 pub struct PrintableByteVec(pub Vec<u8>);
@@ -10,8 +13,26 @@ impl PrintableByteVec {
 }
 impl ::std::fmt::Debug for PrintableByteVec {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        write!(f, "{}", charset().decode(&self.0))
+    }
+}
+
+/// Serialized as a string of the characters with the code points of the bytes, which is
+/// lossless and stays readable for the usual ASCII names.
+impl Serialize for PrintableByteVec {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         let decoded = ISO_8859_1.decode(&self.0, DecoderTrap::Strict).unwrap();
-        write!(f, "{}", decoded)
+        serializer.serialize_str(&decoded)
+    }
+}
+
+impl<'de> Deserialize<'de> for PrintableByteVec {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = String::deserialize(deserializer)?;
+        ISO_8859_1
+            .encode(&text, EncoderTrap::Strict)
+            .map(PrintableByteVec)
+            .map_err(D::Error::custom)
     }
 }
 
@@ -32,8 +53,7 @@ impl<'a> PrintableByteSlice<'a> {
 }
 impl<'a> ::std::fmt::Debug for PrintableByteSlice<'a> {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        let decoded = ISO_8859_1.decode(&self.0, DecoderTrap::Strict).unwrap();
-        write!(f, "{}", decoded)
+        write!(f, "{}", charset().decode(self.0))
     }
 }
 
@@ -0,0 +1,124 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::cell::Cell;
+
+/// Identifies a source file within a single run of Parsiphae.
+///
+/// Ids are handed out by the `SourceMap` in the order files are loaded,
+/// which for a `.src` is the order the files are listed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct FileId(pub usize);
+
+/// A byte range `[start, end)` inside a source file.
+///
+/// Spans are metadata and do not take part in the structural comparison of
+/// AST nodes: two spans always compare equal, so parsing the same code at two
+/// different locations yields equal ASTs. Use `same_location` to compare the
+/// actual positions.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub file: FileId,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(file: FileId, start: usize, end: usize) -> Self {
+        Span { file, start, end }
+    }
+
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    /// Returns the smallest span covering both `self` and `other`.
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            file: self.file,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    pub fn same_location(&self, other: &Span) -> bool {
+        self.file == other.file && self.start == other.start && self.end == other.end
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _other: &Span) -> bool {
+        true
+    }
+}
+
+thread_local! {
+    static DESERIALIZED_FILE: Cell<FileId> = Cell::new(FileId::default());
+}
+
+/// Runs `f` with every span deserialized in it belonging to `file`.
+///
+/// Spans are serialized without their file, so that e.g. a cached AST stays valid when
+/// the file ends up with a different id in the next run.
+pub fn with_deserialized_file<T, F: FnOnce() -> T>(file: FileId, f: F) -> T {
+    let _guard = FileGuard {
+        previous: DESERIALIZED_FILE.with(|current| current.replace(file)),
+    };
+
+    f()
+}
+
+struct FileGuard {
+    previous: FileId,
+}
+
+impl Drop for FileGuard {
+    fn drop(&mut self) {
+        DESERIALIZED_FILE.with(|current| current.set(self.previous));
+    }
+}
+
+impl Serialize for Span {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (self.start, self.end).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Span {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (start, end) = <(usize, usize)>::deserialize(deserializer)?;
+        let file = DESERIALIZED_FILE.with(|file| file.get());
+        Ok(Span { file, start, end })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn join() {
+        let a = Span::new(FileId(1), 4, 8);
+        let b = Span::new(FileId(1), 12, 15);
+
+        let joined = a.to(b);
+
+        assert!(joined.same_location(&Span::new(FileId(1), 4, 15)));
+        assert_eq!(joined.len(), 11);
+    }
+
+    #[test]
+    fn equality_ignores_location() {
+        let a = Span::new(FileId(0), 0, 3);
+        let b = Span::new(FileId(2), 7, 9);
+
+        assert_eq!(a, b);
+        assert!(!a.same_location(&b));
+    }
+}
@@ -1,6 +1,6 @@
-use types::{Expression, VarAccess};
+use types::{Expression, Span, VarAccess};
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum AssignmentOperator {
     PlusEq,
     MinusEq,
@@ -9,22 +9,27 @@ pub enum AssignmentOperator {
     Eq,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Assignment {
     pub var: VarAccess,
     pub op: AssignmentOperator,
     pub exp: Expression,
+    pub span: Span,
 }
 
 impl AssignmentOperator {
     pub fn from(op: &[u8]) -> Self {
-        match op {
+        AssignmentOperator::from_bytes(op).expect("Illegal assignment operator")
+    }
+
+    pub fn from_bytes(op: &[u8]) -> Option<Self> {
+        Some(match op {
             b"+=" => AssignmentOperator::PlusEq,
             b"-=" => AssignmentOperator::MinusEq,
             b"*=" => AssignmentOperator::MultiplyEq,
             b"/=" => AssignmentOperator::DivideEq,
             b"=" => AssignmentOperator::Eq,
-            _ => panic!("Illegal assignment operator"),
-        }
+            _ => return None,
+        })
     }
 }
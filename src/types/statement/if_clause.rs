@@ -1,13 +1,15 @@
-use types::{Expression, Statement};
+use types::{Expression, Span, Statement};
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct IfBranch {
     pub cond: Expression,
     pub body: Vec<Statement>,
+    pub span: Span,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct IfStatement {
     pub branches: Vec<IfBranch>,
     pub else_branch: Option<Vec<Statement>>,
+    pub span: Span,
 }
@@ -1,5 +1,6 @@
 mod assignment;
 mod if_clause;
+#[allow(clippy::module_inception)]
 mod statement;
 
 pub use self::assignment::{Assignment, AssignmentOperator};
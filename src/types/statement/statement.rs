@@ -1,8 +1,10 @@
+use types::decl::joined_span;
 use types::{
-    Assignment, ConstArrayDeclaration, ConstDeclaration, Expression, IfStatement, VarDeclaration,
+    Assignment, ConstArrayDeclaration, ConstDeclaration, Expression, IfStatement, Span,
+    VarDeclaration,
 };
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum Statement {
     Exp(Expression),
     Ass(Assignment),
@@ -10,5 +12,19 @@ pub enum Statement {
     VarDeclarations(Vec<VarDeclaration>),
     ConstDeclaration(ConstDeclaration),
     ConstArrayDeclaration(ConstArrayDeclaration),
-    ReturnStatement(Option<Expression>),
+    ReturnStatement(Option<Expression>, Span),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match *self {
+            Statement::Exp(ref exp) => exp.span(),
+            Statement::Ass(ref ass) => ass.span,
+            Statement::If(ref if_statement) => if_statement.span,
+            Statement::VarDeclarations(ref decls) => joined_span(decls.iter().map(|d| d.span)),
+            Statement::ConstDeclaration(ref decl) => decl.span,
+            Statement::ConstArrayDeclaration(ref decl) => decl.span,
+            Statement::ReturnStatement(_, span) => span,
+        }
+    }
 }
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use types::{Identifier, IdentifierKey, Symbol, SymbolId, SymbolKind, Type};
+
+/// All symbols of a project, looked up case-insensitively like the original compiler does.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolCollection {
+    symbols: Vec<Symbol>,
+    globals: HashMap<IdentifierKey, SymbolId>,
+    scoped: HashMap<(SymbolId, IdentifierKey), SymbolId>,
+    children: HashMap<SymbolId, Vec<SymbolId>>,
+}
+
+impl SymbolCollection {
+    pub fn new() -> Self {
+        SymbolCollection::default()
+    }
+
+    /// Adds `symbol` to its scope. If the name is already taken there, nothing is added
+    /// and the id of the previous declaration is returned as error.
+    pub fn insert(&mut self, symbol: Symbol) -> Result<SymbolId, SymbolId> {
+        let id = SymbolId(self.symbols.len());
+        let name = symbol.name.key();
+
+        match symbol.scope {
+            None => {
+                if let Some(&previous) = self.globals.get(&name) {
+                    return Err(previous);
+                }
+                self.globals.insert(name, id);
+            }
+            Some(scope) => {
+                let name = (scope, name);
+                if let Some(&previous) = self.scoped.get(&name) {
+                    return Err(previous);
+                }
+                self.scoped.insert(name, id);
+                self.children.entry(scope).or_default().push(id);
+            }
+        }
+
+        self.symbols.push(symbol);
+        Ok(id)
+    }
+
+    pub fn get(&self, id: SymbolId) -> &Symbol {
+        &self.symbols[id.0]
+    }
+
+    pub fn lookup_global(&self, name: &[u8]) -> Option<SymbolId> {
+        self.globals.get(&IdentifierKey::new(name)).cloned()
+    }
+
+    /// The global symbol that was registered for the declaration named `name`,
+    /// which is not the case if that declaration was a duplicate.
+    pub fn declaration(&self, name: &Identifier) -> Option<SymbolId> {
+        self.lookup_global(name.as_bytes())
+            .filter(|id| self.get(*id).name.span.same_location(&name.span))
+    }
+
+    pub fn lookup_scoped(&self, scope: SymbolId, name: &[u8]) -> Option<SymbolId> {
+        self.scoped.get(&(scope, IdentifierKey::new(name))).cloned()
+    }
+
+    /// Parameters, locals or members declared in `scope`, in declaration order.
+    pub fn children(&self, scope: SymbolId) -> &[SymbolId] {
+        self.children.get(&scope).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Follows the parents of prototypes to the class that `typ` names, if any.
+    pub fn class_of(&self, typ: &[u8]) -> Option<SymbolId> {
+        let mut id = self.lookup_global(typ)?;
+
+        // Bounded, because a prototype may (erroneously) be derived from itself.
+        for _ in 0..=self.symbols.len() {
+            let symbol = self.get(id);
+            match symbol.kind {
+                SymbolKind::Class => return Some(id),
+                SymbolKind::Prototype => {
+                    id = self.lookup_global(symbol.typ.as_ref()?.as_bytes())?;
+                }
+                _ => return None,
+            }
+        }
+
+        None
+    }
+
+    /// Maps a type name as written in a declaration, like `int` or `C_NPC`, to a type.
+    pub fn declared_type(&self, typ: &Identifier) -> Type {
+        let name = typ.as_bytes();
+        let builtins = [
+            (&b"void"[..], Type::Void),
+            (b"int", Type::Int),
+            (b"float", Type::Float),
+            (b"string", Type::String),
+            (b"func", Type::Func),
+            (b"instance", Type::Instance(None)),
+        ];
+
+        match builtins
+            .iter()
+            .find(|&&(builtin, _)| name.eq_ignore_ascii_case(builtin))
+        {
+            Some((_, typ)) => typ.clone(),
+            None => match self.class_of(name) {
+                Some(class) => Type::Instance(Some(self.get(class).name.clone())),
+                None => Type::Unknown,
+            },
+        }
+    }
+
+    /// The declared type of a symbol; for functions that is their return type,
+    /// for instances and prototypes their class.
+    pub fn type_of(&self, symbol: &Symbol) -> Type {
+        symbol
+            .typ
+            .as_ref()
+            .map(|typ| self.declared_type(typ))
+            .unwrap_or(Type::Unknown)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (SymbolId, &Symbol)> {
+        self.symbols
+            .iter()
+            .enumerate()
+            .map(|(i, symbol)| (SymbolId(i), symbol))
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use types::Identifier;
+
+    fn symbol(name: &str, kind: SymbolKind) -> Symbol {
+        Symbol::new(Identifier::new(name.as_bytes()), kind)
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let mut symbols = SymbolCollection::new();
+        let id = symbols.insert(symbol("Foo", SymbolKind::Func)).unwrap();
+
+        assert_eq!(symbols.lookup_global(b"FOO"), Some(id));
+        assert_eq!(symbols.insert(symbol("fOO", SymbolKind::Var)), Err(id));
+        assert_eq!(symbols.len(), 1);
+
+        // Umlauts are letters as well, in the default Windows-1252.
+        let id = symbols
+            .insert(Symbol::new(Identifier::new(b"gr\xfcn"), SymbolKind::Var))
+            .unwrap();
+        assert_eq!(symbols.lookup_global(b"GR\xdcN"), Some(id));
+    }
+
+    #[test]
+    fn scopes_are_separate() {
+        let mut symbols = SymbolCollection::new();
+        let func = symbols.insert(symbol("foo", SymbolKind::Func)).unwrap();
+        let global = symbols.insert(symbol("x", SymbolKind::Var)).unwrap();
+        let local = symbols
+            .insert(symbol("x", SymbolKind::Var).with_scope(Some(func)))
+            .unwrap();
+
+        assert_eq!(symbols.lookup_global(b"x"), Some(global));
+        assert_eq!(symbols.lookup_scoped(func, b"X"), Some(local));
+        assert_eq!(symbols.children(func), &[local]);
+        assert_eq!(symbols.children(global), &[]);
+    }
+
+    #[test]
+    fn class_of_follows_prototypes() {
+        let mut symbols = SymbolCollection::new();
+        let class = symbols.insert(symbol("C_NPC", SymbolKind::Class)).unwrap();
+        symbols
+            .insert(
+                symbol("Npc_Default", SymbolKind::Prototype).with_type(Identifier::new(b"c_npc")),
+            )
+            .unwrap();
+        symbols
+            .insert(symbol("Loop", SymbolKind::Prototype).with_type(Identifier::new(b"Loop")))
+            .unwrap();
+
+        assert_eq!(symbols.class_of(b"NPC_DEFAULT"), Some(class));
+        assert_eq!(symbols.class_of(b"Loop"), None);
+        assert_eq!(symbols.class_of(b"unknown"), None);
+    }
+}
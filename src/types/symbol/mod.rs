@@ -0,0 +1,82 @@
+mod collection;
+
+pub use self::collection::SymbolCollection;
+
+use types::{ArraySizeDeclaration, Identifier, Span};
+
+/// Index of a symbol within its `SymbolCollection`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(pub usize);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Var,
+    Param,
+    Const,
+    ConstArray,
+    Func,
+    Class,
+    Prototype,
+    Instance,
+}
+
+/// A named entity declared somewhere in the scripts.
+///
+/// Locals, parameters and class members have the function, instance, prototype or class
+/// they were declared in as `scope`; everything else is global.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: Identifier,
+    pub kind: SymbolKind,
+    /// Declared type of variables, constants and functions, or the parent of instances and prototypes.
+    pub typ: Option<Identifier>,
+    pub array_size: Option<ArraySizeDeclaration>,
+    pub scope: Option<SymbolId>,
+    pub span: Span,
+    /// Functions, and their parameters, that the engine implements.
+    pub external: bool,
+}
+
+impl Symbol {
+    pub fn new(name: Identifier, kind: SymbolKind) -> Self {
+        let span = name.span;
+        Symbol {
+            name,
+            kind,
+            typ: None,
+            array_size: None,
+            scope: None,
+            span,
+            external: false,
+        }
+    }
+
+    pub fn with_type(mut self, typ: Identifier) -> Self {
+        self.typ = Some(typ);
+        self
+    }
+
+    pub fn with_array_size(mut self, array_size: Option<ArraySizeDeclaration>) -> Self {
+        self.array_size = array_size;
+        self
+    }
+
+    pub fn with_scope(mut self, scope: Option<SymbolId>) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn with_external(mut self, external: bool) -> Self {
+        self.external = external;
+        self
+    }
+
+    pub fn is_global(&self) -> bool {
+        self.scope.is_none()
+    }
+}
@@ -0,0 +1,69 @@
+use types::Identifier;
+
+/// Type of a Daedalus value. Instances carry the name of their class, if it is known.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Type {
+    Void,
+    Int,
+    Float,
+    String,
+    Func,
+    Instance(Option<Identifier>),
+    /// The type of anything that could not be resolved. It is compatible with every type,
+    /// so that a single undefined name does not cause a cascade of errors.
+    Unknown,
+}
+
+impl Type {
+    /// Whether a value of type `value` can be stored in a variable of this type.
+    /// Instances can always be used as `int`, which yields their symbol index.
+    pub fn accepts(&self, value: &Type) -> bool {
+        use self::Type::*;
+
+        match (self, value) {
+            (&Unknown, _) | (_, &Unknown) => true,
+            (&Int, &Instance(_)) => true,
+            (&Instance(None), &Instance(_)) | (&Instance(_), &Instance(None)) => true,
+            (a, b) => a == b,
+        }
+    }
+
+    /// Whether values of this type can be used in integer arithmetic and conditions.
+    pub fn is_integral(&self) -> bool {
+        Type::Int.accepts(self)
+    }
+}
+
+impl ::std::fmt::Display for Type {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match *self {
+            Type::Void => write!(f, "void"),
+            Type::Int => write!(f, "int"),
+            Type::Float => write!(f, "float"),
+            Type::String => write!(f, "string"),
+            Type::Func => write!(f, "func"),
+            Type::Instance(Some(ref class)) => write!(f, "{}", class),
+            Type::Instance(None) => write!(f, "instance"),
+            Type::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compatibility() {
+        let npc = Type::Instance(Some(Identifier::new(b"C_NPC")));
+        let item = Type::Instance(Some(Identifier::new(b"C_ITEM")));
+
+        assert!(Type::Int.accepts(&npc));
+        assert!(!npc.accepts(&Type::Int));
+        assert!(!npc.accepts(&item));
+        assert!(Type::Instance(None).accepts(&item));
+        assert!(!Type::Float.accepts(&Type::Int));
+        assert!(Type::String.accepts(&Type::Unknown));
+        assert!(!Type::String.is_integral());
+    }
+}